@@ -1,3 +1,5 @@
 fn main() {
+    // the migrations are embedded into the binary
+    println!("cargo:rerun-if-changed=migrations");
     tauri_build::build()
 }
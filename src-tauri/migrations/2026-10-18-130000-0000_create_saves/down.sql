@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE if EXISTS save_backups;
+
+DROP TABLE if EXISTS saves;
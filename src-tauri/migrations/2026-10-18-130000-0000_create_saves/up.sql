@@ -0,0 +1,20 @@
+CREATE TABLE saves (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    path VARCHAR NOT NULL UNIQUE,
+    kind VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    modified_at BIGINT NOT NULL,
+    rom_id INTEGER REFERENCES roms (id),
+    game_id INTEGER REFERENCES games (id)
+);
+
+CREATE TABLE save_backups (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    save_id INTEGER REFERENCES saves (id) NOT NULL,
+    path VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE UNIQUE index save_backup_per_version ON save_backups (save_id, md5);
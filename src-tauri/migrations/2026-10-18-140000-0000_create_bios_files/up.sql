@@ -0,0 +1,12 @@
+CREATE TABLE bios_files (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    console_id INTEGER REFERENCES consoles (id) NOT NULL,
+    name VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL DEFAULT '',
+    md5 VARCHAR NOT NULL DEFAULT '',
+    sha1 VARCHAR NOT NULL DEFAULT '',
+    required BOOLEAN NOT NULL DEFAULT 1
+);
+
+CREATE UNIQUE index bios_file_per_console ON bios_files (console_id, name, md5);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE if EXISTS patched_roms;
+
+DROP TABLE if EXISTS patches;
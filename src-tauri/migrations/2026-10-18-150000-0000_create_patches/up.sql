@@ -0,0 +1,18 @@
+CREATE TABLE patches (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    format VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL
+);
+
+CREATE TABLE patched_roms (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) NOT NULL,
+    patch_id INTEGER REFERENCES patches (id) NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    sha1 VARCHAR NOT NULL
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE if EXISTS rom_tracks;
+
+ALTER TABLE roms DROP COLUMN sha1;
+
+ALTER TABLE roms DROP COLUMN crc;
@@ -0,0 +1,14 @@
+ALTER TABLE roms ADD COLUMN crc VARCHAR NOT NULL DEFAULT '';
+
+ALTER TABLE roms ADD COLUMN sha1 VARCHAR NOT NULL DEFAULT '';
+
+CREATE TABLE rom_tracks (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) NOT NULL,
+    name VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL DEFAULT '',
+    md5 VARCHAR NOT NULL DEFAULT '',
+    sha1 VARCHAR NOT NULL DEFAULT '',
+    UNIQUE (rom_id, name)
+);
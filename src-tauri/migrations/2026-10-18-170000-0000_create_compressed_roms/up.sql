@@ -0,0 +1,12 @@
+CREATE TABLE compressed_roms (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    format VARCHAR NOT NULL,
+    block_size INTEGER NOT NULL,
+    compressed_size BIGINT NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    sha1 VARCHAR NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE games ADD COLUMN serial VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1 @@
+ALTER TABLE roms ADD COLUMN category VARCHAR NOT NULL DEFAULT '';
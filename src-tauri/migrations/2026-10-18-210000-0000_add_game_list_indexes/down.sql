@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX roms_by_game;
+DROP INDEX games_by_console_title;
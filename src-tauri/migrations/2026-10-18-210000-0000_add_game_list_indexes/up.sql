@@ -0,0 +1,2 @@
+CREATE INDEX games_by_console_title ON games (console_id, title COLLATE NOCASE);
+CREATE INDEX roms_by_game ON roms (game_id);
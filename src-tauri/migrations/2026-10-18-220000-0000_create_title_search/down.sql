@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER roms_search_update;
+DROP TRIGGER roms_search_delete;
+DROP TRIGGER roms_search_insert;
+DROP TRIGGER games_search_update;
+DROP TRIGGER games_search_delete;
+DROP TRIGGER games_search_insert;
+DROP TABLE roms_search;
+DROP TABLE games_search;
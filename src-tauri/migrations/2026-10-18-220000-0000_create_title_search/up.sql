@@ -0,0 +1,42 @@
+CREATE VIRTUAL TABLE games_search USING fts5 (
+    title,
+    content = 'games',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE roms_search USING fts5 (
+    title,
+    content = 'roms',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO games_search (games_search) VALUES ('rebuild');
+INSERT INTO roms_search (roms_search) VALUES ('rebuild');
+
+CREATE TRIGGER games_search_insert AFTER INSERT ON games BEGIN
+    INSERT INTO games_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER games_search_delete AFTER DELETE ON games BEGIN
+    INSERT INTO games_search (games_search, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER games_search_update AFTER UPDATE OF title ON games BEGIN
+    INSERT INTO games_search (games_search, rowid, title) VALUES ('delete', old.id, old.title);
+    INSERT INTO games_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER roms_search_insert AFTER INSERT ON roms BEGIN
+    INSERT INTO roms_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER roms_search_delete AFTER DELETE ON roms BEGIN
+    INSERT INTO roms_search (roms_search, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER roms_search_update AFTER UPDATE OF title ON roms BEGIN
+    INSERT INTO roms_search (roms_search, rowid, title) VALUES ('delete', old.id, old.title);
+    INSERT INTO roms_search (rowid, title) VALUES (new.id, new.title);
+END;
@@ -0,0 +1,208 @@
+PRAGMA defer_foreign_keys = ON;
+
+CREATE TABLE new_games (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    title VARCHAR NOT NULL,
+    console_id INTEGER REFERENCES consoles (id) ON DELETE CASCADE NOT NULL DEFAULT 0,
+    serial VARCHAR NOT NULL DEFAULT '',
+    clone_of VARCHAR
+);
+
+INSERT INTO new_games (id, title, console_id, serial, clone_of)
+SELECT id, title, console_id, serial, clone_of FROM games;
+
+DROP TABLE games;
+
+ALTER TABLE new_games RENAME TO games;
+
+CREATE UNIQUE INDEX game_title_per_console ON games (title, console_id);
+
+CREATE INDEX games_by_console_title ON games (console_id, title COLLATE NOCASE);
+
+CREATE TRIGGER games_search_insert AFTER INSERT ON games BEGIN
+    INSERT INTO games_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER games_search_delete AFTER DELETE ON games BEGIN
+    INSERT INTO games_search (games_search, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER games_search_update AFTER UPDATE OF title ON games BEGIN
+    INSERT INTO games_search (games_search, rowid, title) VALUES ('delete', old.id, old.title);
+    INSERT INTO games_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TABLE new_roms (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    title VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    game_id INTEGER REFERENCES games (id) ON DELETE CASCADE NOT NULL,
+    disc INTEGER,
+    crc VARCHAR NOT NULL DEFAULT '',
+    sha1 VARCHAR NOT NULL DEFAULT '',
+    category VARCHAR NOT NULL DEFAULT ''
+);
+
+INSERT INTO new_roms (id, title, md5, size, game_id, disc, crc, sha1, category)
+SELECT id, title, md5, size, game_id, disc, crc, sha1, category FROM roms;
+
+DROP TABLE roms;
+
+ALTER TABLE new_roms RENAME TO roms;
+
+CREATE UNIQUE INDEX rom_title_per_game ON roms (title, game_id);
+
+CREATE INDEX roms_by_game ON roms (game_id);
+
+CREATE TRIGGER roms_search_insert AFTER INSERT ON roms BEGIN
+    INSERT INTO roms_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER roms_search_delete AFTER DELETE ON roms BEGIN
+    INSERT INTO roms_search (roms_search, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER roms_search_update AFTER UPDATE OF title ON roms BEGIN
+    INSERT INTO roms_search (roms_search, rowid, title) VALUES ('delete', old.id, old.title);
+    INSERT INTO roms_search (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TABLE new_rom_regions (
+    rom_id INTEGER REFERENCES roms (id) ON DELETE CASCADE NOT NULL,
+    region_id INTEGER REFERENCES regions (id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (rom_id, region_id)
+);
+
+INSERT INTO new_rom_regions (rom_id, region_id)
+SELECT rom_id, region_id FROM rom_regions;
+
+DROP TABLE rom_regions;
+
+ALTER TABLE new_rom_regions RENAME TO rom_regions;
+
+CREATE TABLE new_saves (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    path VARCHAR NOT NULL UNIQUE,
+    kind VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    modified_at BIGINT NOT NULL,
+    rom_id INTEGER REFERENCES roms (id) ON DELETE SET NULL,
+    game_id INTEGER REFERENCES games (id) ON DELETE SET NULL
+);
+
+INSERT INTO new_saves (id, path, kind, md5, size, modified_at, rom_id, game_id)
+SELECT id, path, kind, md5, size, modified_at, rom_id, game_id FROM saves;
+
+DROP TABLE saves;
+
+ALTER TABLE new_saves RENAME TO saves;
+
+CREATE TABLE new_save_backups (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    save_id INTEGER REFERENCES saves (id) ON DELETE CASCADE NOT NULL,
+    path VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+INSERT INTO new_save_backups (id, save_id, path, md5, created_at)
+SELECT id, save_id, path, md5, created_at FROM save_backups;
+
+DROP TABLE save_backups;
+
+ALTER TABLE new_save_backups RENAME TO save_backups;
+
+CREATE UNIQUE INDEX save_backup_per_version ON save_backups (save_id, md5);
+
+CREATE TABLE new_bios_files (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    console_id INTEGER REFERENCES consoles (id) ON DELETE CASCADE NOT NULL,
+    name VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL DEFAULT '',
+    md5 VARCHAR NOT NULL DEFAULT '',
+    sha1 VARCHAR NOT NULL DEFAULT '',
+    required BOOLEAN NOT NULL DEFAULT 1
+);
+
+INSERT INTO new_bios_files (id, console_id, name, size, crc, md5, sha1, required)
+SELECT id, console_id, name, size, crc, md5, sha1, required FROM bios_files;
+
+DROP TABLE bios_files;
+
+ALTER TABLE new_bios_files RENAME TO bios_files;
+
+CREATE UNIQUE INDEX bios_file_per_console ON bios_files (console_id, name, md5);
+
+CREATE TABLE new_patches (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) ON DELETE CASCADE NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    format VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL
+);
+
+INSERT INTO new_patches (id, rom_id, path, format, md5)
+SELECT id, rom_id, path, format, md5 FROM patches;
+
+DROP TABLE patches;
+
+ALTER TABLE new_patches RENAME TO patches;
+
+CREATE TABLE new_patched_roms (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) ON DELETE CASCADE NOT NULL,
+    patch_id INTEGER REFERENCES patches (id) ON DELETE CASCADE NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    sha1 VARCHAR NOT NULL
+);
+
+INSERT INTO new_patched_roms (id, rom_id, patch_id, path, size, crc, md5, sha1)
+SELECT id, rom_id, patch_id, path, size, crc, md5, sha1 FROM patched_roms;
+
+DROP TABLE patched_roms;
+
+ALTER TABLE new_patched_roms RENAME TO patched_roms;
+
+CREATE TABLE new_rom_tracks (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) ON DELETE CASCADE NOT NULL,
+    name VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL DEFAULT '',
+    md5 VARCHAR NOT NULL DEFAULT '',
+    sha1 VARCHAR NOT NULL DEFAULT '',
+    UNIQUE (rom_id, name)
+);
+
+INSERT INTO new_rom_tracks (id, rom_id, name, size, crc, md5, sha1)
+SELECT id, rom_id, name, size, crc, md5, sha1 FROM rom_tracks;
+
+DROP TABLE rom_tracks;
+
+ALTER TABLE new_rom_tracks RENAME TO rom_tracks;
+
+CREATE TABLE new_compressed_roms (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    rom_id INTEGER REFERENCES roms (id) ON DELETE CASCADE NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    format VARCHAR NOT NULL,
+    block_size INTEGER NOT NULL,
+    compressed_size BIGINT NOT NULL,
+    size BIGINT NOT NULL,
+    crc VARCHAR NOT NULL,
+    md5 VARCHAR NOT NULL,
+    sha1 VARCHAR NOT NULL
+);
+
+INSERT INTO new_compressed_roms (id, rom_id, path, format, block_size, compressed_size, size, crc, md5, sha1)
+SELECT id, rom_id, path, format, block_size, compressed_size, size, crc, md5, sha1 FROM compressed_roms;
+
+DROP TABLE compressed_roms;
+
+ALTER TABLE new_compressed_roms RENAME TO compressed_roms;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE roms DROP COLUMN removed;
+ALTER TABLE games DROP COLUMN removed;
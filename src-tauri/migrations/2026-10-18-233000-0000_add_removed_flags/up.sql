@@ -0,0 +1,3 @@
+-- games and roms still referenced by scanned files are only flagged when removed
+ALTER TABLE games ADD COLUMN removed BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE roms ADD COLUMN removed BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE game_details;
+DROP TABLE game_releases;
+DROP TABLE game_genres;
+DROP TABLE game_publishers;
+DROP TABLE game_developers;
+DROP TABLE genres;
+DROP TABLE publishers;
+DROP INDEX developer_name;
@@ -0,0 +1,43 @@
+CREATE UNIQUE INDEX developer_name ON developers (name);
+
+CREATE TABLE publishers (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE genres (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR NOT NULL UNIQUE
+);
+
+CREATE TABLE game_developers (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    developer_id INTEGER NOT NULL REFERENCES developers (id) ON DELETE CASCADE,
+    PRIMARY KEY (game_id, developer_id)
+);
+
+CREATE TABLE game_publishers (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    publisher_id INTEGER NOT NULL REFERENCES publishers (id) ON DELETE CASCADE,
+    PRIMARY KEY (game_id, publisher_id)
+);
+
+CREATE TABLE game_genres (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    genre_id INTEGER NOT NULL REFERENCES genres (id) ON DELETE CASCADE,
+    PRIMARY KEY (game_id, genre_id)
+);
+
+-- region as named in rom names, e.g. `Europe`, empty if the source doesn't tell
+CREATE TABLE game_releases (
+    game_id INTEGER NOT NULL REFERENCES games (id) ON DELETE CASCADE,
+    region VARCHAR NOT NULL DEFAULT '',
+    date VARCHAR NOT NULL,
+    PRIMARY KEY (game_id, region)
+);
+
+CREATE TABLE game_details (
+    game_id INTEGER NOT NULL PRIMARY KEY REFERENCES games (id) ON DELETE CASCADE,
+    description VARCHAR,
+    players INTEGER
+);
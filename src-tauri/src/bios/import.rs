@@ -0,0 +1,245 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+use winnow::{
+    combinator::{delimited, preceded, repeat},
+    token::take_until,
+    Parser, Result,
+};
+
+use crate::{
+    dat_parser::{
+        clrmamepro::{self, CmpValue},
+        parser::rom_parser,
+    },
+    models::{Console, NewBiosFile},
+    routes::bios_routes,
+};
+
+#[derive(Debug, Default, Serialize)]
+pub struct BiosImportSummary {
+    pub imported: usize,
+    /// systems of the DAT no console in the database matched
+    pub skipped_systems: Vec<String>,
+}
+
+/// Finds the console of a libretro style system name like `Sony - PlayStation 2` or
+/// `Sega - Mega-CD - Sega CD`, as consoles are stored with and without the manufacturer
+pub fn console_for_system<'a>(system: &str, consoles: &'a [Console]) -> Option<&'a Console> {
+    let system = system
+        .split_once(" (")
+        .map(|(system, _)| system)
+        .unwrap_or(system)
+        .trim();
+    let parts: Vec<&str> = system.split(" - ").collect();
+    let manufacturer = parts[0];
+
+    let mut candidates = vec![system.to_string(), parts.join(" ")];
+    for part in &parts[1..] {
+        candidates.push(part.to_string());
+        candidates.push(format!("{} {}", manufacturer, part));
+    }
+
+    candidates.iter().find_map(|candidate| {
+        consoles
+            .iter()
+            .find(|console| console.name.eq_ignore_ascii_case(candidate))
+    })
+}
+
+fn new_bios_file(console_id: i32, rom: impl Fn(&str) -> Option<String>) -> Option<NewBiosFile> {
+    Some(NewBiosFile {
+        console_id,
+        name: rom("name")?,
+        size: rom("size").and_then(|size| size.parse().ok()).unwrap_or(0),
+        crc: rom("crc").unwrap_or_default().to_lowercase(),
+        md5: rom("md5").unwrap_or_default().to_lowercase(),
+        sha1: rom("sha1").unwrap_or_default().to_lowercase(),
+        required: rom("optional").is_none_or(|optional| optional != "yes"),
+    })
+}
+
+/// Reads libretro's System.dat, where every `game` is a system and its `rom`s are the
+/// files expected in RetroArch's system folder
+pub fn parse_system_dat(
+    dat: &str,
+    consoles: &[Console],
+) -> io::Result<(Vec<NewBiosFile>, Vec<String>)> {
+    let entries = clrmamepro::parse(dat)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (_, game) in entries.iter().filter(|(key, _)| key == "game") {
+        let Some(system) = game.text("name") else {
+            continue;
+        };
+        let Some(console) = console_for_system(system, consoles) else {
+            skipped.push(system.to_string());
+            continue;
+        };
+
+        files.extend(game.blocks("rom").filter_map(|rom: &CmpValue| {
+            new_bios_file(console.id, |key| rom.text(key).map(str::to_string))
+        }));
+    }
+
+    Ok((files, skipped))
+}
+
+fn header_name<'s>(input: &mut &'s str) -> Result<&'s str> {
+    preceded(
+        (take_until(0.., "<header>"), take_until(0.., "<name>")),
+        delimited("<name>", take_until(0.., "</name>"), "</name>"),
+    )
+    .parse_next(input)
+}
+
+/// Reads a Logiqx XML BIOS DAT, all its roms belong to the console named in the header
+pub fn parse_logiqx_dat(
+    dat: &str,
+    consoles: &[Console],
+) -> io::Result<(Vec<NewBiosFile>, Vec<String>)> {
+    let input = &mut &dat[..];
+    let system = header_name(input)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "DAT header has no name"))?;
+
+    let Some(console) = console_for_system(system, consoles) else {
+        return Ok((Vec::new(), vec![system.to_string()]));
+    };
+
+    let roms: Vec<HashMap<&str, &str>> = repeat(0.., rom_parser)
+        .parse_next(input)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid rom entry in DAT"))?;
+
+    let files = roms
+        .iter()
+        .filter_map(|rom| {
+            new_bios_file(console.id, |key| {
+                rom.get(key).map(|value| value.to_string())
+            })
+        })
+        .collect();
+
+    Ok((files, Vec::new()))
+}
+
+/// Seeds the BIOS catalogue from libretro's System.dat or a Logiqx BIOS DAT
+pub fn import_dat(
+    conn: &mut SqliteConnection,
+    path: &Path,
+    consoles: &[Console],
+) -> io::Result<BiosImportSummary> {
+    let dat = fs::read_to_string(path)?;
+
+    let (files, skipped_systems) = if dat.trim_start().starts_with('<') {
+        parse_logiqx_dat(&dat, consoles)?
+    } else {
+        parse_system_dat(&dat, consoles)?
+    };
+
+    let imported = if files.is_empty() {
+        0
+    } else {
+        bios_routes::upsert_bios_files(conn, files)
+            .map_err(io::Error::other)?
+            .len()
+    };
+
+    Ok(BiosImportSummary {
+        imported,
+        skipped_systems,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consoles() -> Vec<Console> {
+        [(131, "PlayStation"), (136, "Sega Saturn"), (140, "Sega CD")]
+            .into_iter()
+            .map(|(id, name)| Console {
+                id,
+                name: name.to_string(),
+                abbreviation: String::new(),
+                manufacturer: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_console_for_system() {
+        let consoles = consoles();
+        let id = |system| console_for_system(system, &consoles).map(|console| console.id);
+
+        assert_eq!(Some(131), id("Sony - PlayStation"));
+        assert_eq!(Some(136), id("Sega - Saturn"));
+        assert_eq!(Some(140), id("Sega - Mega-CD - Sega CD"));
+        assert_eq!(Some(131), id("Sony - PlayStation (BIOS Images)"));
+        assert_eq!(None, id("Sony - PlayStation 2"));
+    }
+
+    #[test]
+    fn test_parse_system_dat() {
+        let dat = r#"clrmamepro ( name "System" )
+game (
+	name "Sony - PlayStation"
+	rom ( name scph5501.bin size 524288 crc 8D8CB7E4 md5 490f666e1afb15b7362b406ed1cea246 )
+)
+game (
+	name "Sony - PlayStation 2"
+	rom ( name SCPH-70004_BIOS_V12_EUR_200.BIN size 4194304 md5 d333558cc14561c1fdc334c75d5f37b7 )
+)
+"#;
+
+        let (files, skipped) = parse_system_dat(dat, &consoles()).unwrap();
+
+        assert_eq!(vec!["Sony - PlayStation 2".to_string()], skipped);
+        assert_eq!(
+            vec![NewBiosFile {
+                console_id: 131,
+                name: "scph5501.bin".to_string(),
+                size: 524288,
+                crc: "8d8cb7e4".to_string(),
+                md5: "490f666e1afb15b7362b406ed1cea246".to_string(),
+                sha1: String::new(),
+                required: true,
+            }],
+            files
+        );
+    }
+
+    #[test]
+    fn test_parse_logiqx_dat() {
+        let dat = r#"<?xml version="1.0"?>
+<datafile>
+	<header>
+		<name>Sega - Saturn (BIOS Images)</name>
+	</header>
+	<game name="Sega Saturn BIOS (Japan)">
+		<rom name="sega_101.bin" size="524288" crc="224b752c" md5="85ec9ca47d8f6807718151cbcca8b964"/>
+		<rom name="saturn_bios.bin" size="524288" md5="af5828fdff51384f99b3c4926be27762" optional="yes"/>
+	</game>
+</datafile>
+"#;
+
+        let (files, skipped) = parse_logiqx_dat(dat, &consoles()).unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(2, files.len());
+        assert_eq!(
+            (136, "sega_101.bin", true),
+            (
+                files[0].console_id,
+                files[0].name.as_str(),
+                files[0].required
+            )
+        );
+        assert_eq!(
+            ("saturn_bios.bin", false),
+            (files[1].name.as_str(), files[1].required)
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::BiosConfig,
+    file_utils::walk_files,
+    hashing::{hash_file, FileHashes},
+    models::{BiosFile, Console},
+    routes::bios_routes,
+};
+
+pub mod import;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BiosStatus {
+    Ok,
+    Missing,
+    WrongHash,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BiosFileReport {
+    #[serde(flatten)]
+    pub file: BiosFile,
+    pub status: BiosStatus,
+    /// a file with the right hashes somewhere else in the system folder, e.g. under a wrong name
+    pub found_at: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsoleBiosReport {
+    pub console: Console,
+    /// all required files are in place, in one of their accepted dumps
+    pub complete: bool,
+    pub files: Vec<BiosFileReport>,
+}
+
+/// Folder layouts emulators expect the BIOS files in
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BiosLayout {
+    /// RetroArch's `system/` folder
+    RetroArch,
+    /// Batocera's `bios/` share
+    Batocera,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BiosExportSummary {
+    pub copied: usize,
+    /// files of the catalogue no good copy was found for
+    pub missing: Vec<String>,
+}
+
+impl BiosLayout {
+    pub fn directory(&self, root: &Path) -> PathBuf {
+        match self {
+            BiosLayout::RetroArch => root.join("system"),
+            BiosLayout::Batocera => root.join("bios"),
+        }
+    }
+}
+
+/// Hashes every file in the system folder once, so misplaced BIOS files can be found by content
+struct SystemFolderIndex {
+    files: Vec<(PathBuf, FileHashes)>,
+}
+
+impl SystemFolderIndex {
+    fn new(system_dir: &Path) -> io::Result<Self> {
+        let files = walk_files(system_dir)?
+            .into_iter()
+            .map(|path| hash_file(&path).map(|hashes| (path, hashes)))
+            .collect::<io::Result<_>>()?;
+
+        Ok(SystemFolderIndex { files })
+    }
+
+    fn find(&self, file: &BiosFile) -> Option<&Path> {
+        self.files
+            .iter()
+            .find(|(_, hashes)| file.matches(hashes))
+            .map(|(path, _)| path.as_path())
+    }
+}
+
+fn verify_file(
+    file: BiosFile,
+    system_dir: &Path,
+    hashes: &HashMap<PathBuf, FileHashes>,
+    index: &SystemFolderIndex,
+) -> BiosFileReport {
+    let path = system_dir.join(&file.name);
+
+    let status = match hashes.get(&path) {
+        Some(hashes) if file.matches(hashes) => BiosStatus::Ok,
+        Some(_) => BiosStatus::WrongHash,
+        None => BiosStatus::Missing,
+    };
+    let found_at = (status != BiosStatus::Ok)
+        .then(|| index.find(&file).map(Path::to_path_buf))
+        .flatten();
+
+    BiosFileReport {
+        file,
+        status,
+        found_at,
+    }
+}
+
+/// Names with at least one good file in place. The catalogue lists a name once for each
+/// dump it accepts, so any of them satisfies it.
+fn satisfied_names(files: &[BiosFileReport]) -> HashSet<&str> {
+    files
+        .iter()
+        .filter(|report| report.status == BiosStatus::Ok)
+        .map(|report| report.file.name.as_str())
+        .collect()
+}
+
+fn console_report(console: Console, files: Vec<BiosFileReport>) -> ConsoleBiosReport {
+    let satisfied = satisfied_names(&files);
+    let complete = files
+        .iter()
+        .all(|report| !report.file.required || satisfied.contains(report.file.name.as_str()));
+
+    ConsoleBiosReport {
+        console,
+        complete,
+        files,
+    }
+}
+
+/// Checks every catalogued BIOS file against the configured system folder
+pub fn verify(
+    conn: &mut SqliteConnection,
+    config: &BiosConfig,
+) -> io::Result<Vec<ConsoleBiosReport>> {
+    let system_dir = config.system_dir()?;
+    let index = if system_dir.is_dir() {
+        SystemFolderIndex::new(&system_dir)?
+    } else {
+        SystemFolderIndex { files: Vec::new() }
+    };
+    let hashes: HashMap<PathBuf, FileHashes> = index.files.iter().cloned().collect();
+
+    let consoles = bios_routes::get_consoles_with_bios_files(conn).map_err(io::Error::other)?;
+
+    Ok(consoles
+        .into_iter()
+        .map(|(console, files)| {
+            let files: Vec<BiosFileReport> = files
+                .into_iter()
+                .map(|file| verify_file(file, &system_dir, &hashes, &index))
+                .collect();
+
+            console_report(console, files)
+        })
+        .collect())
+}
+
+/// Copies every verified BIOS file, or a misplaced copy with the right hashes, into the
+/// layout's folder below `root`. A name is copied once, from the first of its dumps available.
+pub fn export(
+    reports: &[ConsoleBiosReport],
+    root: &Path,
+    layout: BiosLayout,
+    system_dir: &Path,
+) -> io::Result<BiosExportSummary> {
+    let directory = layout.directory(root);
+    let mut summary = BiosExportSummary::default();
+
+    for console in reports {
+        let satisfied = satisfied_names(&console.files);
+        let mut copied: HashSet<&str> = HashSet::new();
+        let mut missing: Vec<&str> = Vec::new();
+
+        for report in &console.files {
+            let name = report.file.name.as_str();
+            if copied.contains(name) {
+                continue;
+            }
+            let source = match (report.status, &report.found_at) {
+                (BiosStatus::Ok, _) => system_dir.join(name),
+                // another dump of the name is in place
+                _ if satisfied.contains(name) => continue,
+                (_, Some(found_at)) => found_at.clone(),
+                _ => {
+                    if !missing.contains(&name) {
+                        missing.push(name);
+                    }
+                    continue;
+                }
+            };
+
+            let destination = directory.join(name);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(source, destination)?;
+            copied.insert(name);
+            summary.copied += 1;
+        }
+
+        summary.missing.extend(
+            missing
+                .into_iter()
+                .filter(|name| !copied.contains(name))
+                .map(String::from),
+        );
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hashing::hash_reader;
+
+    use super::*;
+
+    fn bios_file(name: &str, data: &[u8]) -> BiosFile {
+        let hashes = hash_reader(&mut &data[..]).unwrap();
+
+        BiosFile {
+            id: 1,
+            console_id: 1,
+            name: name.to_string(),
+            size: hashes.size as i64,
+            crc: hashes.crc32,
+            md5: hashes.md5,
+            sha1: String::new(),
+            required: true,
+        }
+    }
+
+    #[test]
+    fn test_verify_and_export() {
+        let dir = std::env::temp_dir().join("romana_bios_verify");
+        let _ = fs::remove_dir_all(&dir);
+        let system_dir = dir.join("system");
+        fs::create_dir_all(system_dir.join("dc")).unwrap();
+
+        fs::write(system_dir.join("scph5501.bin"), b"psx bios").unwrap();
+        fs::write(system_dir.join("dc_boot.bin"), b"dc bios").unwrap();
+        fs::write(system_dir.join("sega_101.bin"), b"bad dump").unwrap();
+
+        let index = SystemFolderIndex::new(&system_dir).unwrap();
+        let hashes: HashMap<PathBuf, FileHashes> = index.files.iter().cloned().collect();
+        let reports: Vec<BiosFileReport> = [
+            bios_file("scph5501.bin", b"psx bios"),
+            bios_file("dc/dc_boot.bin", b"dc bios"),
+            bios_file("sega_101.bin", b"saturn bios"),
+        ]
+        .into_iter()
+        .map(|file| verify_file(file, &system_dir, &hashes, &index))
+        .collect();
+
+        assert_eq!(BiosStatus::Ok, reports[0].status);
+        assert_eq!(BiosStatus::Missing, reports[1].status);
+        assert_eq!(Some(system_dir.join("dc_boot.bin")), reports[1].found_at);
+        assert_eq!(BiosStatus::WrongHash, reports[2].status);
+        assert_eq!(None, reports[2].found_at);
+
+        let console = ConsoleBiosReport {
+            console: Console {
+                id: 1,
+                name: String::new(),
+                abbreviation: String::new(),
+                manufacturer: String::new(),
+            },
+            complete: false,
+            files: reports,
+        };
+        let summary = export(&[console], &dir, BiosLayout::Batocera, &system_dir).unwrap();
+
+        assert_eq!(2, summary.copied);
+        assert_eq!(vec!["sega_101.bin".to_string()], summary.missing);
+        assert_eq!(
+            b"dc bios".to_vec(),
+            fs::read(dir.join("bios/dc/dc_boot.bin")).unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_accepted_dumps() {
+        let dir = std::env::temp_dir().join("romana_bios_dumps");
+        let _ = fs::remove_dir_all(&dir);
+        let system_dir = dir.join("system");
+        fs::create_dir_all(&system_dir).unwrap();
+        fs::write(system_dir.join("scph5501.bin"), b"psx bios v3.0").unwrap();
+
+        let index = SystemFolderIndex::new(&system_dir).unwrap();
+        let hashes: HashMap<PathBuf, FileHashes> = index.files.iter().cloned().collect();
+        let files = [
+            bios_file("scph5501.bin", b"psx bios v2.2"),
+            bios_file("scph5501.bin", b"psx bios v3.0"),
+        ]
+        .into_iter()
+        .map(|file| verify_file(file, &system_dir, &hashes, &index))
+        .collect();
+        let console = Console {
+            id: 1,
+            name: String::new(),
+            abbreviation: String::new(),
+            manufacturer: String::new(),
+        };
+        let report = console_report(console, files);
+
+        // one of the accepted dumps is enough
+        assert_eq!(BiosStatus::WrongHash, report.files[0].status);
+        assert!(report.complete);
+
+        let summary = export(&[report], &dir, BiosLayout::Batocera, &system_dir).unwrap();
+        assert_eq!(1, summary.copied);
+        assert!(summary.missing.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entry_without_hashes() {
+        let dir = std::env::temp_dir().join("romana_bios_unhashed");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("other.bin"), b"other bios").unwrap();
+
+        let index = SystemFolderIndex::new(&dir).unwrap();
+        let hashes: HashMap<PathBuf, FileHashes> = index.files.iter().cloned().collect();
+        let file = BiosFile {
+            size: 0,
+            crc: String::new(),
+            md5: String::new(),
+            ..bios_file("bios.bin", b"")
+        };
+        let report = verify_file(file, &dir, &hashes, &index);
+
+        // nothing is known to compare, so no file is taken for it
+        assert_eq!(BiosStatus::Missing, report.status);
+        assert_eq!(None, report.found_at);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
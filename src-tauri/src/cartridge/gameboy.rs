@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use super::ascii;
+
+/// The start of the Nintendo logo every cartridge carries, which the boot rom checks
+const LOGO_START: [u8; 4] = [0xce, 0xed, 0x66, 0x66];
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CgbSupport {
+    /// original Game Boy game
+    None,
+    /// runs on both, with colors on the Game Boy Color
+    Enhanced,
+    Only,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GameBoyHeader {
+    pub title: String,
+    pub cgb: CgbSupport,
+    pub sgb: bool,
+    pub cartridge_type: u8,
+    pub japanese: bool,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub computed_header_checksum: u8,
+    /// the boot rom refuses to start cartridges with a wrong header checksum
+    pub header_checksum_valid: bool,
+    pub global_checksum: u16,
+    pub computed_global_checksum: u16,
+    /// not checked by the hardware, but a wrong one points to a bad dump or a hack
+    pub global_checksum_valid: bool,
+}
+
+pub fn parse(data: &[u8]) -> Option<GameBoyHeader> {
+    if data.get(0x104..0x108)? != LOGO_START || data.len() < 0x150 {
+        return None;
+    }
+
+    let cgb = match data[0x143] {
+        0xc0 => CgbSupport::Only,
+        0x80 => CgbSupport::Enhanced,
+        _ => CgbSupport::None,
+    };
+    // the last title byte became the CGB flag
+    let title_end = if cgb == CgbSupport::None {
+        0x144
+    } else {
+        0x143
+    };
+
+    let computed_header_checksum = data[0x134..0x14d].iter().fold(0u8, |checksum, &byte| {
+        checksum.wrapping_sub(byte).wrapping_sub(1)
+    });
+    let computed_global_checksum = data
+        .iter()
+        .enumerate()
+        .filter(|(offset, _)| !(0x14e..0x150).contains(offset))
+        .fold(0u16, |checksum, (_, &byte)| {
+            checksum.wrapping_add(byte as u16)
+        });
+    let global_checksum = u16::from_be_bytes([data[0x14e], data[0x14f]]);
+
+    Some(GameBoyHeader {
+        title: ascii(&data[0x134..title_end]),
+        cgb,
+        sgb: data[0x146] == 0x03,
+        cartridge_type: data[0x147],
+        japanese: data[0x14a] == 0x00,
+        version: data[0x14c],
+        header_checksum: data[0x14d],
+        computed_header_checksum,
+        header_checksum_valid: data[0x14d] == computed_header_checksum,
+        global_checksum,
+        computed_global_checksum,
+        global_checksum_valid: global_checksum == computed_global_checksum,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut data = vec![0; 0x8000];
+        data[0x104..0x108].copy_from_slice(&LOGO_START);
+        // Pokemon Red's header
+        data[0x134..0x13f].copy_from_slice(b"POKEMON RED");
+        data[0x144..0x14d].copy_from_slice(&[0x30, 0x31, 0x03, 0x13, 0x05, 0x03, 0x01, 0x33, 0x00]);
+        data[0x14d] = 0x20;
+        let checksum = data.iter().map(|&byte| byte as u16).sum::<u16>();
+        data[0x14e..0x150].copy_from_slice(&checksum.to_be_bytes());
+
+        let header = parse(&data).unwrap();
+        assert_eq!("POKEMON RED", header.title);
+        assert_eq!(CgbSupport::None, header.cgb);
+        assert!(header.sgb);
+        assert!(!header.japanese);
+        assert!(header.header_checksum_valid);
+        assert!(header.global_checksum_valid);
+
+        data[0x143] = 0x80;
+        let header = parse(&data).unwrap();
+        assert_eq!(CgbSupport::Enhanced, header.cgb);
+        assert!(!header.header_checksum_valid);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+use super::ascii;
+
+/// The start of the Nintendo logo after the entry point
+const LOGO_START: [u8; 4] = [0x24, 0xff, 0xae, 0x51];
+/// fixed value the BIOS checks
+const FIXED_VALUE: u8 = 0x96;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GbaHeader {
+    pub title: String,
+    /// e.g. `AXVE` for Pokemon Ruby (USA), the last character is the region
+    pub game_code: String,
+    pub maker_code: String,
+    pub version: u8,
+    pub complement: u8,
+    pub computed_complement: u8,
+    /// the BIOS refuses to start cartridges with a wrong header complement
+    pub complement_valid: bool,
+}
+
+pub fn parse(data: &[u8]) -> Option<GbaHeader> {
+    if data.get(0x04..0x08)? != LOGO_START || data.get(0xb2) != Some(&FIXED_VALUE) {
+        return None;
+    }
+
+    let computed_complement = data[0xa0..0xbd]
+        .iter()
+        .fold(0u8, |complement, &byte| complement.wrapping_sub(byte))
+        .wrapping_sub(0x19);
+
+    Some(GbaHeader {
+        title: ascii(&data[0xa0..0xac]),
+        game_code: ascii(&data[0xac..0xb0]),
+        maker_code: ascii(&data[0xb0..0xb2]),
+        version: data[0xbc],
+        complement: data[0xbd],
+        computed_complement,
+        complement_valid: data[0xbd] == computed_complement,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut data = vec![0; 0x200];
+        data[0x04..0x08].copy_from_slice(&LOGO_START);
+        data[0xa0..0xb2].copy_from_slice(b"POKEMON RUBYAXVE01");
+        data[0xb2] = FIXED_VALUE;
+        data[0xbd] = 0x41;
+
+        let header = parse(&data).unwrap();
+        assert_eq!("POKEMON RUBY", header.title);
+        assert_eq!("AXVE", header.game_code);
+        assert_eq!("01", header.maker_code);
+        assert!(header.complement_valid);
+
+        data[0xbc] = 1;
+        assert!(!parse(&data).unwrap().complement_valid);
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+use super::ascii;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MegaDriveHeader {
+    /// e.g. `SEGA MEGA DRIVE` or `SEGA GENESIS`
+    pub system: String,
+    pub domestic_title: String,
+    pub overseas_title: String,
+    /// the product code without type and version, e.g. `00001009` of `GM 00001009-00`
+    pub serial: String,
+    /// region codes like `JUE`
+    pub regions: String,
+    pub checksum: u16,
+    pub computed_checksum: u16,
+    pub checksum_valid: bool,
+}
+
+pub fn parse(data: &[u8]) -> Option<MegaDriveHeader> {
+    let system = data.get(0x100..0x110)?;
+    if !system.starts_with(b"SEGA") && !system.starts_with(b" SEGA") {
+        return None;
+    }
+    let header = data.get(0x100..0x200)?;
+
+    let product = ascii(&header[0x80..0x8e]);
+    let serial = product
+        .split_once(' ')
+        .map_or(product.as_str(), |(_, serial)| serial)
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    // big endian words from the end of the header on
+    let computed_checksum = data[0x200..].chunks_exact(2).fold(0u16, |checksum, word| {
+        checksum.wrapping_add(u16::from_be_bytes([word[0], word[1]]))
+    });
+    let checksum = u16::from_be_bytes([header[0x8e], header[0x8f]]);
+
+    Some(MegaDriveHeader {
+        system: ascii(system),
+        domestic_title: ascii(&header[0x20..0x50]),
+        overseas_title: ascii(&header[0x50..0x80]),
+        serial,
+        regions: ascii(&header[0xf0..0xf3]),
+        checksum,
+        computed_checksum,
+        checksum_valid: checksum == computed_checksum,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut data = vec![b' '; 0x400];
+        data[0x100..0x10f].copy_from_slice(b"SEGA MEGA DRIVE");
+        data[0x120..0x133].copy_from_slice(b"SONIC THE HEDGEHOG ");
+        data[0x150..0x163].copy_from_slice(b"SONIC THE HEDGEHOG ");
+        data[0x180..0x18e].copy_from_slice(b"GM 00001009-00");
+        data[0x1f0..0x1f3].copy_from_slice(b"JUE");
+        data[0x200..0x204].copy_from_slice(&[0x12, 0x34, 0x00, 0x01]);
+        // the rest are spaces: 254 more words of 0x2020
+        let checksum = 0x1235u16.wrapping_add(0x2020u16.wrapping_mul(254));
+        data[0x18e..0x190].copy_from_slice(&checksum.to_be_bytes());
+
+        let header = parse(&data).unwrap();
+        assert_eq!("SEGA MEGA DRIVE", header.system);
+        assert_eq!("SONIC THE HEDGEHOG", header.overseas_title);
+        assert_eq!("00001009", header.serial);
+        assert_eq!("JUE", header.regions);
+        assert!(header.checksum_valid);
+
+        data[0x3ff] = 0;
+        assert!(!parse(&data).unwrap().checksum_valid);
+    }
+}
@@ -0,0 +1,101 @@
+// Reads the internal headers of cartridge roms, so titles, ids and checksums can be compared
+// with the DATs and corrupted dumps stand out.
+
+use std::{fs, io, path::Path};
+
+use serde::Serialize;
+
+use self::{
+    gameboy::GameBoyHeader, gba::GbaHeader, megadrive::MegaDriveHeader, n64::N64Header,
+    nes::NesHeader, snes::SnesHeader,
+};
+
+pub mod gameboy;
+pub mod gba;
+pub mod megadrive;
+pub mod n64;
+pub mod nes;
+pub mod snes;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "system", rename_all = "snake_case")]
+pub enum RomHeader {
+    Nes(NesHeader),
+    Snes(SnesHeader),
+    GameBoy(GameBoyHeader),
+    GameBoyAdvance(GbaHeader),
+    N64(N64Header),
+    MegaDrive(MegaDriveHeader),
+}
+
+impl RomHeader {
+    /// Whether the checksums the header carries match the rom, `None` without checksums to check
+    pub fn checksum_valid(&self) -> Option<bool> {
+        match self {
+            RomHeader::Snes(header) => Some(header.checksum_valid),
+            RomHeader::GameBoy(header) => {
+                Some(header.header_checksum_valid && header.global_checksum_valid)
+            }
+            RomHeader::GameBoyAdvance(header) => Some(header.complement_valid),
+            RomHeader::MegaDrive(header) => Some(header.checksum_valid),
+            RomHeader::Nes(_) | RomHeader::N64(_) => None,
+        }
+    }
+}
+
+/// Header text without padding and unprintable bytes
+fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if (0x20..0x7f).contains(&byte) {
+                byte as char
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Detects the system by its header. The ones with magic numbers go first, SNES headers
+/// have none and are found by scoring the possible locations.
+pub fn parse(data: &[u8]) -> Option<RomHeader> {
+    nes::parse(data)
+        .map(RomHeader::Nes)
+        .or_else(|| n64::parse(data).map(RomHeader::N64))
+        .or_else(|| gba::parse(data).map(RomHeader::GameBoyAdvance))
+        .or_else(|| gameboy::parse(data).map(RomHeader::GameBoy))
+        .or_else(|| megadrive::parse(data).map(RomHeader::MegaDrive))
+        .or_else(|| snes::parse(data).map(RomHeader::Snes))
+}
+
+pub fn read_header(path: &Path) -> io::Result<Option<RomHeader>> {
+    Ok(parse(&fs::read(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut n64 = n64::tests::header();
+        assert!(matches!(parse(&n64), Some(RomHeader::N64(_))));
+
+        n64::ByteOrder::ByteSwapped.swap(&mut n64);
+        let Some(RomHeader::N64(header)) = parse(&n64) else {
+            panic!("no N64 header");
+        };
+        assert_eq!(n64::ByteOrder::ByteSwapped, header.byte_order);
+        assert_eq!(None, RomHeader::N64(header).checksum_valid());
+
+        assert_eq!(None, parse(&[0; 0x10000]));
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!("SUPER MARIO", ascii(b"SUPER MARIO\0\0  \xff"));
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hashing::{hash_reader, FileHashes};
+
+use super::ascii;
+
+/// Converted at a time, a multiple of the word size
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Byte orders N64 dumps come in, named after their usual extensions
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrder {
+    /// big endian as the cartridge stores it, `.z64`
+    BigEndian,
+    /// every 16 bit pair swapped, `.v64`
+    ByteSwapped,
+    /// every 32 bit word reversed, `.n64`
+    LittleEndian,
+}
+
+impl ByteOrder {
+    pub const ALL: [ByteOrder; 3] = [
+        ByteOrder::BigEndian,
+        ByteOrder::ByteSwapped,
+        ByteOrder::LittleEndian,
+    ];
+
+    /// Detects the order by the first word, `80 37 12 40` in big endian
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data.get(..4)? {
+            [0x80, 0x37, 0x12, 0x40] => Some(ByteOrder::BigEndian),
+            [0x37, 0x80, 0x40, 0x12] => Some(ByteOrder::ByteSwapped),
+            [0x40, 0x12, 0x37, 0x80] => Some(ByteOrder::LittleEndian),
+            _ => None,
+        }
+    }
+
+    /// The order a file extension stands for, case insensitive
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        ByteOrder::ALL
+            .into_iter()
+            .find(|order| order.extension().eq_ignore_ascii_case(extension))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ByteOrder::BigEndian => "z64",
+            ByteOrder::ByteSwapped => "v64",
+            ByteOrder::LittleEndian => "n64",
+        }
+    }
+
+    /// Converts data in this order to big endian in place. Every order is its own inverse,
+    /// so the same converts big endian data to this order.
+    pub fn swap(&self, data: &mut [u8]) {
+        match self {
+            ByteOrder::BigEndian => (),
+            ByteOrder::ByteSwapped => data.chunks_exact_mut(2).for_each(|pair| pair.swap(0, 1)),
+            ByteOrder::LittleEndian => data.chunks_exact_mut(4).for_each(|word| word.reverse()),
+        }
+    }
+}
+
+/// Reads a rom stored in one byte order as another, converting whole words at a time
+pub struct ConvertingReader<R> {
+    inner: R,
+    from: ByteOrder,
+    to: ByteOrder,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl<R: Read> ConvertingReader<R> {
+    pub fn new(inner: R, from: ByteOrder, to: ByteOrder) -> Self {
+        ConvertingReader {
+            inner,
+            from,
+            to,
+            buffer: vec![0; BUFFER_SIZE],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let mut filled = 0;
+        while filled < self.buffer.len() {
+            match self.inner.read(&mut self.buffer[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        // only the end of a truncated rom can leave a partial word, which stays as it is
+        let words = filled - filled % 4;
+        self.from.swap(&mut self.buffer[..words]);
+        self.to.swap(&mut self.buffer[..words]);
+        self.start = 0;
+        self.end = filled;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ConvertingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.start == self.end {
+            self.fill()?;
+        }
+
+        let len = buf.len().min(self.end - self.start);
+        buf[..len].copy_from_slice(&self.buffer[self.start..self.start + len]);
+        self.start += len;
+        Ok(len)
+    }
+}
+
+/// The byte order of the file, `None` if it is no N64 rom
+pub fn detect_file(path: &Path) -> io::Result<Option<ByteOrder>> {
+    let mut first_word = Vec::with_capacity(4);
+    File::open(path)?.take(4).read_to_end(&mut first_word)?;
+
+    Ok(ByteOrder::detect(&first_word))
+}
+
+/// Opens the rom for reading in the given byte order, whatever order it is stored in
+pub fn open_as(path: &Path, order: ByteOrder) -> io::Result<ConvertingReader<BufReader<File>>> {
+    let mut file = File::open(path)?;
+    let mut first_word = [0; 4];
+    file.read_exact(&mut first_word)?;
+    let stored = ByteOrder::detect(&first_word).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is no N64 rom", path.display()),
+        )
+    })?;
+    file.rewind()?;
+
+    Ok(ConvertingReader::new(BufReader::new(file), stored, order))
+}
+
+/// Hashes the big endian form of the rom, the one No-Intro lists
+pub fn hash_file(path: &Path) -> io::Result<FileHashes> {
+    hash_reader(&mut open_as(path, ByteOrder::BigEndian)?)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct N64Header {
+    pub title: String,
+    /// media, cartridge id and region, e.g. `NSME` for Super Mario 64 (USA)
+    pub game_id: String,
+    pub version: u8,
+    pub byte_order: ByteOrder,
+    pub crc1: u32,
+    pub crc2: u32,
+}
+
+pub fn parse(data: &[u8]) -> Option<N64Header> {
+    let byte_order = ByteOrder::detect(data)?;
+    let mut header = data.get(..0x40)?.to_vec();
+    byte_order.swap(&mut header);
+
+    let word = |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+
+    Some(N64Header {
+        title: ascii(&header[0x20..0x34]),
+        game_id: ascii(&header[0x3b..0x3f]),
+        version: header[0x3f],
+        byte_order,
+        crc1: word(0x10),
+        crc2: word(0x14),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// The header of a big endian rom
+    pub fn header() -> Vec<u8> {
+        let mut header = vec![0; 0x1000];
+        header[..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        header[0x10..0x18].copy_from_slice(&[0x63, 0x5a, 0x2b, 0xff, 0x8b, 0x02, 0x23, 0x26]);
+        header[0x20..0x34].copy_from_slice(b"SUPER MARIO 64      ");
+        header[0x3b..0x3f].copy_from_slice(b"NSME");
+        header
+    }
+
+    #[test]
+    fn test_byte_orders() {
+        let big_endian = header();
+
+        for order in ByteOrder::ALL {
+            let mut data = big_endian.clone();
+            order.swap(&mut data);
+            assert_eq!(Some(order), ByteOrder::detect(&data));
+
+            let header = parse(&data).unwrap();
+            assert_eq!("SUPER MARIO 64", header.title);
+            assert_eq!("NSME", header.game_id);
+            assert_eq!(0x635a2bff, header.crc1);
+
+            order.swap(&mut data);
+            assert_eq!(big_endian, data);
+        }
+    }
+
+    #[test]
+    fn test_converting_reader() {
+        // longer than the buffer, so the conversion continues in a second fill
+        let mut big_endian: Vec<u8> = (0..BUFFER_SIZE + 12).map(|i| (i % 251) as u8).collect();
+        big_endian[..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        let mut byte_swapped = big_endian.clone();
+        ByteOrder::ByteSwapped.swap(&mut byte_swapped);
+        let mut little_endian = big_endian.clone();
+        ByteOrder::LittleEndian.swap(&mut little_endian);
+
+        let mut converted = Vec::new();
+        ConvertingReader::new(
+            byte_swapped.as_slice(),
+            ByteOrder::ByteSwapped,
+            ByteOrder::LittleEndian,
+        )
+        .read_to_end(&mut converted)
+        .unwrap();
+        assert_eq!(little_endian, converted);
+
+        let path = std::env::temp_dir().join("romana_n64_test.v64");
+        std::fs::write(&path, &byte_swapped).unwrap();
+        let hashes = hash_file(&path);
+        let order = detect_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            hash_reader(&mut big_endian.as_slice()).unwrap(),
+            hashes.unwrap()
+        );
+        assert_eq!(Some(ByteOrder::ByteSwapped), order.unwrap());
+        assert_eq!(
+            Some(ByteOrder::LittleEndian),
+            ByteOrder::from_extension("N64")
+        );
+    }
+}
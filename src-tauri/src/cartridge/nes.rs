@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+const MAGIC: &[u8; 4] = b"NES\x1a";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NesFormat {
+    INes,
+    Nes2,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+/// The iNES header, NES roms have no internal title. No-Intro hashes the roms without it.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NesHeader {
+    pub format: NesFormat,
+    pub mapper: u16,
+    pub prg_rom_size: u64,
+    pub chr_rom_size: u64,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+}
+
+pub fn parse(data: &[u8]) -> Option<NesHeader> {
+    let header = data.get(..16)?;
+    if &header[..4] != MAGIC {
+        return None;
+    }
+
+    let format = if header[7] & 0x0c == 0x08 {
+        NesFormat::Nes2
+    } else {
+        NesFormat::INes
+    };
+    let mut mapper = ((header[7] & 0xf0) | (header[6] >> 4)) as u16;
+    let (mut prg_units, mut chr_units) = (header[4] as u64, header[5] as u64);
+    if format == NesFormat::Nes2 {
+        // NES 2.0 extends the mapper and the sizes by a nibble each
+        mapper |= ((header[8] & 0x0f) as u16) << 8;
+        prg_units |= ((header[9] & 0x0f) as u64) << 8;
+        chr_units |= ((header[9] >> 4) as u64) << 8;
+    }
+
+    Some(NesHeader {
+        format,
+        mapper,
+        prg_rom_size: prg_units * 16 * 1024,
+        chr_rom_size: chr_units * 8 * 1024,
+        mirroring: match header[6] & 0x09 {
+            0x08 | 0x09 => Mirroring::FourScreen,
+            0x01 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        },
+        battery: header[6] & 0x02 != 0,
+        trainer: header[6] & 0x04 != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // Super Mario Bros. 3: MMC3, 256 KiB PRG and 128 KiB CHR
+        let header = [
+            b'N', b'E', b'S', 0x1a, 16, 16, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        assert_eq!(
+            Some(NesHeader {
+                format: NesFormat::INes,
+                mapper: 4,
+                prg_rom_size: 256 * 1024,
+                chr_rom_size: 128 * 1024,
+                mirroring: Mirroring::Horizontal,
+                battery: false,
+                trainer: false,
+            }),
+            parse(&header)
+        );
+    }
+}
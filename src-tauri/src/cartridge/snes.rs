@@ -0,0 +1,191 @@
+use serde::Serialize;
+
+use super::ascii;
+
+/// Bytes copiers put in front of the rom
+const COPIER_HEADER_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnesMapping {
+    LoRom,
+    HiRom,
+    ExHiRom,
+}
+
+impl SnesMapping {
+    /// Offset of the internal header in the rom
+    fn header_offset(&self) -> usize {
+        match self {
+            SnesMapping::LoRom => 0x7fc0,
+            SnesMapping::HiRom => 0xffc0,
+            SnesMapping::ExHiRom => 0x40ffc0,
+        }
+    }
+
+    /// Whether the header's map mode fits the header's location
+    fn matches_map_mode(&self, map_mode: u8) -> bool {
+        matches!(
+            (self, map_mode & 0x0f),
+            (SnesMapping::LoRom, 0 | 2 | 3) | (SnesMapping::HiRom, 1) | (SnesMapping::ExHiRom, 5)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SnesHeader {
+    pub title: String,
+    pub mapping: SnesMapping,
+    pub fast_rom: bool,
+    pub region: &'static str,
+    pub version: u8,
+    /// the dump starts with a 512 byte copier header, which the checksum leaves out
+    pub copier_header: bool,
+    pub checksum: u16,
+    pub complement: u16,
+    pub computed_checksum: u16,
+    pub checksum_valid: bool,
+}
+
+fn region(code: u8) -> &'static str {
+    match code {
+        0x00 => "Japan",
+        0x01 => "USA",
+        0x02 => "Europe",
+        0x03 => "Sweden",
+        0x04 => "Finland",
+        0x05 => "Denmark",
+        0x06 => "France",
+        0x07 => "Netherlands",
+        0x08 => "Spain",
+        0x09 => "Germany",
+        0x0a => "Italy",
+        0x0b => "China",
+        0x0c => "Indonesia",
+        0x0d => "Korea",
+        0x0f => "Canada",
+        0x10 => "Brazil",
+        0x11 => "Australia",
+        _ => "Unknown",
+    }
+}
+
+/// Sum of all bytes, with sizes that are no power of two mirrored up like the cartridge
+/// address space does: the part after the largest power of two repeats to fill it
+fn mirrored_sum(data: &[u8]) -> u32 {
+    if data.is_empty() || data.len().is_power_of_two() {
+        return data.iter().map(|&byte| byte as u32).sum();
+    }
+
+    let base = 1 << (usize::BITS - 1 - data.len().leading_zeros());
+    let rest = &data[base..];
+    let repeats = base / rest.len().next_power_of_two();
+
+    mirrored_sum(&data[..base]).wrapping_add(mirrored_sum(rest).wrapping_mul(repeats as u32))
+}
+
+/// Rates how plausible a header location is, as emulators do
+fn score(rom: &[u8], mapping: SnesMapping) -> Option<u32> {
+    let header = rom.get(mapping.header_offset()..mapping.header_offset() + 0x40)?;
+    let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+
+    let mut score = 0;
+    if word(0x1c) ^ word(0x1e) == 0xffff {
+        score += 4;
+    }
+    if mapping.matches_map_mode(header[0x15]) {
+        score += 2;
+    }
+    if header[..21]
+        .iter()
+        .all(|&byte| (0x20..0x7f).contains(&byte))
+    {
+        score += 1;
+    }
+    // the reset vector points into the rom
+    if word(0x3c) >= 0x8000 {
+        score += 1;
+    }
+    Some(score)
+}
+
+pub fn parse(data: &[u8]) -> Option<SnesHeader> {
+    let copier_header = data.len() % 1024 == COPIER_HEADER_SIZE;
+    let rom = if copier_header {
+        &data[COPIER_HEADER_SIZE..]
+    } else {
+        data
+    };
+
+    let (mapping, _) = [SnesMapping::LoRom, SnesMapping::HiRom, SnesMapping::ExHiRom]
+        .into_iter()
+        .filter_map(|mapping| Some((mapping, score(rom, mapping)?)))
+        .filter(|(_, score)| *score >= 4)
+        .max_by_key(|(_, score)| *score)?;
+
+    let header = &rom[mapping.header_offset()..mapping.header_offset() + 0x40];
+    let word = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+    let computed_checksum = mirrored_sum(rom) as u16;
+
+    Some(SnesHeader {
+        title: ascii(&header[..21]),
+        mapping,
+        fast_rom: header[0x15] & 0x10 != 0,
+        region: region(header[0x19]),
+        version: header[0x1b],
+        copier_header,
+        checksum: word(0x1e),
+        complement: word(0x1c),
+        computed_checksum,
+        checksum_valid: word(0x1e) == computed_checksum && word(0x1c) == !computed_checksum,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A HiROM image with a correct checksum
+    fn rom(size: usize) -> Vec<u8> {
+        let mut rom: Vec<u8> = (0..size).map(|i| (i % 253) as u8).collect();
+        let header = &mut rom[0xffc0..0x10000];
+        header[..21].copy_from_slice(b"SECRET OF MANA       ");
+        header[0x15] = 0x31;
+        header[0x19] = 0x02;
+        header[0x3c..0x3e].copy_from_slice(&0x8000u16.to_le_bytes());
+        // the checksum and its complement always add up to 0x1fe
+        header[0x1c..0x20].copy_from_slice(&[0xff, 0xff, 0x00, 0x00]);
+
+        let checksum = mirrored_sum(&rom) as u16;
+        rom[0xffdc..0xffde].copy_from_slice(&(!checksum).to_le_bytes());
+        rom[0xffde..0xffe0].copy_from_slice(&checksum.to_le_bytes());
+        rom
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = rom(0x20000);
+        let header = parse(&data).unwrap();
+
+        assert_eq!("SECRET OF MANA", header.title);
+        assert_eq!(SnesMapping::HiRom, header.mapping);
+        assert!(header.fast_rom);
+        assert_eq!("Europe", header.region);
+        assert!(header.checksum_valid);
+
+        let mut copier = vec![0; COPIER_HEADER_SIZE];
+        copier.extend(&data);
+        copier[0x18000] ^= 0xff;
+        let header = parse(&copier).unwrap();
+        assert!(header.copier_header);
+        assert!(!header.checksum_valid);
+    }
+
+    #[test]
+    fn test_mirrored_sum() {
+        // 12 bytes sum as 8 plus the last 4 twice
+        let data = [1u8; 12];
+        assert_eq!(16, mirrored_sum(&data));
+        assert_eq!(2048, mirrored_sum(&[1; 1280]));
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::{self, Read};
+
+use super::{
+    codecs::{CD_FRAME_SIZE, CD_SECTOR_SIZE},
+    Chd,
+};
+
+/// Track metadata tags of CD images, old and current, and of GD-ROM images
+const TRACK_TAGS: [&[u8; 4]; 3] = [b"CHTR", b"CHT2", b"CHGD"];
+/// every track is padded to a multiple of four frames in the image
+const TRACK_PADDING: u64 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CdTrack {
+    pub number: u32,
+    /// e.g. `MODE1_RAW`, `MODE2_RAW` or `AUDIO`
+    pub kind: String,
+    /// frames of the track, including a pregap stored in the image
+    pub frames: u64,
+    /// first frame of the track in the image
+    pub start_frame: u64,
+}
+
+impl CdTrack {
+    /// Bytes of every sector the track's dumps contain
+    pub fn sector_size(&self) -> usize {
+        match self.kind.as_str() {
+            "MODE1" | "MODE2_FORM1" => 2048,
+            "MODE2_FORM2" => 2324,
+            "MODE2" | "MODE2_FORM_MIX" => 2336,
+            _ => CD_SECTOR_SIZE,
+        }
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.kind == "AUDIO"
+    }
+}
+
+/// Reads a metadata value like `TRACK:1 TYPE:MODE2_RAW SUBTYPE:NONE FRAMES:1234`
+fn metadata_value<'a>(metadata: &'a str, key: &str) -> Option<&'a str> {
+    metadata
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix(key)?.strip_prefix(':'))
+}
+
+/// Returns the tracks of a CD or GD-ROM image, none for other images
+pub fn tracks(chd: &mut Chd) -> io::Result<Vec<CdTrack>> {
+    let mut tracks = Vec::new();
+
+    for (tag, data) in chd.metadata()? {
+        if !TRACK_TAGS.contains(&&tag) {
+            continue;
+        }
+        let metadata = String::from_utf8_lossy(&data);
+        let metadata = metadata.trim_end_matches('\0');
+        let value = |key| metadata_value(metadata, key).and_then(|value| value.parse().ok());
+
+        let (Some(number), Some(frames)) = (value("TRACK"), value("FRAMES")) else {
+            return Err(super::invalid_chd("invalid track metadata"));
+        };
+        tracks.push(CdTrack {
+            number: number as u32,
+            kind: metadata_value(metadata, "TYPE")
+                .unwrap_or_default()
+                .to_string(),
+            frames,
+            start_frame: 0,
+        });
+    }
+
+    tracks.sort_by_key(|track| track.number);
+    let mut start_frame = 0;
+    for track in &mut tracks {
+        track.start_frame = start_frame;
+        start_frame += track.frames.next_multiple_of(TRACK_PADDING);
+    }
+
+    Ok(tracks)
+}
+
+/// Reads a track as its `.bin` dump: the sectors without subcode, with the big endian
+/// audio samples of the image swapped back to little endian
+pub struct TrackReader<'a> {
+    chd: &'a mut Chd,
+    track: &'a CdTrack,
+    frame: u64,
+    sector: Vec<u8>,
+    position: usize,
+}
+
+impl<'a> TrackReader<'a> {
+    pub fn new(chd: &'a mut Chd, track: &'a CdTrack) -> Self {
+        TrackReader {
+            chd,
+            track,
+            frame: 0,
+            sector: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for TrackReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.sector.len() {
+            if self.frame == self.track.frames {
+                return Ok(0);
+            }
+
+            let mut frame = vec![0; CD_FRAME_SIZE];
+            let offset = (self.track.start_frame + self.frame) * CD_FRAME_SIZE as u64;
+            self.chd.read_bytes(offset, &mut frame)?;
+            frame.truncate(self.track.sector_size());
+            if self.track.is_audio() {
+                for sample in frame.chunks_exact_mut(2) {
+                    sample.swap(0, 1);
+                }
+            }
+
+            self.sector = frame;
+            self.position = 0;
+            self.frame += 1;
+        }
+
+        let count = buffer.len().min(self.sector.len() - self.position);
+        buffer[..count].copy_from_slice(&self.sector[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
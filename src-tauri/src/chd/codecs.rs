@@ -0,0 +1,295 @@
+use std::io::{self, Cursor};
+
+use claxon::frame::FrameReader;
+use flate2::{Decompress, FlushDecompress};
+use lzma_rs::decompress::{Options, UnpackedSize};
+
+use super::{ecc, invalid_chd};
+
+/// Bytes of a CD frame in a CHD: the raw sector followed by its subcode
+pub const CD_FRAME_SIZE: usize = 2448;
+pub const CD_SECTOR_SIZE: usize = 2352;
+const CD_SUBCODE_SIZE: usize = 96;
+const CD_SYNC_HEADER: [u8; 12] = [0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0];
+
+/// Hunk compressors of CHD v5, identified by their four character tags in the header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Zlib,
+    Lzma,
+    Flac,
+    CdZlib,
+    CdLzma,
+    CdFlac,
+}
+
+impl Codec {
+    pub fn from_tag(tag: u32) -> io::Result<Option<Self>> {
+        Ok(Some(match &tag.to_be_bytes() {
+            [0, 0, 0, 0] => return Ok(None),
+            b"zlib" => Codec::Zlib,
+            b"lzma" => Codec::Lzma,
+            b"flac" => Codec::Flac,
+            b"cdzl" => Codec::CdZlib,
+            b"cdlz" => Codec::CdLzma,
+            b"cdfl" => Codec::CdFlac,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "unsupported CHD compression {}",
+                        String::from_utf8_lossy(other)
+                    ),
+                ))
+            }
+        }))
+    }
+
+    /// Decompresses a hunk of `hunk_bytes` bytes
+    pub fn decompress(&self, data: &[u8], hunk_bytes: usize) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Zlib => inflate(data, hunk_bytes),
+            Codec::Lzma => lzma(data, hunk_bytes),
+            Codec::Flac => {
+                // the first byte tells the byte order of the samples
+                let big_endian = match data.first() {
+                    Some(b'B') => true,
+                    Some(b'L') => false,
+                    _ => return Err(invalid_chd("invalid flac hunk")),
+                };
+                Ok(flac(&data[1..], hunk_bytes, big_endian)?.0)
+            }
+            Codec::CdZlib => cd_decompress(data, hunk_bytes, inflate),
+            Codec::CdLzma => cd_decompress(data, hunk_bytes, lzma),
+            Codec::CdFlac => cd_flac(data, hunk_bytes),
+        }
+    }
+}
+
+/// Raw deflate without zlib header, as CHD stores it
+fn inflate(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    Decompress::new(false)
+        .decompress_vec(data, &mut output, FlushDecompress::Finish)
+        .map_err(|e| invalid_chd(&e.to_string()))?;
+
+    if output.len() != size {
+        return Err(invalid_chd("deflate hunk has the wrong size"));
+    }
+    Ok(output)
+}
+
+/// Raw LZMA without header. MAME encodes with level 9 reduced to the hunk size, which
+/// gives `lc=3 lp=0 pb=2` and a dictionary no larger than the hunk.
+fn lzma(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let dictionary = (size as u32).next_power_of_two().max(4096);
+    let mut stream = vec![0x5d];
+    stream.extend(dictionary.to_le_bytes());
+    stream.extend(data);
+
+    let mut output = Vec::with_capacity(size);
+    lzma_rs::lzma_decompress_with_options(
+        &mut stream.as_slice(),
+        &mut output,
+        &Options {
+            unpacked_size: UnpackedSize::UseProvided(Some(size as u64)),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| invalid_chd(&e.to_string()))?;
+
+    Ok(output)
+}
+
+/// Decodes FLAC frames of 16 bit stereo into `size` bytes of interleaved samples. Returns
+/// the samples and the length of the FLAC data, as CD hunks store the subcode after it.
+fn flac(data: &[u8], size: usize, big_endian: bool) -> io::Result<(Vec<u8>, usize)> {
+    let mut reader = FrameReader::new(Cursor::new(data));
+    let mut output = Vec::with_capacity(size);
+    let mut buffer = Vec::new();
+
+    while output.len() < size {
+        let block = reader
+            .read_next_or_eof(buffer)
+            .map_err(|e| invalid_chd(&e.to_string()))?
+            .ok_or_else(|| invalid_chd("flac hunk ended early"))?;
+
+        for (left, right) in block.stereo_samples() {
+            for sample in [left as i16, right as i16] {
+                output.extend(if big_endian {
+                    sample.to_be_bytes()
+                } else {
+                    sample.to_le_bytes()
+                });
+            }
+        }
+        buffer = block.into_buffer();
+    }
+    output.truncate(size);
+
+    Ok((output, reader.into_inner().position() as usize))
+}
+
+/// Interleaves the decompressed sectors and subcodes into frames
+fn cd_frames(sectors: &[u8], subcodes: &[u8], frames: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(frames * CD_FRAME_SIZE);
+    for frame in 0..frames {
+        output.extend(&sectors[frame * CD_SECTOR_SIZE..(frame + 1) * CD_SECTOR_SIZE]);
+        output.extend(&subcodes[frame * CD_SUBCODE_SIZE..(frame + 1) * CD_SUBCODE_SIZE]);
+    }
+    output
+}
+
+/// The CD codecs compress sectors and subcodes separately. Sectors whose sync header and
+/// ECC could be regenerated have them stripped, flagged in a bitmap in front.
+fn cd_decompress(
+    data: &[u8],
+    hunk_bytes: usize,
+    base: fn(&[u8], usize) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    let frames = hunk_bytes / CD_FRAME_SIZE;
+    let ecc_bytes = frames.div_ceil(8);
+    let length_bytes = if hunk_bytes < 65536 { 2 } else { 3 };
+    let header_bytes = ecc_bytes + length_bytes;
+    if data.len() < header_bytes {
+        return Err(invalid_chd("cd hunk too short"));
+    }
+
+    let base_length = data[ecc_bytes..header_bytes]
+        .iter()
+        .fold(0, |length, &byte| (length << 8) | byte as usize);
+    let subcode_start = header_bytes + base_length;
+    if subcode_start > data.len() {
+        return Err(invalid_chd("cd hunk too short"));
+    }
+
+    let sectors = base(&data[header_bytes..subcode_start], frames * CD_SECTOR_SIZE)?;
+    let subcodes = inflate(&data[subcode_start..], frames * CD_SUBCODE_SIZE)?;
+    let mut output = cd_frames(&sectors, &subcodes, frames);
+
+    for frame in 0..frames {
+        if data[frame / 8] & (1 << (frame % 8)) != 0 {
+            let sector = &mut output[frame * CD_FRAME_SIZE..frame * CD_FRAME_SIZE + CD_SECTOR_SIZE];
+            sector[..12].copy_from_slice(&CD_SYNC_HEADER);
+            ecc::generate(sector);
+        }
+    }
+
+    Ok(output)
+}
+
+/// cdfl stores the sectors as big endian FLAC audio followed by the deflated subcodes
+fn cd_flac(data: &[u8], hunk_bytes: usize) -> io::Result<Vec<u8>> {
+    let frames = hunk_bytes / CD_FRAME_SIZE;
+    let (sectors, length) = flac(data, frames * CD_SECTOR_SIZE, true)?;
+    let subcodes = inflate(&data[length..], frames * CD_SUBCODE_SIZE)?;
+
+    Ok(cd_frames(&sectors, &subcodes, frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use super::*;
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_lzma() {
+        let data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress(&mut data.as_slice(), &mut compressed).unwrap();
+
+        // without the 13 byte header of props, dictionary and size
+        assert_eq!(data, lzma(&compressed[13..], data.len()).unwrap());
+    }
+
+    #[test]
+    fn test_cd_zlib() {
+        let frames = 2;
+        let mut sectors = vec![0x42; frames * CD_SECTOR_SIZE];
+        // the first sector is mode 1 with its sync header stripped
+        sectors[..12].fill(0);
+        sectors[15] = 1;
+        let subcodes = vec![0x17; frames * CD_SUBCODE_SIZE];
+
+        let base = deflate(&sectors);
+        let mut data = vec![0b01];
+        data.extend((base.len() as u16).to_be_bytes());
+        data.extend(&base);
+        data.extend(deflate(&subcodes));
+
+        let hunk = Codec::CdZlib
+            .decompress(&data, frames * CD_FRAME_SIZE)
+            .unwrap();
+
+        assert_eq!(frames * CD_FRAME_SIZE, hunk.len());
+        assert_eq!(CD_SYNC_HEADER, hunk[..12]);
+        assert_eq!(
+            &sectors[CD_SECTOR_SIZE..],
+            &hunk[CD_FRAME_SIZE..CD_FRAME_SIZE + CD_SECTOR_SIZE]
+        );
+        assert_eq!([0x17; 96], hunk[CD_SECTOR_SIZE..CD_FRAME_SIZE]);
+    }
+
+    fn crc8(data: &[u8]) -> u8 {
+        data.iter().fold(0, |crc, &byte| {
+            (0..8).fold(crc ^ byte, |crc, _| {
+                if crc & 0x80 != 0 {
+                    (crc << 1) ^ 0x07
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    fn crc16(data: &[u8]) -> u16 {
+        data.iter().fold(0, |crc, &byte| {
+            (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+                if crc & 0x8000 != 0 {
+                    (crc << 1) ^ 0x8005
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    /// A FLAC frame with verbatim 16 bit stereo subframes
+    fn flac_frame(samples: &[(i16, i16)]) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xf8, 0x69, 0x18, 0x00, samples.len() as u8 - 1];
+        frame.push(crc8(&frame));
+        for channel in 0..2 {
+            frame.push(0x02);
+            for (left, right) in samples {
+                let sample = if channel == 0 { left } else { right };
+                frame.extend(sample.to_be_bytes());
+            }
+        }
+        frame.extend(crc16(&frame).to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_flac() {
+        let samples = [(1, -1), (256, 512), (-32768, 32767)];
+        let mut data = vec![b'L'];
+        data.extend(flac_frame(&samples));
+
+        let expected: Vec<u8> = samples
+            .iter()
+            .flat_map(|(left, right)| [left.to_le_bytes(), right.to_le_bytes()])
+            .flatten()
+            .collect();
+
+        assert_eq!(expected, Codec::Flac.decompress(&data, 12).unwrap());
+    }
+}
@@ -0,0 +1,69 @@
+// Reed-Solomon P and Q parity of raw CD sectors (ECMA-130 annex A), regenerated for
+// sectors the CD codecs stored without it
+
+const P_OFFSET: usize = 0x81c;
+const P_BYTES: usize = 86;
+const P_COMPONENTS: usize = 24;
+const Q_OFFSET: usize = P_OFFSET + 2 * P_BYTES;
+const Q_BYTES: usize = 52;
+const Q_COMPONENTS: usize = 43;
+/// parity covers the sector from its header on
+const HEADER_OFFSET: usize = 12;
+const MODE_OFFSET: usize = 15;
+
+/// (multiply by α, divide by 1 + α) in GF(2^8)
+const fn tables() -> ([u8; 256], [u8; 256]) {
+    let mut low = [0; 256];
+    let mut high = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let j = ((i << 1) ^ if i & 0x80 != 0 { 0x11d } else { 0 }) as u8;
+        low[i] = j;
+        high[i ^ j as usize] = i as u8;
+        i += 1;
+    }
+    (low, high)
+}
+
+const TABLES: ([u8; 256], [u8; 256]) = tables();
+
+fn source_byte(sector: &[u8], offset: usize) -> u8 {
+    // mode 2 sectors compute the parity with a zeroed header
+    if sector[MODE_OFFSET] == 2 && offset < 4 {
+        0
+    } else {
+        sector[HEADER_OFFSET + offset]
+    }
+}
+
+fn compute(sector: &[u8], offsets: impl Iterator<Item = usize>) -> (u8, u8) {
+    let (low, high) = &TABLES;
+    let (mut first, mut second) = (0u8, 0u8);
+
+    for offset in offsets {
+        let byte = source_byte(sector, offset);
+        first = low[(first ^ byte) as usize];
+        second ^= byte;
+    }
+
+    let first = high[(low[first as usize] ^ second) as usize];
+    (first, second ^ first)
+}
+
+/// Writes the P and Q parity of a raw 2352 byte sector
+pub fn generate(sector: &mut [u8]) {
+    for byte in 0..P_BYTES {
+        let offsets = (0..P_COMPONENTS).map(|component| 2 * (43 * component + byte / 2) + byte % 2);
+        let (first, second) = compute(sector, offsets);
+        sector[P_OFFSET + byte] = first;
+        sector[P_OFFSET + P_BYTES + byte] = second;
+    }
+
+    for byte in 0..Q_BYTES {
+        let offsets = (0..Q_COMPONENTS)
+            .map(|component| 2 * ((44 * component + 43 * (byte / 2)) % 1118) + byte % 2);
+        let (first, second) = compute(sector, offsets);
+        sector[Q_OFFSET + byte] = first;
+        sector[Q_OFFSET + Q_BYTES + byte] = second;
+    }
+}
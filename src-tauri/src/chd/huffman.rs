@@ -0,0 +1,147 @@
+use std::io;
+
+use super::invalid_chd;
+
+/// Reads bits most significant first, reading past the end yields zeros like MAME's bitstream
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    pub fn peek(&self, bits: u32) -> u32 {
+        (0..bits as usize).fold(0, |value, index| {
+            let position = self.position + index;
+            let bit = self
+                .data
+                .get(position / 8)
+                .map_or(0, |byte| (byte >> (7 - position % 8)) & 1);
+            (value << 1) | bit as u32
+        })
+    }
+
+    pub fn read(&mut self, bits: u32) -> u32 {
+        let value = self.peek(bits);
+        self.position += bits as usize;
+        value
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.position > self.data.len() * 8
+    }
+}
+
+/// Canonical Huffman decoder with the RLE encoded tree of MAME's `huffman_decoder`
+pub struct HuffmanDecoder {
+    max_bits: u32,
+    /// (code, length) for every `max_bits` wide input
+    lookup: Vec<(u32, u32)>,
+}
+
+impl HuffmanDecoder {
+    pub fn import_tree_rle(
+        bits: &mut BitReader,
+        num_codes: usize,
+        max_bits: u32,
+    ) -> io::Result<Self> {
+        let entry_bits = match max_bits {
+            16.. => 5,
+            8.. => 4,
+            _ => 3,
+        };
+
+        let mut lengths = Vec::with_capacity(num_codes);
+        while lengths.len() < num_codes {
+            let length = bits.read(entry_bits);
+            if length != 1 {
+                lengths.push(length);
+                continue;
+            }
+
+            // 1 escapes either a literal 1 or a repeated length
+            let length = bits.read(entry_bits);
+            if length == 1 {
+                lengths.push(length);
+            } else {
+                let count = bits.read(entry_bits) + 3;
+                lengths.extend((0..count).map(|_| length));
+            }
+        }
+        if lengths.len() != num_codes || bits.overflowed() {
+            return Err(invalid_chd("invalid huffman tree"));
+        }
+
+        Self::from_lengths(&lengths, max_bits)
+    }
+
+    fn from_lengths(lengths: &[u32], max_bits: u32) -> io::Result<Self> {
+        let mut histogram = [0u32; 33];
+        for &length in lengths {
+            if length > max_bits {
+                return Err(invalid_chd("invalid huffman code length"));
+            }
+            histogram[length as usize] += 1;
+        }
+
+        // the first code of every length, assigned from the longest codes up
+        let mut start = 0;
+        for length in (1..=32).rev() {
+            let next = (start + histogram[length]) >> 1;
+            if length != 1 && next * 2 != start + histogram[length] {
+                return Err(invalid_chd("incomplete huffman tree"));
+            }
+            histogram[length] = start;
+            start = next;
+        }
+
+        let mut lookup = vec![(0, 0); 1 << max_bits];
+        for (code, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let bits = histogram[length as usize];
+            histogram[length as usize] += 1;
+
+            let shift = max_bits - length;
+            let first = (bits << shift) as usize;
+            for entry in &mut lookup[first..first + (1 << shift)] {
+                *entry = (code as u32, length);
+            }
+        }
+
+        Ok(HuffmanDecoder { max_bits, lookup })
+    }
+
+    pub fn decode_one(&self, bits: &mut BitReader) -> u32 {
+        let (code, length) = self.lookup[bits.peek(self.max_bits) as usize];
+        bits.read(length);
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_tree_rle() {
+        // 16 codes of 4 bit lengths: codes 0 and 4 are one bit long, a literal 1 is `1 1`
+        let mut data = vec![0x11, 0x00, 0x01, 0x10];
+        data.extend([0; 5]);
+        data.extend([0b0110_1000]);
+        let mut bits = BitReader::new(&data);
+
+        let decoder = HuffmanDecoder::import_tree_rle(&mut bits, 16, 8).unwrap();
+
+        assert_eq!(
+            vec![0, 4, 4, 0, 4],
+            (0..5)
+                .map(|_| decoder.decode_one(&mut bits))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,720 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    hashing::{hash_reader, to_hex, FileHashes},
+    models::{Rom, RomTrack},
+    routes::disc_routes,
+};
+
+use self::{
+    cdrom::{CdTrack, TrackReader},
+    codecs::Codec,
+    huffman::{BitReader, HuffmanDecoder},
+};
+
+pub mod cdrom;
+pub mod codecs;
+mod ecc;
+mod huffman;
+
+const HEADER_TAG: &[u8; 8] = b"MComprHD";
+const HEADER_SIZE: usize = 124;
+const MAP_HEADER_SIZE: usize = 16;
+const METADATA_HEADER_SIZE: usize = 16;
+
+// hunk types of the compressed map, the pseudo types only appear in the encoded map
+const COMPRESSION_TYPE_3: u8 = 3;
+const COMPRESSION_NONE: u8 = 4;
+const COMPRESSION_SELF: u8 = 5;
+const COMPRESSION_PARENT: u8 = 6;
+const COMPRESSION_RLE_SMALL: u32 = 7;
+const COMPRESSION_RLE_LARGE: u32 = 8;
+const COMPRESSION_SELF_0: u8 = 9;
+const COMPRESSION_SELF_1: u8 = 10;
+const COMPRESSION_PARENT_SELF: u8 = 11;
+const COMPRESSION_PARENT_0: u8 = 12;
+const COMPRESSION_PARENT_1: u8 = 13;
+
+pub(crate) fn invalid_chd(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid CHD: {}", message),
+    )
+}
+
+/// CRC-16/CCITT, which CHD uses for the map and every hunk
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn be(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+#[derive(Debug, Clone)]
+pub struct ChdHeader {
+    pub codecs: [Option<Codec>; 4],
+    pub logical_bytes: u64,
+    pub map_offset: u64,
+    pub meta_offset: u64,
+    pub hunk_bytes: u32,
+    pub unit_bytes: u32,
+    /// SHA-1 of the data without metadata, all zeros if it was never computed
+    pub raw_sha1: String,
+    pub sha1: String,
+    /// set for CHDs storing only the differences to a parent CHD
+    pub parent_sha1: Option<String>,
+}
+
+impl ChdHeader {
+    pub fn parse(header: &[u8]) -> io::Result<Self> {
+        if header.len() < HEADER_SIZE || &header[..8] != HEADER_TAG {
+            return Err(invalid_chd("missing MComprHD tag"));
+        }
+        let version = be(&header[12..16]);
+        if version != 5 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported CHD version {}", version),
+            ));
+        }
+
+        let mut codecs = [None; 4];
+        for (index, codec) in codecs.iter_mut().enumerate() {
+            *codec = Codec::from_tag(be(&header[16 + index * 4..20 + index * 4]) as u32)?;
+        }
+        let parent_sha1 = Some(&header[104..124])
+            .filter(|sha1| sha1.iter().any(|&byte| byte != 0))
+            .map(to_hex);
+
+        let header = ChdHeader {
+            codecs,
+            logical_bytes: be(&header[32..40]),
+            map_offset: be(&header[40..48]),
+            meta_offset: be(&header[48..56]),
+            hunk_bytes: be(&header[56..60]) as u32,
+            unit_bytes: be(&header[60..64]) as u32,
+            raw_sha1: to_hex(&header[64..84]),
+            sha1: to_hex(&header[84..104]),
+            parent_sha1,
+        };
+        if header.hunk_bytes == 0 || header.unit_bytes == 0 {
+            return Err(invalid_chd("zero hunk size"));
+        }
+
+        Ok(header)
+    }
+
+    pub fn hunk_count(&self) -> u64 {
+        self.logical_bytes.div_ceil(self.hunk_bytes as u64)
+    }
+
+    pub fn has_raw_sha1(&self) -> bool {
+        self.raw_sha1.bytes().any(|digit| digit != b'0')
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Hunk {
+    Compressed {
+        codec: usize,
+        offset: u64,
+        length: u32,
+        crc: u16,
+    },
+    Uncompressed {
+        offset: u64,
+        crc: Option<u16>,
+    },
+    Zeros,
+    /// a copy of an earlier hunk
+    Copy(u64),
+    Parent,
+}
+
+/// A MAME CHD v5 file, read hunk by hunk
+pub struct Chd {
+    file: BufReader<File>,
+    pub header: ChdHeader,
+    map: Vec<Hunk>,
+    cache: Option<(u64, Vec<u8>)>,
+}
+
+impl Chd {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let header = ChdHeader::parse(&header)?;
+
+        let mut chd = Chd {
+            file,
+            header,
+            map: Vec::new(),
+            cache: None,
+        };
+        chd.map = if chd.header.codecs[0].is_some() {
+            chd.read_compressed_map()?
+        } else {
+            chd.read_uncompressed_map()?
+        };
+
+        Ok(chd)
+    }
+
+    fn read_at(&mut self, offset: u64, length: usize) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; length];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    fn read_uncompressed_map(&mut self) -> io::Result<Vec<Hunk>> {
+        let count = self.header.hunk_count() as usize;
+        let map = self.read_at(self.header.map_offset, count * 4)?;
+
+        Ok(map
+            .chunks(4)
+            .map(|entry| match be(entry) {
+                0 => Hunk::Zeros,
+                block => Hunk::Uncompressed {
+                    offset: block * self.header.hunk_bytes as u64,
+                    crc: None,
+                },
+            })
+            .collect())
+    }
+
+    fn read_compressed_map(&mut self) -> io::Result<Vec<Hunk>> {
+        let map_header = self.read_at(self.header.map_offset, MAP_HEADER_SIZE)?;
+        let length = be(&map_header[0..4]) as usize;
+        let first_offset = be(&map_header[4..10]);
+        let map_crc = be(&map_header[10..12]) as u16;
+        let (length_bits, self_bits, parent_bits) = (
+            map_header[12] as u32,
+            map_header[13] as u32,
+            map_header[14] as u32,
+        );
+
+        let compressed = self.read_at(self.header.map_offset + MAP_HEADER_SIZE as u64, length)?;
+        let mut bits = BitReader::new(&compressed);
+        let count = self.header.hunk_count() as usize;
+        let hunk_bytes = self.header.hunk_bytes as u64;
+        let unit_bytes = self.header.unit_bytes as u64;
+
+        // the hunk types come first, Huffman coded with runs of the previous type
+        let decoder = HuffmanDecoder::import_tree_rle(&mut bits, 16, 8)?;
+        let mut types = Vec::with_capacity(count);
+        let mut last = 0;
+        while types.len() < count {
+            let repeat = match decoder.decode_one(&mut bits) {
+                COMPRESSION_RLE_SMALL => 2 + decoder.decode_one(&mut bits),
+                COMPRESSION_RLE_LARGE => {
+                    2 + 16 + (decoder.decode_one(&mut bits) << 4) + decoder.decode_one(&mut bits)
+                }
+                value => {
+                    last = value as u8;
+                    types.push(last);
+                    continue;
+                }
+            };
+            // a run covers this hunk and `repeat` more
+            types.extend((0..=repeat).map(|_| last));
+        }
+        types.truncate(count);
+
+        // then the locations, partly relative to the previous hunks
+        let mut map = Vec::with_capacity(count);
+        let mut raw_map = Vec::with_capacity(count * 12);
+        let mut offset = first_offset;
+        let (mut last_self, mut last_parent) = (0, 0);
+
+        for (index, &kind) in types.iter().enumerate() {
+            let (mut kind, mut length, mut location, mut crc) = (kind, 0, offset, 0);
+            match kind {
+                0..=COMPRESSION_TYPE_3 => {
+                    length = bits.read(length_bits);
+                    offset += length as u64;
+                    crc = bits.read(16) as u16;
+                }
+                COMPRESSION_NONE => {
+                    length = hunk_bytes as u32;
+                    offset += hunk_bytes;
+                    crc = bits.read(16) as u16;
+                }
+                COMPRESSION_SELF => {
+                    location = bits.read(self_bits) as u64;
+                    last_self = location;
+                }
+                COMPRESSION_PARENT => {
+                    location = bits.read(parent_bits) as u64;
+                    last_parent = location;
+                }
+                COMPRESSION_SELF_0 | COMPRESSION_SELF_1 => {
+                    if kind == COMPRESSION_SELF_1 {
+                        last_self += 1;
+                    }
+                    kind = COMPRESSION_SELF;
+                    location = last_self;
+                }
+                COMPRESSION_PARENT_SELF => {
+                    kind = COMPRESSION_PARENT;
+                    location = index as u64 * hunk_bytes / unit_bytes;
+                    last_parent = location;
+                }
+                COMPRESSION_PARENT_0 | COMPRESSION_PARENT_1 => {
+                    if kind == COMPRESSION_PARENT_1 {
+                        last_parent += hunk_bytes / unit_bytes;
+                    }
+                    kind = COMPRESSION_PARENT;
+                    location = last_parent;
+                }
+                _ => return Err(invalid_chd("unknown hunk type in map")),
+            }
+
+            raw_map.push(kind);
+            raw_map.extend(&length.to_be_bytes()[1..]);
+            raw_map.extend(&location.to_be_bytes()[2..]);
+            raw_map.extend(crc.to_be_bytes());
+
+            map.push(match kind {
+                0..=COMPRESSION_TYPE_3 => Hunk::Compressed {
+                    codec: kind as usize,
+                    offset: location,
+                    length,
+                    crc,
+                },
+                COMPRESSION_NONE => Hunk::Uncompressed {
+                    offset: location,
+                    crc: Some(crc),
+                },
+                COMPRESSION_SELF => Hunk::Copy(location),
+                _ => Hunk::Parent,
+            });
+        }
+
+        if bits.overflowed() || crc16(&raw_map) != map_crc {
+            return Err(invalid_chd("map checksum mismatch"));
+        }
+
+        Ok(map)
+    }
+
+    fn check_crc(data: Vec<u8>, crc: Option<u16>) -> io::Result<Vec<u8>> {
+        match crc {
+            Some(crc) if crc16(&data) != crc => Err(invalid_chd("hunk checksum mismatch")),
+            _ => Ok(data),
+        }
+    }
+
+    /// Reads and decompresses a hunk
+    pub fn read_hunk(&mut self, index: u64) -> io::Result<Vec<u8>> {
+        let hunk_bytes = self.header.hunk_bytes as usize;
+        let hunk = *self
+            .map
+            .get(index as usize)
+            .ok_or_else(|| invalid_chd("hunk out of range"))?;
+
+        match hunk {
+            Hunk::Compressed {
+                codec,
+                offset,
+                length,
+                crc,
+            } => {
+                let codec = self.header.codecs[codec]
+                    .ok_or_else(|| invalid_chd("hunk uses an unset compressor"))?;
+                let data = self.read_at(offset, length as usize)?;
+                Self::check_crc(codec.decompress(&data, hunk_bytes)?, Some(crc))
+            }
+            Hunk::Uncompressed { offset, crc } => {
+                let data = self.read_at(offset, hunk_bytes)?;
+                Self::check_crc(data, crc)
+            }
+            Hunk::Zeros => Ok(vec![0; hunk_bytes]),
+            Hunk::Copy(source) if source < index => self.read_hunk(source),
+            Hunk::Copy(_) => Err(invalid_chd("hunk copies a later hunk")),
+            Hunk::Parent => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "CHDs depending on a parent CHD are not supported",
+            )),
+        }
+    }
+
+    /// Copies logical data starting at `offset` into `buffer`, keeping the last hunk cached
+    /// for sequential reads
+    pub fn read_bytes(&mut self, mut offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let hunk_bytes = self.header.hunk_bytes as u64;
+        let mut written = 0;
+
+        while written < buffer.len() {
+            let index = offset / hunk_bytes;
+            if self
+                .cache
+                .as_ref()
+                .is_none_or(|(cached, _)| *cached != index)
+            {
+                let hunk = self.read_hunk(index)?;
+                self.cache = Some((index, hunk));
+            }
+            let (_, hunk) = self.cache.as_ref().unwrap();
+
+            let start = (offset % hunk_bytes) as usize;
+            let count = (hunk.len() - start).min(buffer.len() - written);
+            buffer[written..written + count].copy_from_slice(&hunk[start..start + count]);
+            written += count;
+            offset += count as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Returns all metadata entries as (tag, data)
+    pub fn metadata(&mut self) -> io::Result<Vec<([u8; 4], Vec<u8>)>> {
+        let mut entries = Vec::new();
+        let mut offset = self.header.meta_offset;
+
+        while offset != 0 {
+            let header = self.read_at(offset, METADATA_HEADER_SIZE)?;
+            let tag = [header[0], header[1], header[2], header[3]];
+            let length = be(&header[5..8]) as usize;
+            let data = self.read_at(offset + METADATA_HEADER_SIZE as u64, length)?;
+            entries.push((tag, data));
+
+            let next = be(&header[8..16]);
+            if next != 0 && next <= offset {
+                return Err(invalid_chd("metadata loops"));
+            }
+            offset = next;
+        }
+
+        Ok(entries)
+    }
+
+    /// Hashes the whole logical data, e.g. the ISO of a DVD image
+    pub fn hash_data(&mut self) -> io::Result<FileHashes> {
+        hash_reader(&mut DataReader {
+            chd: self,
+            offset: 0,
+        })
+    }
+}
+
+struct DataReader<'a> {
+    chd: &'a mut Chd,
+    offset: u64,
+}
+
+impl Read for DataReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.chd.header.logical_bytes - self.offset;
+        let length = (buffer.len() as u64).min(remaining) as usize;
+        self.chd.read_bytes(self.offset, &mut buffer[..length])?;
+        self.offset += length as u64;
+        Ok(length)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChdTrackReport {
+    pub number: u32,
+    #[serde(flatten)]
+    pub hashes: FileHashes,
+    /// the DAT track with the same hashes
+    pub matched: Option<RomTrack>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChdReport {
+    pub path: PathBuf,
+    /// the library rom the image was identified as
+    pub rom: Option<Rom>,
+    /// identified by the header's raw SHA-1, without decompressing anything
+    pub fast_path: bool,
+    /// the image holds every track of the rom with the right hashes
+    pub complete: bool,
+    pub tracks: Vec<ChdTrackReport>,
+}
+
+/// Hashes every track of a CD or GD-ROM image the way the tracks are dumped as `.bin` files
+pub fn track_hashes(chd: &mut Chd, tracks: &[CdTrack]) -> io::Result<Vec<FileHashes>> {
+    tracks
+        .iter()
+        .map(|track| hash_reader(&mut TrackReader::new(chd, track)))
+        .collect()
+}
+
+/// Identifies a CHD by its contents in the library. Disc images are matched track by track
+/// against the DAT's tracks, other images by the SHA-1 of their data, for which the
+/// header's raw SHA-1 is trusted if present.
+pub fn verify(conn: &mut SqliteConnection, path: &Path) -> io::Result<ChdReport> {
+    let mut chd = Chd::open(path)?;
+    let tracks = cdrom::tracks(&mut chd)?;
+
+    if tracks.is_empty() {
+        let fast_path = chd.header.has_raw_sha1();
+        let sha1 = if fast_path {
+            chd.header.raw_sha1.clone()
+        } else {
+            chd.hash_data()?.sha1
+        };
+        let rom = disc_routes::find_rom_by_sha1(conn, &sha1).map_err(io::Error::other)?;
+
+        return Ok(ChdReport {
+            path: path.to_path_buf(),
+            complete: rom.is_some(),
+            rom,
+            fast_path,
+            tracks: Vec::new(),
+        });
+    }
+
+    let hashes = track_hashes(&mut chd, &tracks)?;
+    let sha1s = hashes.iter().map(|hashes| hashes.sha1.clone()).collect();
+    let matches = disc_routes::find_tracks_by_sha1(conn, sha1s).map_err(io::Error::other)?;
+
+    let reports: Vec<ChdTrackReport> = tracks
+        .iter()
+        .zip(hashes)
+        .map(|(track, hashes)| ChdTrackReport {
+            number: track.number,
+            matched: matches
+                .iter()
+                .find(|rom_track| rom_track.matches(&hashes))
+                .cloned(),
+            hashes,
+        })
+        .collect();
+
+    let disc = match reports.iter().find_map(|report| report.matched.as_ref()) {
+        Some(track) => {
+            Some(disc_routes::get_rom_with_tracks(conn, track.rom_id).map_err(io::Error::other)?)
+        }
+        None => None,
+    };
+    let complete = disc.as_ref().is_some_and(|disc| {
+        disc.tracks.len() == reports.len()
+            && disc.tracks.iter().zip(&reports).all(|(track, report)| {
+                report
+                    .matched
+                    .as_ref()
+                    .is_some_and(|matched| matched.id == track.id)
+            })
+    });
+
+    Ok(ChdReport {
+        path: path.to_path_buf(),
+        rom: disc.map(|disc| disc.rom),
+        fast_path: false,
+        complete,
+        tracks: reports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use flate2::{write::DeflateEncoder, Compression};
+    use sha1::{Digest, Sha1};
+
+    use super::{
+        codecs::{CD_FRAME_SIZE, CD_SECTOR_SIZE},
+        *,
+    };
+
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        bits: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u64, bits: u32) {
+            for bit in (0..bits).rev() {
+                if self.bits.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                let byte = self.bytes.last_mut().unwrap();
+                *byte |= (((value >> bit) & 1) as u8) << (7 - self.bits % 8);
+                self.bits += 1;
+            }
+        }
+    }
+
+    fn header(
+        codecs: [&[u8; 4]; 4],
+        logical_bytes: u64,
+        map_offset: u64,
+        meta_offset: u64,
+        hunk_bytes: u32,
+        unit_bytes: u32,
+        raw_sha1: &[u8],
+    ) -> Vec<u8> {
+        let mut header = HEADER_TAG.to_vec();
+        header.extend((HEADER_SIZE as u32).to_be_bytes());
+        header.extend(5u32.to_be_bytes());
+        codecs.iter().for_each(|codec| header.extend(*codec));
+        header.extend(logical_bytes.to_be_bytes());
+        header.extend(map_offset.to_be_bytes());
+        header.extend(meta_offset.to_be_bytes());
+        header.extend(hunk_bytes.to_be_bytes());
+        header.extend(unit_bytes.to_be_bytes());
+        header.extend(raw_sha1);
+        header.extend([0; 40]);
+        header
+    }
+
+    #[test]
+    fn test_compressed_map() {
+        let hunk_bytes = 1024;
+        let first: Vec<u8> = (0..hunk_bytes).map(|i| (i % 7) as u8).collect();
+        let second = vec![0x55; hunk_bytes];
+        let logical: Vec<u8> = [&first[..], &second, &first].concat();
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&first).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        // hunks: zlib compressed, uncompressed, copy of the first
+        let data_start = HEADER_SIZE as u64;
+        let map_offset = data_start + (compressed.len() + hunk_bytes) as u64;
+        let mut bits = BitWriter::default();
+        // tree of 16 four bit lengths: type 0 has length 1, types 4 and 5 length 2
+        for length in [1, 1, 0, 0, 0, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] {
+            bits.write(length, 4);
+        }
+        bits.write(0b1, 1);
+        bits.write(0b00, 2);
+        bits.write(0b01, 2);
+        bits.write(compressed.len() as u64, 16);
+        bits.write(crc16(&first) as u64, 16);
+        bits.write(crc16(&second) as u64, 16);
+        bits.write(0, 8);
+
+        let mut raw_map = vec![0];
+        raw_map.extend(&(compressed.len() as u32).to_be_bytes()[1..]);
+        raw_map.extend(&data_start.to_be_bytes()[2..]);
+        raw_map.extend(crc16(&first).to_be_bytes());
+        raw_map.push(COMPRESSION_NONE);
+        raw_map.extend(&(hunk_bytes as u32).to_be_bytes()[1..]);
+        raw_map.extend(&(data_start + compressed.len() as u64).to_be_bytes()[2..]);
+        raw_map.extend(crc16(&second).to_be_bytes());
+        raw_map.push(COMPRESSION_SELF);
+        raw_map.extend([0; 11]);
+
+        let mut chd = header(
+            [b"zlib", &[0; 4], &[0; 4], &[0; 4]],
+            logical.len() as u64,
+            map_offset,
+            0,
+            hunk_bytes as u32,
+            512,
+            &Sha1::digest(&logical),
+        );
+        chd.extend(&compressed);
+        chd.extend(&second);
+        chd.extend((bits.bytes.len() as u32).to_be_bytes());
+        chd.extend(&data_start.to_be_bytes()[2..]);
+        chd.extend(crc16(&raw_map).to_be_bytes());
+        chd.extend([16, 8, 0, 0]);
+        chd.extend(&bits.bytes);
+
+        let path = std::env::temp_dir().join("romana_chd_compressed_map.chd");
+        fs::write(&path, &chd).unwrap();
+        let mut chd = Chd::open(&path).unwrap();
+        let hashes = chd.hash_data().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(chd.header.has_raw_sha1());
+        assert_eq!(chd.header.raw_sha1, hashes.sha1);
+        assert_eq!(logical.len() as u64, hashes.size);
+    }
+
+    #[test]
+    fn test_cd_tracks() {
+        let hunk_bytes = 4 * CD_FRAME_SIZE;
+        let frame = |sector: u8| {
+            let mut frame = vec![sector; CD_SECTOR_SIZE];
+            frame.extend([0xee; CD_FRAME_SIZE - CD_SECTOR_SIZE]);
+            frame
+        };
+        // a data track of 3 frames, padded to 4, and an audio track of 2 big endian frames
+        let mut data: Vec<u8> = (0..3).flat_map(|_| frame(0x11)).collect();
+        data.extend(vec![0; CD_FRAME_SIZE]);
+        let mut audio = frame(0x22);
+        audio[..4].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        data.extend(&audio);
+        data.extend(frame(0x22));
+        data.resize(2 * hunk_bytes, 0);
+
+        let mut metadata = Vec::new();
+        let entries = [
+            "TRACK:1 TYPE:MODE1_RAW SUBTYPE:NONE FRAMES:3 PREGAP:0 PGTYPE:MODE1 PGSUB:RW POSTGAP:0\0",
+            "TRACK:2 TYPE:AUDIO SUBTYPE:NONE FRAMES:2 PREGAP:150 PGTYPE:VAUDIO PGSUB:RW POSTGAP:0\0",
+        ];
+        let meta_offset = (3 * hunk_bytes + 8) as u64;
+        for (index, entry) in entries.iter().enumerate() {
+            let next = match index {
+                0 => meta_offset + (METADATA_HEADER_SIZE + entry.len()) as u64,
+                _ => 0,
+            };
+            metadata.extend(b"CHT2");
+            metadata.extend((entry.len() as u32 | 0x01000000).to_be_bytes());
+            metadata.extend(next.to_be_bytes());
+            metadata.extend(entry.as_bytes());
+        }
+
+        let mut chd = header(
+            [&[0; 4]; 4],
+            6 * CD_FRAME_SIZE as u64,
+            (3 * hunk_bytes) as u64,
+            meta_offset,
+            hunk_bytes as u32,
+            CD_FRAME_SIZE as u32,
+            &[0; 20],
+        );
+        chd.resize(hunk_bytes, 0);
+        chd.extend(&data);
+        chd.extend(1u32.to_be_bytes());
+        chd.extend(2u32.to_be_bytes());
+        chd.extend(&metadata);
+
+        let path = std::env::temp_dir().join("romana_chd_cd_tracks.chd");
+        fs::write(&path, &chd).unwrap();
+        let mut chd = Chd::open(&path).unwrap();
+        let tracks = cdrom::tracks(&mut chd).unwrap();
+        let hashes = track_hashes(&mut chd, &tracks).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            vec![(1, 0), (2, 4)],
+            tracks
+                .iter()
+                .map(|track| (track.number, track.start_frame))
+                .collect::<Vec<_>>()
+        );
+
+        let mut expected = vec![0x34, 0x12, 0x78, 0x56];
+        expected.resize(2 * CD_SECTOR_SIZE, 0x22);
+        assert_eq!(to_hex(&Sha1::digest(&expected)), hashes[1].sha1);
+        assert_eq!(3 * CD_SECTOR_SIZE as u64, hashes[0].size);
+    }
+}
@@ -0,0 +1,458 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use flate2::{write::DeflateEncoder, Compression, Decompress, FlushDecompress};
+
+use super::{invalid_image, CompressionFormat};
+use crate::hashing::{FileHashes, Hasher};
+
+pub const HEADER_SIZE: usize = 0x18;
+/// the high bit of an index entry flags the block, the rest is its offset
+const FLAG_BIT: u32 = 0x8000_0000;
+const MAX_OFFSET: u64 = FLAG_BIT as u64;
+
+/// The 24 byte header shared by CSO and ZSO, all fields little endian
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub format: CompressionFormat,
+    pub total_bytes: u64,
+    pub block_size: u32,
+    /// index entries are offsets shifted right by `align`
+    pub align: u8,
+}
+
+impl Header {
+    pub fn parse(data: &[u8; HEADER_SIZE]) -> io::Result<Self> {
+        let format = match (&data[..4], data[20]) {
+            (b"CISO", 0 | 1) => CompressionFormat::CsoV1,
+            (b"CISO", 2) => CompressionFormat::CsoV2,
+            (b"ZISO", _) => CompressionFormat::Zso,
+            _ => return Err(invalid_image("not a CSO or ZSO image")),
+        };
+        let block_size = u32::from_le_bytes(data[16..20].try_into().unwrap());
+        if block_size == 0 {
+            return Err(invalid_image("invalid block size"));
+        }
+
+        Ok(Header {
+            format,
+            total_bytes: u64::from_le_bytes(data[8..16].try_into().unwrap()),
+            block_size,
+            align: data[21],
+        })
+    }
+
+    pub fn render(&self) -> [u8; HEADER_SIZE] {
+        let mut data = [0; HEADER_SIZE];
+        data[..4].copy_from_slice(self.format.magic());
+        data[4..8].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        data[8..16].copy_from_slice(&self.total_bytes.to_le_bytes());
+        data[16..20].copy_from_slice(&self.block_size.to_le_bytes());
+        data[20] = self.format.version();
+        data[21] = self.align;
+        data
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.total_bytes.div_ceil(self.block_size as u64) as usize
+    }
+
+    /// Bytes of the block once decompressed, only the last one may be shorter
+    fn block_bytes(&self, block: usize) -> usize {
+        let start = block as u64 * self.block_size as u64;
+        (self.total_bytes - start).min(self.block_size as u64) as usize
+    }
+}
+
+/// How a block is stored in the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    Plain,
+    Deflate,
+    Lz4,
+}
+
+impl Block {
+    /// CSO v1 flags plain blocks and deflates the others, ZSO does the same with LZ4. CSO v2
+    /// flags LZ4 blocks and stores blocks plain that are not smaller than `block_size`.
+    fn from_entry(format: CompressionFormat, flagged: bool, length: u64, block_size: u32) -> Self {
+        match format {
+            CompressionFormat::CsoV1 if flagged => Block::Plain,
+            CompressionFormat::CsoV1 => Block::Deflate,
+            CompressionFormat::Zso if flagged => Block::Plain,
+            CompressionFormat::Zso => Block::Lz4,
+            CompressionFormat::CsoV2 if length >= block_size as u64 => Block::Plain,
+            CompressionFormat::CsoV2 if flagged => Block::Lz4,
+            CompressionFormat::CsoV2 => Block::Deflate,
+        }
+    }
+
+    fn flagged(&self, format: CompressionFormat) -> bool {
+        match format {
+            CompressionFormat::CsoV1 | CompressionFormat::Zso => *self == Block::Plain,
+            CompressionFormat::CsoV2 => *self == Block::Lz4,
+        }
+    }
+}
+
+/// Raw deflate without zlib header. Blocks are padded for the alignment, the padding after
+/// the end of the stream is ignored.
+fn inflate(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    Decompress::new(false)
+        .decompress_vec(data, &mut output, FlushDecompress::Finish)
+        .map_err(|e| invalid_image(&e.to_string()))?;
+
+    if output.len() != size {
+        return Err(invalid_image("deflate block has the wrong size"));
+    }
+    Ok(output)
+}
+
+fn deflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decodes an LZ4 block until `size` bytes are written, like `LZ4_decompress_fast` readers
+/// of ZSO do, as the alignment padding after the block is no valid LZ4 sequence
+fn lz4_decompress(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    let mut input = data.iter().copied();
+    let mut next = || {
+        input
+            .next()
+            .ok_or_else(|| invalid_image("lz4 block ended early"))
+    };
+
+    let read_length = |length: usize, next: &mut dyn FnMut() -> io::Result<u8>| {
+        let mut length = length;
+        if length == 15 {
+            loop {
+                let byte = next()?;
+                length += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+        }
+        Ok::<_, io::Error>(length)
+    };
+
+    while output.len() < size {
+        let token = next()?;
+
+        let literals = read_length((token >> 4) as usize, &mut next)?;
+        for _ in 0..literals {
+            output.push(next()?);
+        }
+        if output.len() >= size {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([next()?, next()?]) as usize;
+        if offset == 0 || offset > output.len() {
+            return Err(invalid_image("invalid lz4 match offset"));
+        }
+        let length = read_length((token & 0x0f) as usize, &mut next)? + 4;
+        // matches may overlap the bytes they write
+        let start = output.len() - offset;
+        for index in start..start + length {
+            output.push(output[index]);
+        }
+    }
+
+    if output.len() != size {
+        return Err(invalid_image("lz4 block has the wrong size"));
+    }
+    Ok(output)
+}
+
+/// Compresses a block, `None` if it is stored plain
+fn compress_block(format: CompressionFormat, data: &[u8]) -> io::Result<Option<(Block, Vec<u8>)>> {
+    let candidates = match format {
+        CompressionFormat::CsoV1 => vec![(Block::Deflate, deflate(data)?)],
+        CompressionFormat::Zso => vec![(Block::Lz4, lz4_flex::block::compress(data))],
+        CompressionFormat::CsoV2 => vec![
+            (Block::Deflate, deflate(data)?),
+            (Block::Lz4, lz4_flex::block::compress(data)),
+        ],
+    };
+
+    Ok(candidates
+        .into_iter()
+        .min_by_key(|(_, compressed)| compressed.len())
+        .filter(|(_, compressed)| compressed.len() < data.len()))
+}
+
+/// Smallest alignment that keeps every offset of the worst case image below 2^31
+fn alignment(total_bytes: u64, block_size: u32) -> io::Result<u8> {
+    let blocks = total_bytes.div_ceil(block_size as u64);
+    let index_bytes = (blocks + 1) * 4;
+
+    (0..32)
+        .find(|align| {
+            let padding = (1u64 << align) - 1;
+            let bound = HEADER_SIZE as u64 + index_bytes + blocks * (block_size as u64 + padding);
+            bound >> align < MAX_OFFSET
+        })
+        .ok_or_else(|| invalid_image("image too large"))
+}
+
+/// Compresses `total_bytes` of `reader` into `writer`, returning the hashes of the input
+pub fn compress(
+    reader: &mut impl Read,
+    writer: &mut (impl Write + Seek),
+    total_bytes: u64,
+    format: CompressionFormat,
+    block_size: u32,
+) -> io::Result<FileHashes> {
+    let header = Header {
+        format,
+        total_bytes,
+        block_size,
+        align: alignment(total_bytes, block_size)?,
+    };
+    let alignment = 1u64 << header.align;
+    let blocks = header.blocks();
+
+    writer.write_all(&header.render())?;
+    let index_start = HEADER_SIZE as u64;
+    writer.write_all(&vec![0; (blocks + 1) * 4])?;
+    let mut position = index_start + (blocks as u64 + 1) * 4;
+
+    let mut hasher = Hasher::default();
+    let mut index = Vec::with_capacity(blocks + 1);
+    let mut data = vec![0; block_size as usize];
+
+    for block in 0..blocks {
+        let padding = position.next_multiple_of(alignment) - position;
+        writer.write_all(&vec![0; padding as usize])?;
+        position += padding;
+
+        let bytes = header.block_bytes(block);
+        reader.read_exact(&mut data[..bytes])?;
+        hasher.update(&data[..bytes]);
+
+        let (kind, stored) = match compress_block(format, &data[..bytes])? {
+            // CSO v2 readers take padded blocks of `block_size` bytes or more as plain
+            Some((kind, compressed))
+                if format != CompressionFormat::CsoV2
+                    || compressed.len().next_multiple_of(alignment as usize)
+                        < block_size as usize =>
+            {
+                (kind, compressed)
+            }
+            // CSO v2 tells plain blocks by their size, so the last one is padded as well
+            _ if format == CompressionFormat::CsoV2 => {
+                data[bytes..].fill(0);
+                (Block::Plain, data.clone())
+            }
+            _ => (Block::Plain, data[..bytes].to_vec()),
+        };
+
+        let flag = if kind.flagged(format) { FLAG_BIT } else { 0 };
+        index.push((position >> header.align) as u32 | flag);
+        writer.write_all(&stored)?;
+        position += stored.len() as u64;
+    }
+    index.push((position >> header.align) as u32);
+
+    writer.seek(SeekFrom::Start(index_start))?;
+    writer.write_all(
+        &index
+            .iter()
+            .flat_map(|entry| entry.to_le_bytes())
+            .collect::<Vec<_>>(),
+    )?;
+    writer.seek(SeekFrom::Start(position))?;
+    writer.flush()?;
+
+    Ok(hasher.finish())
+}
+
+/// Reads a CSO or ZSO image as the original ISO
+pub struct CisoReader<R> {
+    reader: R,
+    header: Header,
+    index: Vec<u32>,
+    /// the decompressed block in `data`
+    block: Option<usize>,
+    data: Vec<u8>,
+    /// position in the original ISO
+    position: u64,
+}
+
+impl<R: Read + Seek> CisoReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut data = [0; HEADER_SIZE];
+        reader.read_exact(&mut data)?;
+        let header = Header::parse(&data)?;
+
+        let mut index = vec![0; (header.blocks() + 1) * 4];
+        reader.read_exact(&mut index)?;
+        let index = index
+            .chunks_exact(4)
+            .map(|entry| u32::from_le_bytes(entry.try_into().unwrap()))
+            .collect();
+
+        Ok(CisoReader {
+            reader,
+            header,
+            index,
+            block: None,
+            data: Vec::new(),
+            position: 0,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn read_block(&mut self, block: usize) -> io::Result<Vec<u8>> {
+        let offset = |entry: u32| ((entry & !FLAG_BIT) as u64) << self.header.align;
+        let start = offset(self.index[block]);
+        let end = offset(self.index[block + 1]);
+        if end < start {
+            return Err(invalid_image("invalid block index"));
+        }
+
+        let mut stored = vec![0; (end - start) as usize];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut stored)?;
+
+        let bytes = self.header.block_bytes(block);
+        let flagged = self.index[block] & FLAG_BIT != 0;
+        match Block::from_entry(
+            self.header.format,
+            flagged,
+            end - start,
+            self.header.block_size,
+        ) {
+            Block::Plain if stored.len() < bytes => Err(invalid_image("plain block too short")),
+            Block::Plain => {
+                stored.truncate(bytes);
+                Ok(stored)
+            }
+            Block::Deflate => inflate(&stored, bytes),
+            Block::Lz4 => lz4_decompress(&stored, bytes),
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for CisoReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.header.total_bytes {
+            return Ok(0);
+        }
+
+        let block = (self.position / self.header.block_size as u64) as usize;
+        if self.block != Some(block) {
+            self.data = self.read_block(block)?;
+            self.block = Some(block);
+        }
+
+        let start = (self.position % self.header.block_size as u64) as usize;
+        let count = buffer.len().min(self.data.len() - start);
+        buffer[..count].copy_from_slice(&self.data[start..start + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+/// Seeking only decompresses the block read next, so disc images can be inspected without
+/// decompressing them
+impl<R: Read + Seek> Seek for CisoReader<R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.header.total_bytes.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the image",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::hashing::hash_reader;
+
+    /// Compressible sectors mixed with noise that is stored plain, ending in a partial block
+    fn image() -> Vec<u8> {
+        let mut seed = 0x1234_5678u32;
+        (0..5 * 2048 + 700)
+            .map(|i| {
+                if (i / 2048) % 2 == 0 {
+                    (i % 7) as u8
+                } else {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    seed as u8
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = image();
+        let expected = hash_reader(&mut data.as_slice()).unwrap();
+
+        for format in [
+            CompressionFormat::CsoV1,
+            CompressionFormat::CsoV2,
+            CompressionFormat::Zso,
+        ] {
+            let mut image = Cursor::new(Vec::new());
+            let hashes = compress(
+                &mut data.as_slice(),
+                &mut image,
+                data.len() as u64,
+                format,
+                2048,
+            )
+            .unwrap();
+            assert_eq!(expected, hashes);
+            assert!(image.get_ref().len() < data.len());
+
+            image.set_position(0);
+            let mut reader = CisoReader::new(image).unwrap();
+            assert_eq!(format, reader.header().format);
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output).unwrap();
+            assert_eq!(data, output, "{:?}", format);
+
+            let mut sector = [0; 16];
+            reader.seek(SeekFrom::Start(3 * 2048 + 5)).unwrap();
+            reader.read_exact(&mut sector).unwrap();
+            assert_eq!(data[3 * 2048 + 5..3 * 2048 + 21], sector);
+        }
+    }
+
+    #[test]
+    fn test_lz4_padding() {
+        let data: Vec<u8> = (0..4096).map(|i| (i % 13) as u8).collect();
+        let mut block = lz4_flex::block::compress(&data);
+        block.extend([0; 16]);
+
+        assert_eq!(data, lz4_decompress(&block, data.len()).unwrap());
+    }
+
+    #[test]
+    fn test_alignment() {
+        assert_eq!(0, alignment(650 * 1024 * 1024, 2048).unwrap());
+        assert_eq!(2, alignment(6 * 1024 * 1024 * 1024, 2048).unwrap());
+    }
+}
@@ -0,0 +1,452 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cartridge::n64::{self, ByteOrder},
+    config::AppConfig,
+    hashing::{hash_reader, FileHashes, Hasher},
+    models::{CompressedRom, Console, NewCompressedRom, Rom},
+    routes::{compression_routes, patch_routes},
+};
+
+pub mod ciso;
+
+pub const DEFAULT_BLOCK_SIZE: u32 = 2048;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionFormat {
+    /// deflate blocks, read by every CSO capable emulator
+    CsoV1,
+    /// deflate or LZ4 blocks, whichever is smaller
+    CsoV2,
+    /// LZ4 blocks
+    Zso,
+}
+
+impl CompressionFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionFormat::CsoV1 => "cso_v1",
+            CompressionFormat::CsoV2 => "cso_v2",
+            CompressionFormat::Zso => "zso",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompressionFormat::CsoV1 | CompressionFormat::CsoV2 => "cso",
+            CompressionFormat::Zso => "zso",
+        }
+    }
+
+    fn magic(&self) -> &'static [u8; 4] {
+        match self {
+            CompressionFormat::CsoV1 | CompressionFormat::CsoV2 => b"CISO",
+            CompressionFormat::Zso => b"ZISO",
+        }
+    }
+
+    fn version(&self) -> u8 {
+        match self {
+            CompressionFormat::CsoV1 | CompressionFormat::Zso => 1,
+            CompressionFormat::CsoV2 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RomFileStatus {
+    Have,
+    Missing,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RomFileReport {
+    pub rom: Rom,
+    pub status: RomFileStatus,
+    /// the rom file, or the compressed image standing in for it
+    pub path: Option<PathBuf>,
+    pub compressed: bool,
+}
+
+fn invalid_image(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Block sizes have to be powers of two of at least a sector
+fn check_block_size(block_size: u32) -> io::Result<()> {
+    if block_size < 2048 || !block_size.is_power_of_two() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid block size {}, expected a power of two of at least 2048",
+                block_size
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Compares the size and hashes the DAT lists for the rom. Values the DAT leaves out are
+/// skipped, but at least one hash has to be listed and match.
+fn matches_rom(rom: &Rom, hashes: &FileHashes) -> bool {
+    let listed: Vec<(&str, &str)> = [
+        (rom.crc.as_str(), hashes.crc32.as_str()),
+        (rom.md5.as_str(), hashes.md5.as_str()),
+        (rom.sha1.as_str(), hashes.sha1.as_str()),
+    ]
+    .into_iter()
+    .filter(|(expected, _)| !expected.is_empty())
+    .collect();
+
+    (rom.size == 0 || rom.size as u64 == hashes.size)
+        && !listed.is_empty()
+        && listed
+            .iter()
+            .all(|(expected, actual)| expected.eq_ignore_ascii_case(actual))
+}
+
+/// Compresses an ISO to a CSO or ZSO file, returning the hashes of the ISO
+pub fn compress_file(
+    input: &Path,
+    output: &Path,
+    format: CompressionFormat,
+    block_size: u32,
+) -> io::Result<FileHashes> {
+    check_block_size(block_size)?;
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    let total_bytes = fs::metadata(input)?.len();
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    ciso::compress(&mut reader, &mut writer, total_bytes, format, block_size).inspect_err(|_| {
+        let _ = fs::remove_file(output);
+    })
+}
+
+/// Returns the hashes of the ISO a CSO or ZSO file decompresses to
+pub fn hash_image(path: &Path) -> io::Result<FileHashes> {
+    hash_reader(&mut ciso::CisoReader::new(BufReader::new(File::open(
+        path,
+    )?))?)
+}
+
+/// Decompresses a CSO or ZSO file to an ISO, returning the hashes of the ISO
+pub fn decompress_file(input: &Path, output: &Path) -> io::Result<FileHashes> {
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    let mut reader = ciso::CisoReader::new(BufReader::new(File::open(input)?))?;
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    copy_hashed(&mut reader, &mut writer).inspect_err(|_| {
+        let _ = fs::remove_file(output);
+    })
+}
+
+/// Copies the whole input, hashing it on the way
+fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<FileHashes> {
+    let mut hasher = Hasher::default();
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+    writer.flush()?;
+
+    Ok(hasher.finish())
+}
+
+/// Records a compressed file of the rom with the hashes of the ISO it holds
+fn register(
+    conn: &mut SqliteConnection,
+    rom: &Rom,
+    path: &Path,
+    format: CompressionFormat,
+    block_size: u32,
+    hashes: &FileHashes,
+) -> io::Result<CompressedRom> {
+    compression_routes::upsert_compressed_rom(
+        conn,
+        &NewCompressedRom {
+            rom_id: rom.id,
+            path: &path.to_string_lossy(),
+            format: format.name(),
+            block_size: block_size as i32,
+            compressed_size: fs::metadata(path)?.len() as i64,
+            size: hashes.size as i64,
+            crc: &hashes.crc32,
+            md5: &hashes.md5,
+            sha1: &hashes.sha1,
+        },
+    )
+    .map_err(io::Error::other)
+}
+
+/// Compresses the rom's ISO next to it, keeping the ISO's hashes so the compressed file
+/// still verifies against the DAT. The ISO is only removed once the compressed file has
+/// been read back to the same hashes.
+pub fn compress_rom(
+    conn: &mut SqliteConnection,
+    rom_id: i32,
+    format: CompressionFormat,
+    block_size: Option<u32>,
+    remove_original: bool,
+    config: &AppConfig,
+) -> io::Result<CompressedRom> {
+    let (rom, console) =
+        patch_routes::get_rom_with_console(conn, rom_id).map_err(io::Error::other)?;
+    let rom_path = config.rom_dir(&console)?.join(&rom.title);
+    let output = rom_path.with_extension(format.extension());
+    let block_size = block_size.unwrap_or(DEFAULT_BLOCK_SIZE);
+
+    let hashes = compress_file(&rom_path, &output, format, block_size)?;
+    if !matches_rom(&rom, &hashes) {
+        fs::remove_file(&output)?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} does not match the DAT's hashes", rom_path.display()),
+        ));
+    }
+
+    if remove_original {
+        if hash_image(&output)? != hashes {
+            fs::remove_file(&output)?;
+            return Err(invalid_image(
+                "compressed image does not read back to the ISO",
+            ));
+        }
+        fs::remove_file(&rom_path)?;
+    }
+
+    register(conn, &rom, &output, format, block_size, &hashes)
+}
+
+/// Decompresses a recorded CSO or ZSO file to the rom's ISO, or to `output`, checking the
+/// result against the hashes recorded when it was compressed
+pub fn decompress_rom(
+    conn: &mut SqliteConnection,
+    compressed_rom_id: i32,
+    output: Option<PathBuf>,
+    config: &AppConfig,
+) -> io::Result<PathBuf> {
+    let compressed_rom = compression_routes::get_compressed_rom(conn, compressed_rom_id)
+        .map_err(io::Error::other)?;
+    let (rom, console) = patch_routes::get_rom_with_console(conn, compressed_rom.rom_id)
+        .map_err(io::Error::other)?;
+    let output = match output {
+        Some(output) => output,
+        None => config.rom_dir(&console)?.join(&rom.title),
+    };
+
+    let hashes = decompress_file(Path::new(&compressed_rom.path), &output)?;
+    if hashes != compressed_rom.hashes() {
+        fs::remove_file(&output)?;
+        return Err(invalid_image(&format!(
+            "{} does not decompress to the recorded ISO",
+            compressed_rom.path
+        )));
+    }
+
+    Ok(output)
+}
+
+/// Finds the file standing in for the rom: the plain file, a recorded compressed file that
+/// has not changed since, an N64 rom in another byte order or an unrecorded `.cso`/`.zso`
+/// next to it, which is verified and recorded
+fn find_rom_file(
+    conn: &mut SqliteConnection,
+    rom: &Rom,
+    compressed_roms: &[CompressedRom],
+    rom_dir: &Path,
+) -> io::Result<Option<(PathBuf, bool)>> {
+    let rom_path = rom_dir.join(&rom.title);
+    if rom_path.is_file() {
+        return Ok(Some((rom_path, false)));
+    }
+
+    for compressed_rom in compressed_roms {
+        let path = PathBuf::from(&compressed_rom.path);
+        let unchanged = fs::metadata(&path)
+            .is_ok_and(|metadata| metadata.len() as i64 == compressed_rom.compressed_size);
+        if unchanged && matches_rom(rom, &compressed_rom.hashes()) {
+            return Ok(Some((path, true)));
+        }
+    }
+
+    // No-Intro lists N64 roms big endian, `.v64` and `.n64` dumps match once converted
+    let listed_order = rom_path
+        .extension()
+        .and_then(|extension| ByteOrder::from_extension(&extension.to_string_lossy()));
+    if let Some(listed_order) = listed_order {
+        for order in ByteOrder::ALL
+            .into_iter()
+            .filter(|order| *order != listed_order)
+        {
+            let path = rom_path.with_extension(order.extension());
+            if path.is_file()
+                && n64::detect_file(&path)?.is_some()
+                && matches_rom(rom, &n64::hash_file(&path)?)
+            {
+                return Ok(Some((path, false)));
+            }
+        }
+    }
+
+    for format in [CompressionFormat::CsoV1, CompressionFormat::Zso] {
+        let path = rom_path.with_extension(format.extension());
+        if !path.is_file() {
+            continue;
+        }
+        let header = ciso::CisoReader::new(BufReader::new(File::open(&path)?))?
+            .header()
+            .clone();
+        let hashes = hash_image(&path)?;
+        if matches_rom(rom, &hashes) {
+            register(conn, rom, &path, header.format, header.block_size, &hashes)?;
+            return Ok(Some((path, true)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks which roms of the console are present, counting compressed images as the ISO
+/// they hold
+pub fn scan_console(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    config: &AppConfig,
+) -> io::Result<Vec<RomFileReport>> {
+    let rom_dir = config.rom_dir(console)?;
+
+    compression_routes::get_roms_with_compressed_roms(conn, console.id)
+        .map_err(io::Error::other)?
+        .into_iter()
+        .map(|(rom, compressed_roms)| {
+            let found = find_rom_file(conn, &rom, &compressed_roms, &rom_dir)?;
+            Ok(RomFileReport {
+                status: if found.is_some() {
+                    RomFileStatus::Have
+                } else {
+                    RomFileStatus::Missing
+                },
+                compressed: found.as_ref().is_some_and(|(_, compressed)| *compressed),
+                path: found.map(|(path, _)| path),
+                rom,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::test_connection;
+
+    use super::*;
+
+    #[test]
+    fn test_matches_rom() {
+        let hashes = hash_reader(&mut &b"rom data"[..]).unwrap();
+        let mut rom = Rom {
+            id: 1,
+            title: "Game (USA).iso".to_string(),
+            md5: hashes.md5.to_uppercase(),
+            size: hashes.size as i64,
+            game_id: 1,
+            ..Default::default()
+        };
+        assert!(matches_rom(&rom, &hashes));
+
+        rom.size += 1;
+        assert!(!matches_rom(&rom, &hashes));
+
+        // an unknown size is skipped, but a rom without any hash matches nothing
+        rom.size = 0;
+        assert!(matches_rom(&rom, &hashes));
+        rom.md5.clear();
+        assert!(!matches_rom(&rom, &hashes));
+    }
+
+    #[test]
+    fn test_compress_file() {
+        let dir = std::env::temp_dir().join("romana_compress_file");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let iso = dir.join("Game (USA).iso");
+        let data: Vec<u8> = (0..3 * 4096 + 100).map(|i| (i / 3 % 251) as u8).collect();
+        fs::write(&iso, &data).unwrap();
+        let expected = hash_reader(&mut data.as_slice()).unwrap();
+
+        assert!(compress_file(&iso, &dir.join("bad.cso"), CompressionFormat::CsoV1, 3000).is_err());
+
+        let cso = dir.join("Game (USA).cso");
+        let hashes = compress_file(&iso, &cso, CompressionFormat::CsoV2, 4096).unwrap();
+        assert_eq!(expected, hashes);
+        assert_eq!(expected, hash_image(&cso).unwrap());
+        assert!(compress_file(&iso, &cso, CompressionFormat::CsoV2, 4096).is_err());
+
+        let output = dir.join("output.iso");
+        assert_eq!(expected, decompress_file(&cso, &output).unwrap());
+        assert_eq!(data, fs::read(&output).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_rom_file_n64() {
+        let dir = std::env::temp_dir().join("romana_find_rom_file_n64");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+        data[..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        let hashes = hash_reader(&mut data.as_slice()).unwrap();
+        let rom = Rom {
+            id: 1,
+            title: "Game (USA).z64".to_string(),
+            md5: hashes.md5,
+            game_id: 1,
+            crc: hashes.crc32,
+            sha1: hashes.sha1,
+            ..Default::default()
+        };
+        assert_eq!(
+            None,
+            find_rom_file(&mut test_connection(), &rom, &[], &dir).unwrap()
+        );
+
+        ByteOrder::LittleEndian.swap(&mut data);
+        fs::write(dir.join("Game (USA).n64"), &data).unwrap();
+        assert_eq!(
+            Some((dir.join("Game (USA).n64"), false)),
+            find_rom_file(&mut test_connection(), &rom, &[], &dir).unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
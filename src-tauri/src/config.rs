@@ -2,7 +2,7 @@
 
 use std::{
     collections::HashMap,
-    fs,
+    fs, io,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -10,32 +10,100 @@ use std::{
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 
-use crate::routes::console_routes;
+use crate::{
+    exporters::target::ExportTarget, filters::FilterRuleset, models::Console, saves::SaveFolder,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
     // pub rom_paths: RomPaths,
     pub rom_paths: HashMap<String, String>,
+    #[serde(default)]
+    pub pegasus: PegasusConfig,
+    #[serde(default)]
+    pub retroarch: RetroArchConfig,
+    #[serde(default)]
+    pub saves: SavesConfig,
+    #[serde(default)]
+    pub bios: BiosConfig,
+    /// devices exports can be written for, keyed by a user chosen name
+    #[serde(default)]
+    pub export_targets: HashMap<String, ExportTarget>,
+    /// saved filter rulesets, keyed by a user chosen name
+    #[serde(default)]
+    pub filters: HashMap<String, FilterRuleset>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RomPaths {
-    pub snes: Option<String>,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PegasusConfig {
+    /// launch command used for consoles without their own template, e.g. `retroarch "{file.path}"`
+    pub default_launch: String,
+    /// launch command templates keyed by console abbreviation
+    pub launch: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetroArchConfig {
+    /// RetroArch's `playlists` directory the `.lpl` files are written to
+    pub playlist_dir: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavesConfig {
+    /// folders scanned for battery saves and save states
+    pub folders: Vec<SaveFolder>,
+    /// directory romana keeps the versioned save backups in
+    pub backup_dir: String,
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        let mut consoles = console_routes::get_consoles();
-        consoles.sort_by_key(|console| console.abbreviation.clone());
-        Self {
-            rom_paths: consoles
-                .iter()
-                .map(|console| (console.abbreviation.clone(), "".to_owned()))
-                .collect(),
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BiosConfig {
+    /// emulator system folder the BIOS files are verified against, e.g. RetroArch's `system`
+    pub system_dir: String,
+}
+
+impl SavesConfig {
+    pub fn backup_dir(&self) -> io::Result<PathBuf> {
+        if self.backup_dir.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Save backup directory is not configured",
+            ));
+        }
+
+        Ok(PathBuf::from(&self.backup_dir))
+    }
+}
+
+impl BiosConfig {
+    pub fn system_dir(&self) -> io::Result<PathBuf> {
+        if self.system_dir.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "BIOS system directory is not configured",
+            ));
         }
+
+        Ok(PathBuf::from(&self.system_dir))
     }
 }
 
+impl PegasusConfig {
+    /// Returns the launch template for the console, `None` if neither it nor the default is set
+    pub fn launch_template(&self, console_abbreviation: &str) -> Option<String> {
+        self.launch
+            .get(console_abbreviation)
+            .filter(|template| !template.is_empty())
+            .or(Some(&self.default_launch).filter(|template| !template.is_empty()))
+            .cloned()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RomPaths {
+    pub snes: Option<String>,
+}
+
 impl AppConfig {
     /// get the config path, either local .config folder or system app config folder
     fn config_path(app: Option<&AppHandle>) -> PathBuf {
@@ -52,14 +120,15 @@ impl AppConfig {
         }
     }
 
-    pub fn load(app: Option<&AppHandle>) -> Self {
+    /// Loads the saved config, with an empty rom path for every console missing one
+    pub fn load(app: Option<&AppHandle>, consoles: &[Console]) -> Self {
         let path = Self::config_path(app);
 
         let data = fs::read_to_string(&path).unwrap_or_default();
         let mut data: AppConfig = toml::from_str(&data).unwrap_or_default();
 
         // merge missing fields from defaults and save
-        data.fill_defaults();
+        data.fill_defaults(consoles);
         Self::save(&data, app);
 
         data
@@ -79,28 +148,66 @@ impl AppConfig {
             .as_table_mut()
             .unwrap()
             .sort_values_by(|a, _, b, _| a.cmp(b));
+        if let Some(launch) = toml_doc["pegasus"]["launch"].as_table_mut() {
+            launch.sort_values_by(|a, _, b, _| a.cmp(b));
+        }
 
         fs::write(&path, toml_doc.to_string()).unwrap();
     }
 
-    pub fn fill_defaults(&mut self) {
-        let defaults = AppConfig::default();
-
-        for (console, path) in defaults.rom_paths {
-            if !self.rom_paths.contains_key(console.as_str()) {
-                self.rom_paths.insert(console, path);
-            }
+    pub fn fill_defaults(&mut self, consoles: &[Console]) {
+        for console in consoles {
+            self.rom_paths
+                .entry(console.abbreviation.clone())
+                .or_default();
         }
     }
+
+    /// Returns the console's rom directory, an error if none is configured
+    pub fn rom_dir(&self, console: &Console) -> io::Result<PathBuf> {
+        self.rom_paths
+            .get(&console.abbreviation)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No rom path configured for {}", console.name),
+                )
+            })
+    }
+
+    /// Looks up an export target by name, `None` as name exports for the local machine
+    pub fn export_target(&self, name: Option<&str>) -> Result<Option<&ExportTarget>, String> {
+        name.map(|name| {
+            self.export_targets
+                .get(name)
+                .ok_or_else(|| format!("Unknown export target: {}", name))
+        })
+        .transpose()
+    }
+
+    /// Looks up a saved filter ruleset by name, `None` as name keeps all roms
+    pub fn filter_ruleset(&self, name: Option<&str>) -> Result<Option<&FilterRuleset>, String> {
+        name.map(|name| {
+            self.filters
+                .get(name)
+                .ok_or_else(|| format!("Unknown filter: {}", name))
+        })
+        .transpose()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::routes::console_routes;
+
     use super::*;
 
     #[test]
     fn test() {
-        let config = AppConfig::load(None);
+        let consoles = console_routes::get_consoles(&mut crate::db::test_connection());
+        let config = AppConfig::load(None, &consoles);
         println!("{:#?}", config);
     }
 }
@@ -0,0 +1,122 @@
+use winnow::{
+    ascii::multispace0,
+    combinator::{alt, delimited, preceded, repeat, terminated},
+    token::{take_till, take_until},
+    ModalResult, Parser,
+};
+
+/// A value in a clrmamepro DAT, either plain text or a nested `( key value ... )` block
+#[derive(Debug, PartialEq, Clone)]
+pub enum CmpValue {
+    Text(String),
+    Block(Vec<(String, CmpValue)>),
+}
+
+impl CmpValue {
+    /// Returns the first text value of the key inside a block
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self {
+            CmpValue::Block(entries) => entries.iter().find_map(|(k, value)| match value {
+                CmpValue::Text(text) if k == key => Some(text.as_str()),
+                _ => None,
+            }),
+            CmpValue::Text(_) => None,
+        }
+    }
+
+    /// Returns all blocks of the key inside a block, e.g. every `rom ( ... )` of a game
+    pub fn blocks<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a CmpValue> + 'a {
+        let entries = match self {
+            CmpValue::Block(entries) => entries.as_slice(),
+            CmpValue::Text(_) => &[],
+        };
+
+        entries
+            .iter()
+            .filter(move |(k, value)| k == key && matches!(value, CmpValue::Block(_)))
+            .map(|(_, value)| value)
+    }
+}
+
+fn quoted_parser(input: &mut &str) -> ModalResult<String> {
+    delimited('"', take_until(0.., '"'), '"')
+        .map(str::to_string)
+        .parse_next(input)
+}
+
+fn word_parser(input: &mut &str) -> ModalResult<String> {
+    take_till(1.., |c: char| c.is_whitespace() || c == '(' || c == ')')
+        .map(str::to_string)
+        .parse_next(input)
+}
+
+fn block_parser(input: &mut &str) -> ModalResult<CmpValue> {
+    delimited('(', repeat(0.., entry_parser), preceded(multispace0, ')'))
+        .map(CmpValue::Block)
+        .parse_next(input)
+}
+
+fn value_parser(input: &mut &str) -> ModalResult<CmpValue> {
+    alt((
+        block_parser,
+        quoted_parser.map(CmpValue::Text),
+        word_parser.map(CmpValue::Text),
+    ))
+    .parse_next(input)
+}
+
+fn entry_parser(input: &mut &str) -> ModalResult<(String, CmpValue)> {
+    (
+        preceded(multispace0, word_parser),
+        preceded(multispace0, value_parser),
+    )
+        .parse_next(input)
+}
+
+/// Parses a whole clrmamepro DAT, like libretro's System.dat, into its top level entries
+pub fn parse(input: &str) -> ModalResult<Vec<(String, CmpValue)>> {
+    let input = &mut input.trim_start_matches('\u{feff}');
+
+    terminated(repeat(0.., entry_parser), multispace0).parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYSTEM_DAT: &str = r#"clrmamepro (
+	name "System"
+	description "System"
+)
+
+game (
+	name "Sony - PlayStation"
+	comment "Sony - PlayStation"
+	rom ( name scph5500.bin size 524288 crc ff3eeb8c md5 8dd7d5296a650fac7319bce665a6a53c sha1 b05def971d8ec59f346f2d9ac21fb742e3eb6917 )
+	rom ( name "PSXONPSP660.bin" size 524288 crc 5660f34f md5 c53ca5908936d412331790f4426c6c33 )
+)
+"#;
+
+    #[test]
+    fn test_parse_system_dat() {
+        let entries = parse(SYSTEM_DAT).unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!("clrmamepro", entries[0].0);
+        assert_eq!(Some("System"), entries[0].1.text("name"));
+
+        let game = &entries[1].1;
+        assert_eq!(Some("Sony - PlayStation"), game.text("name"));
+
+        let roms: Vec<&CmpValue> = game.blocks("rom").collect();
+        assert_eq!(2, roms.len());
+        assert_eq!(Some("scph5500.bin"), roms[0].text("name"));
+        assert_eq!(Some("524288"), roms[0].text("size"));
+        assert_eq!(
+            Some("b05def971d8ec59f346f2d9ac21fb742e3eb6917"),
+            roms[0].text("sha1")
+        );
+        assert_eq!(Some("PSXONPSP660.bin"), roms[1].text("name"));
+        assert_eq!(None, roms[1].text("sha1"));
+    }
+}
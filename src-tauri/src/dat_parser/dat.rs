@@ -0,0 +1,341 @@
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    file_utils::walk_files,
+    hashing::hash_file,
+    models::{Console, GameWithRoms},
+};
+
+/// A whole DAT file as it's authored and edited, unlike the parser's `DatGame`s which are
+/// already cleaned and combined for the library
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Dat {
+    pub header: DatHeader,
+    pub games: Vec<DatEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatHeader {
+    /// No-Intro's DAT id
+    pub id: Option<String>,
+    /// `Manufacturer - System`, the importer finds the console by this name
+    pub name: String,
+    pub description: String,
+    pub category: Option<String>,
+    pub version: Option<String>,
+    pub date: Option<String>,
+    pub author: Option<String>,
+    pub email: Option<String>,
+    pub homepage: Option<String>,
+    pub url: Option<String>,
+    pub comment: Option<String>,
+    pub clrmamepro: Option<ClrMameProSettings>,
+}
+
+/// The header's `<clrmamepro>` element, telling ROM managers how to build the sets
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClrMameProSettings {
+    /// detector file for headered roms, e.g. `No-Intro_NES.xml`
+    pub header: Option<String>,
+    pub forcemerging: Option<String>,
+    pub forcenodump: Option<String>,
+    pub forcepacking: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatEntry {
+    pub name: String,
+    /// No-Intro's game id, which `cloneofid` refers to
+    pub id: Option<String>,
+    pub cloneof: Option<String>,
+    pub cloneofid: Option<String>,
+    /// No-Intro categories like `Games` or `Demos`
+    pub categories: Vec<String>,
+    pub description: String,
+    pub releases: Vec<DatRelease>,
+    pub roms: Vec<DatFile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatRelease {
+    pub name: String,
+    pub region: String,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatFile {
+    pub name: String,
+    pub size: u64,
+    pub crc: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    /// `baddump`, `nodump`, `good` or `verified`
+    pub status: Option<String>,
+    pub serial: Option<String>,
+}
+
+/// A single change to a DAT, as sent by the editor
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DatEdit {
+    SetHeader {
+        header: DatHeader,
+    },
+    /// adds the game, or replaces the game with the same name
+    UpsertGame {
+        game: DatEntry,
+    },
+    RenameGame {
+        name: String,
+        new_name: String,
+    },
+    RemoveGame {
+        name: String,
+    },
+    /// adds the rom to the game, or replaces its rom with the same name
+    UpsertRom {
+        game: String,
+        rom: DatFile,
+    },
+    RemoveRom {
+        game: String,
+        name: String,
+    },
+}
+
+impl Dat {
+    /// Sorts games, their releases and roms by name, so written DATs diff cleanly
+    pub fn sort(&mut self) {
+        self.games.sort_by(|a, b| {
+            (a.name.to_lowercase(), &a.name).cmp(&(b.name.to_lowercase(), &b.name))
+        });
+
+        for game in &mut self.games {
+            game.releases
+                .sort_by(|a, b| (&a.region, &a.name).cmp(&(&b.region, &b.name)));
+            game.roms.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+
+    pub fn game(&self, name: &str) -> Option<&DatEntry> {
+        self.games.iter().find(|game| game.name == name)
+    }
+
+    fn game_mut(&mut self, name: &str) -> Result<&mut DatEntry, String> {
+        self.games
+            .iter_mut()
+            .find(|game| game.name == name)
+            .ok_or_else(|| format!("Unknown game: {}", name))
+    }
+
+    pub fn apply(&mut self, edit: DatEdit) -> Result<(), String> {
+        match edit {
+            DatEdit::SetHeader { header } => self.header = header,
+            DatEdit::UpsertGame { game } => match self.game_mut(&game.name) {
+                Ok(existing) => *existing = game,
+                Err(_) => self.games.push(game),
+            },
+            DatEdit::RenameGame { name, new_name } => {
+                if self.game(&new_name).is_some() {
+                    return Err(format!("A game named {} already exists", new_name));
+                }
+                let game = self.game_mut(&name)?;
+                if game.description == game.name {
+                    game.description = new_name.clone();
+                }
+                game.name = new_name;
+            }
+            DatEdit::RemoveGame { name } => {
+                self.game_mut(&name)?;
+                self.games.retain(|game| game.name != name);
+            }
+            DatEdit::UpsertRom { game, rom } => {
+                let game = self.game_mut(&game)?;
+                match game
+                    .roms
+                    .iter_mut()
+                    .find(|existing| existing.name == rom.name)
+                {
+                    Some(existing) => *existing = rom,
+                    None => game.roms.push(rom),
+                }
+            }
+            DatEdit::RemoveRom { game, name } => {
+                let game = self.game_mut(&game)?;
+                let count = game.roms.len();
+                game.roms.retain(|rom| rom.name != name);
+                if game.roms.len() == count {
+                    return Err(format!("Unknown rom: {}", name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds a DAT with a game per file below the folder, named after the file
+    pub fn from_folder(folder: &Path, header: DatHeader) -> io::Result<Self> {
+        let games = walk_files(folder)?
+            .into_iter()
+            .map(|path| {
+                let hashes = hash_file(&path)?;
+                // Logiqx uses backslashes for files in subfolders
+                let name = path
+                    .strip_prefix(folder)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('/', "\\");
+                let game_name = Path::new(&name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| name.clone());
+
+                Ok(DatEntry {
+                    name: game_name.clone(),
+                    description: game_name,
+                    roms: vec![DatFile {
+                        name,
+                        size: hashes.size,
+                        crc: Some(hashes.crc32),
+                        md5: Some(hashes.md5),
+                        sha1: Some(hashes.sha1),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Dat { header, games })
+    }
+
+    /// Builds a DAT from library games. The library keeps the cleaned game title only, so
+    /// every rom becomes its own game named after the rom file, which the importer combines
+    /// into the same library games again.
+    pub fn from_games(console: &Console, games: &[GameWithRoms]) -> Self {
+        let manufacturer = if console.manufacturer.is_empty() {
+            "Romana"
+        } else {
+            &console.manufacturer
+        };
+        let name = format!("{} - {}", manufacturer, console.name);
+
+        let games = games
+            .iter()
+            .flat_map(|game_roms| &game_roms.roms)
+            .map(|rom| {
+                let game_name = Path::new(&rom.title)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| rom.title.clone());
+
+                DatEntry {
+                    name: game_name.clone(),
+                    description: game_name,
+                    roms: vec![DatFile {
+                        name: rom.title.clone(),
+                        size: rom.size.max(0) as u64,
+                        md5: Some(rom.md5.clone()).filter(|md5| !md5.is_empty()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        Dat {
+            header: DatHeader {
+                description: name.clone(),
+                name,
+                ..Default::default()
+            },
+            games,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn dat() -> Dat {
+        Dat {
+            header: DatHeader {
+                name: "Nintendo - Game Boy".to_string(),
+                ..Default::default()
+            },
+            games: vec![DatEntry {
+                name: "Tetris (World)".to_string(),
+                description: "Tetris (World)".to_string(),
+                roms: vec![DatFile {
+                    name: "Tetris (World).gb".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut dat = dat();
+
+        dat.apply(DatEdit::RenameGame {
+            name: "Tetris (World)".to_string(),
+            new_name: "Tetris (World) (Rev 1)".to_string(),
+        })
+        .unwrap();
+        dat.apply(DatEdit::UpsertRom {
+            game: "Tetris (World) (Rev 1)".to_string(),
+            rom: DatFile {
+                name: "Tetris (World).gb".to_string(),
+                size: 32768,
+                ..Default::default()
+            },
+        })
+        .unwrap();
+
+        let game = dat.game("Tetris (World) (Rev 1)").unwrap();
+        assert_eq!("Tetris (World) (Rev 1)", game.description);
+        assert_eq!(32768, game.roms[0].size);
+        assert_eq!(1, game.roms.len());
+
+        assert!(dat
+            .apply(DatEdit::RemoveGame {
+                name: "Tetris (World)".to_string()
+            })
+            .is_err());
+        dat.apply(DatEdit::RemoveGame {
+            name: "Tetris (World) (Rev 1)".to_string(),
+        })
+        .unwrap();
+        assert!(dat.games.is_empty());
+    }
+
+    #[test]
+    fn test_from_folder() {
+        let dir = std::env::temp_dir().join("romana_dat_from_folder");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("disks")).unwrap();
+        fs::write(dir.join("Game.bin"), b"romana").unwrap();
+        fs::write(dir.join("disks").join("Game (Disk 2).bin"), b"disk").unwrap();
+
+        let dat = Dat::from_folder(&dir, DatHeader::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(2, dat.games.len());
+        assert_eq!("Game", dat.games[0].name);
+        assert_eq!(6, dat.games[0].roms[0].size);
+        assert_eq!("disks\\Game (Disk 2).bin", dat.games[1].roms[0].name);
+        assert_eq!(
+            Some(32),
+            dat.games[1].roms[0].md5.as_ref().map(|md5| md5.len())
+        );
+    }
+}
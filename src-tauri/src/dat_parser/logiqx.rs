@@ -0,0 +1,539 @@
+use std::{borrow::Cow, fs, io, path::Path};
+
+use html_escape::{decode_html_entities, encode_double_quoted_attribute};
+use winnow::{
+    ascii::multispace0,
+    combinator::{alt, delimited, opt, preceded, repeat, terminated},
+    token::{take_till, take_until, take_while},
+    ModalResult, Parser,
+};
+
+use crate::dat_parser::dat::{ClrMameProSettings, Dat, DatEntry, DatFile, DatHeader, DatRelease};
+
+/// An XML element of a Logiqx DAT or another XML file, with entities already decoded
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    pub(crate) children: Vec<Element>,
+}
+
+impl Element {
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Text of the child element, `None` if it's missing or empty
+    pub(crate) fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name)
+            .map(|child| child.text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+    Skipped,
+}
+
+fn name_parser<'s>(input: &mut &'s str) -> ModalResult<&'s str> {
+    take_while(1.., |c: char| {
+        c.is_alphanumeric() || matches!(c, ':' | '_' | '-' | '.')
+    })
+    .parse_next(input)
+}
+
+fn attribute_parser(input: &mut &str) -> ModalResult<(String, String)> {
+    let value = alt((
+        delimited('"', take_until(0.., '"'), '"'),
+        delimited('\'', take_until(0.., '\''), '\''),
+    ));
+
+    (
+        preceded(multispace0, name_parser),
+        preceded((multispace0, '=', multispace0), value),
+    )
+        .map(|(name, value): (&str, &str)| {
+            (name.to_string(), decode_html_entities(value).to_string())
+        })
+        .parse_next(input)
+}
+
+/// Comments, processing instructions and the doctype, which the DAT model doesn't keep
+fn skipped_parser(input: &mut &str) -> ModalResult<()> {
+    alt((
+        delimited("<!--", take_until(0.., "-->"), "-->"),
+        delimited("<?", take_until(0.., "?>"), "?>"),
+        delimited("<!", take_until(0.., ">"), ">"),
+    ))
+    .void()
+    .parse_next(input)
+}
+
+fn node_parser(input: &mut &str) -> ModalResult<Node> {
+    alt((
+        skipped_parser.map(|_| Node::Skipped),
+        element_parser.map(Node::Element),
+        take_till(1.., '<').map(|text: &str| Node::Text(decode_html_entities(text).to_string())),
+    ))
+    .parse_next(input)
+}
+
+fn element_parser(input: &mut &str) -> ModalResult<Element> {
+    let (name, attributes) =
+        preceded('<', (name_parser, repeat(0.., attribute_parser))).parse_next(input)?;
+    let mut element = Element {
+        name: name.to_string(),
+        attributes,
+        ..Default::default()
+    };
+
+    let is_empty = opt(preceded(multispace0, "/>"))
+        .parse_next(input)?
+        .is_some();
+    if is_empty {
+        return Ok(element);
+    }
+
+    preceded(multispace0, '>').parse_next(input)?;
+    let nodes: Vec<Node> = repeat(0.., node_parser).parse_next(input)?;
+    ("</", name, multispace0, '>').void().parse_next(input)?;
+
+    for node in nodes {
+        match node {
+            Node::Element(child) => element.children.push(child),
+            Node::Text(text) => element.text.push_str(&text),
+            Node::Skipped => (),
+        }
+    }
+
+    Ok(element)
+}
+
+pub(crate) fn document_parser(input: &mut &str) -> ModalResult<Element> {
+    let _: Vec<()> = repeat(0.., preceded(multispace0, skipped_parser)).parse_next(input)?;
+
+    terminated(preceded(multispace0, element_parser), multispace0).parse_next(input)
+}
+
+fn dat_file(rom: &Element) -> DatFile {
+    let attribute = |name| rom.attribute(name).map(str::to_string);
+    let hash = |name| rom.attribute(name).map(str::to_lowercase);
+
+    DatFile {
+        name: rom.attribute("name").unwrap_or_default().to_string(),
+        size: rom
+            .attribute("size")
+            .and_then(|size| size.parse().ok())
+            .unwrap_or_default(),
+        crc: hash("crc"),
+        md5: hash("md5"),
+        sha1: hash("sha1"),
+        sha256: hash("sha256"),
+        status: attribute("status"),
+        serial: attribute("serial"),
+    }
+}
+
+fn dat_entry(game: &Element) -> DatEntry {
+    let attribute = |name| game.attribute(name).map(str::to_string);
+
+    DatEntry {
+        name: game.attribute("name").unwrap_or_default().to_string(),
+        id: attribute("id"),
+        cloneof: attribute("cloneof"),
+        cloneofid: attribute("cloneofid"),
+        categories: game
+            .children("category")
+            .map(|category| category.text.trim().to_string())
+            .collect(),
+        description: game.child_text("description").unwrap_or_default(),
+        releases: game
+            .children("release")
+            .map(|release| DatRelease {
+                name: release.attribute("name").unwrap_or_default().to_string(),
+                region: release.attribute("region").unwrap_or_default().to_string(),
+                language: release.attribute("language").map(str::to_string),
+            })
+            .collect(),
+        roms: game.children("rom").map(dat_file).collect(),
+    }
+}
+
+fn dat_header(header: &Element) -> DatHeader {
+    DatHeader {
+        id: header.child_text("id"),
+        name: header.child_text("name").unwrap_or_default(),
+        description: header.child_text("description").unwrap_or_default(),
+        category: header.child_text("category"),
+        version: header.child_text("version"),
+        date: header.child_text("date"),
+        author: header.child_text("author"),
+        email: header.child_text("email"),
+        homepage: header.child_text("homepage"),
+        url: header.child_text("url"),
+        comment: header.child_text("comment"),
+        clrmamepro: header.child("clrmamepro").map(|clrmamepro| {
+            let attribute = |name| clrmamepro.attribute(name).map(str::to_string);
+
+            ClrMameProSettings {
+                header: attribute("header"),
+                forcemerging: attribute("forcemerging"),
+                forcenodump: attribute("forcenodump"),
+                forcepacking: attribute("forcepacking"),
+            }
+        }),
+    }
+}
+
+/// Reads a Logiqx XML DAT into the editable DAT model
+pub fn parse(input: &str) -> Result<Dat, String> {
+    let datafile = document_parser
+        .parse(input.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("invalid DAT: {}", e))?;
+    if datafile.name != "datafile" {
+        return Err(format!(
+            "invalid DAT: unexpected <{}> element",
+            datafile.name
+        ));
+    }
+
+    Ok(Dat {
+        header: datafile.child("header").map(dat_header).unwrap_or_default(),
+        games: datafile
+            .children
+            .iter()
+            .filter(|child| child.name == "game" || child.name == "machine")
+            .map(dat_entry)
+            .collect(),
+    })
+}
+
+/// Escapes text and attribute values, `decode_html_entities` turns the result back into
+/// the original string
+fn escape(value: &str) -> Cow<'_, str> {
+    encode_double_quoted_attribute(value)
+}
+
+/// Appends ` name="value"` for every attribute with a value, in the given order
+fn push_attributes(xml: &mut String, attributes: &[(&str, Option<&str>)]) {
+    for (name, value) in attributes {
+        if let Some(value) = value {
+            xml.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+        }
+    }
+}
+
+fn push_element(xml: &mut String, indent: &str, name: &str, value: &str) {
+    xml.push_str(&format!("{indent}<{name}>{}</{name}>\n", escape(value)));
+}
+
+/// Writes the DAT as Logiqx XML, readable by `parse` and the library importer.
+///
+/// Elements follow the order of the Logiqx DTD, with No-Intro's extensions (ids, categories,
+/// sha256 and serials) only written when set. Games, releases and roms are sorted and
+/// attributes always come in the same order, so the same DAT always gives the same file.
+pub fn write(dat: &Dat) -> String {
+    let mut dat = dat.clone();
+    dat.sort();
+
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\"?>\n",
+        "<!DOCTYPE datafile PUBLIC \"-//Logiqx//DTD ROM Management Datafile//EN\" ",
+        "\"http://www.logiqx.com/Dats/datafile.dtd\">\n",
+        "<datafile>\n\t<header>\n"
+    ));
+
+    let header = &dat.header;
+    let optional = |value: &Option<String>| value.clone();
+    // version and author are required by the DTD
+    let fields = [
+        ("id", optional(&header.id)),
+        ("name", Some(header.name.clone())),
+        ("description", Some(header.description.clone())),
+        ("category", optional(&header.category)),
+        ("version", Some(header.version.clone().unwrap_or_default())),
+        ("date", optional(&header.date)),
+        ("author", Some(header.author.clone().unwrap_or_default())),
+        ("email", optional(&header.email)),
+        ("homepage", optional(&header.homepage)),
+        ("url", optional(&header.url)),
+        ("comment", optional(&header.comment)),
+    ];
+    for (field, value) in fields {
+        if let Some(value) = value {
+            push_element(&mut xml, "\t\t", field, &value);
+        }
+    }
+    if let Some(clrmamepro) = &header.clrmamepro {
+        xml.push_str("\t\t<clrmamepro");
+        push_attributes(
+            &mut xml,
+            &[
+                ("header", clrmamepro.header.as_deref()),
+                ("forcemerging", clrmamepro.forcemerging.as_deref()),
+                ("forcenodump", clrmamepro.forcenodump.as_deref()),
+                ("forcepacking", clrmamepro.forcepacking.as_deref()),
+            ],
+        );
+        xml.push_str("/>\n");
+    }
+    xml.push_str("\t</header>\n");
+
+    for game in &dat.games {
+        xml.push_str("\t<game");
+        push_attributes(
+            &mut xml,
+            &[
+                ("name", Some(&game.name)),
+                ("id", game.id.as_deref()),
+                ("cloneof", game.cloneof.as_deref()),
+                ("cloneofid", game.cloneofid.as_deref()),
+            ],
+        );
+        xml.push_str(">\n");
+
+        for category in &game.categories {
+            push_element(&mut xml, "\t\t", "category", category);
+        }
+        push_element(&mut xml, "\t\t", "description", &game.description);
+
+        for release in &game.releases {
+            xml.push_str("\t\t<release");
+            push_attributes(
+                &mut xml,
+                &[
+                    ("name", Some(&release.name)),
+                    ("region", Some(&release.region)),
+                    ("language", release.language.as_deref()),
+                ],
+            );
+            xml.push_str("/>\n");
+        }
+
+        for rom in &game.roms {
+            let size = rom.size.to_string();
+            let hash = |hash: &Option<String>| hash.as_ref().map(|hash| hash.to_lowercase());
+            let (crc, md5, sha1, sha256) = (
+                hash(&rom.crc),
+                hash(&rom.md5),
+                hash(&rom.sha1),
+                hash(&rom.sha256),
+            );
+
+            xml.push_str("\t\t<rom");
+            push_attributes(
+                &mut xml,
+                &[
+                    ("name", Some(&rom.name)),
+                    ("size", Some(&size)),
+                    ("crc", crc.as_deref()),
+                    ("md5", md5.as_deref()),
+                    ("sha1", sha1.as_deref()),
+                    ("sha256", sha256.as_deref()),
+                    ("status", rom.status.as_deref()),
+                    ("serial", rom.serial.as_deref()),
+                ],
+            );
+            xml.push_str("/>\n");
+        }
+
+        xml.push_str("\t</game>\n");
+    }
+
+    xml.push_str("</datafile>\n");
+    xml
+}
+
+pub fn read_file(path: &Path) -> io::Result<Dat> {
+    parse(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_file(dat: &Dat, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, write(dat))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dat_parser::parser::parse_dat,
+        db::test_connection,
+        models::{Console, Game, GameWithRoms, Rom},
+    };
+
+    use super::*;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/Dats/datafile.dtd">
+<datafile>
+	<header>
+		<name>Nintendo - Super Nintendo Entertainment System</name>
+		<description>Nintendo - Super Nintendo Entertainment System</description>
+		<version>20251012-045317</version>
+		<clrmamepro forcenodump="required"/>
+	</header>
+	<!-- a comment -->
+	<game name="Tom &amp; Jerry (USA)" id="0001">
+		<description>Tom &amp; Jerry (USA)</description>
+		<rom name="Tom &amp; Jerry (USA).sfc" size="1048576" crc="EB1E5FC9" md5="9b36075b53dec1a506b1f9334e670c63"/>
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_parse() {
+        let dat = parse(DAT).unwrap();
+
+        assert_eq!(
+            "Nintendo - Super Nintendo Entertainment System",
+            dat.header.name
+        );
+        assert_eq!(Some("20251012-045317".to_string()), dat.header.version);
+        assert_eq!(1, dat.games.len());
+        assert_eq!("Tom & Jerry (USA)", dat.games[0].name);
+        assert_eq!(
+            DatFile {
+                name: "Tom & Jerry (USA).sfc".to_string(),
+                size: 1048576,
+                crc: Some("eb1e5fc9".to_string()),
+                md5: Some("9b36075b53dec1a506b1f9334e670c63".to_string()),
+                ..Default::default()
+            },
+            dat.games[0].roms[0]
+        );
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let dat = parse(DAT).unwrap();
+
+        assert_eq!(dat, parse(&write(&dat)).unwrap());
+    }
+
+    #[test]
+    fn test_escape_is_inverse_of_decode() {
+        for value in [
+            "Tom & Jerry",
+            r#"Say "Hi" <Beta>"#,
+            "Pok\u{e9}mon 'Red'",
+            "already escaped &amp; &#39;",
+        ] {
+            assert_eq!(value, decode_html_entities(&escape(value)));
+        }
+    }
+
+    #[test]
+    fn test_write_is_deterministic() {
+        let game = |name: &str| DatEntry {
+            name: name.to_string(),
+            description: name.to_string(),
+            ..Default::default()
+        };
+        let mut dat = Dat {
+            games: vec![game("b"), game("A"), game("a")],
+            ..Default::default()
+        };
+        let xml = write(&dat);
+
+        dat.games.reverse();
+        assert_eq!(xml, write(&dat));
+
+        let names: Vec<String> = parse(&xml)
+            .unwrap()
+            .games
+            .into_iter()
+            .map(|game| game.name)
+            .collect();
+        assert_eq!(vec!["A", "a", "b"], names);
+    }
+
+    #[test]
+    fn test_no_intro_round_trip() {
+        let path =
+            Path::new("tests/Nintendo - Super Nintendo Entertainment System (20251012-045317).dat");
+        let mut dat = read_file(path).unwrap();
+
+        let xml = write(&dat);
+        let written = parse(&xml).unwrap();
+        dat.sort();
+
+        assert_eq!(4084, written.games.len());
+        assert_eq!(Some("49".to_string()), written.header.id);
+        assert_eq!(
+            Some("required".to_string()),
+            written
+                .header
+                .clrmamepro
+                .as_ref()
+                .and_then(|settings| settings.forcenodump.clone())
+        );
+        assert_eq!(dat, written);
+        assert_eq!(xml, write(&written));
+    }
+
+    #[test]
+    fn test_library_round_trip() {
+        let console = Console {
+            id: 144,
+            name: "Super Nintendo Entertainment System".to_string(),
+            abbreviation: "snes".to_string(),
+            manufacturer: String::new(),
+        };
+        let rom = |id, title: &str| Rom {
+            id,
+            title: title.to_string(),
+            md5: format!("{:032x}", id),
+            game_id: 1,
+            ..Default::default()
+        };
+        let games = vec![GameWithRoms {
+            game: Game {
+                id: 1,
+                title: "Secret of Mana".to_string(),
+                console_id: 144,
+                ..Default::default()
+            },
+            roms: vec![
+                rom(1, "Secret of Mana (Europe).sfc"),
+                rom(2, "Secret of Mana (USA).sfc"),
+            ],
+            metadata: Default::default(),
+        }];
+
+        let xml = write(&Dat::from_games(&console, &games));
+        let (imported_console, imported_games) = parse_dat(&mut test_connection(), &xml).unwrap();
+
+        assert_eq!(console.id, imported_console.id);
+        assert_eq!(1, imported_games.len());
+        assert_eq!("Secret of Mana", imported_games[0].name);
+        assert_eq!(
+            games[0]
+                .roms
+                .iter()
+                .map(|rom| (rom.title.clone(), rom.md5.clone()))
+                .collect::<Vec<_>>(),
+            imported_games[0]
+                .roms
+                .iter()
+                .map(|rom| (rom.name.clone(), rom.md5.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,2 +1,6 @@
+pub mod clrmamepro;
+pub mod dat;
+pub mod logiqx;
+pub mod name_flags;
 pub mod parser;
 pub mod system_name_helper;
@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::parser::parse_disc_number;
+
+/// Regions as No-Intro and Redump spell them in names
+const REGIONS: [&str; 37] = [
+    "World",
+    "Europe",
+    "USA",
+    "Japan",
+    "Asia",
+    "Australia",
+    "Austria",
+    "Belgium",
+    "Brazil",
+    "Canada",
+    "China",
+    "Denmark",
+    "Finland",
+    "France",
+    "Germany",
+    "Greece",
+    "Hong Kong",
+    "India",
+    "Ireland",
+    "Israel",
+    "Italy",
+    "Korea",
+    "Latin America",
+    "Mexico",
+    "Netherlands",
+    "New Zealand",
+    "Norway",
+    "Poland",
+    "Portugal",
+    "Russia",
+    "Scandinavia",
+    "South Africa",
+    "Spain",
+    "Sweden",
+    "Switzerland",
+    "Taiwan",
+    "UK",
+];
+
+/// Everything the parentheses of a rom name tell about the release,
+/// e.g. `Secret of Mana (Europe) (En,Fr,De) (Rev 1)`
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct NameFlags {
+    pub regions: Vec<String>,
+    /// language codes like `En`, only listed by the DATs for releases with several
+    pub languages: Vec<String>,
+    /// `Rev 1`, `Rev A` or `v1.1` as comparable numbers, empty for the first release
+    pub revision: Vec<u32>,
+    pub disc: Option<i32>,
+    pub beta: bool,
+    pub proto: bool,
+    /// demos, samples and kiosk versions
+    pub demo: bool,
+    pub pirate: bool,
+    pub unlicensed: bool,
+}
+
+impl NameFlags {
+    /// Parses the flags of a game or rom name, a file extension is ignored
+    pub fn parse(name: &str) -> Self {
+        let mut flags = NameFlags::default();
+
+        for group in parenthesized(name) {
+            let items: Vec<&str> = group.split(',').map(str::trim).collect();
+
+            if items.iter().all(|item| REGIONS.contains(item)) {
+                flags
+                    .regions
+                    .extend(items.iter().map(|item| item.to_string()));
+            } else if items.iter().all(|item| is_language_code(item)) {
+                flags.languages.extend(
+                    items
+                        .iter()
+                        .flat_map(|item| item.split('+'))
+                        .map(str::to_string),
+                );
+            } else {
+                items.iter().for_each(|item| flags.add_flag(item));
+            }
+        }
+
+        flags
+    }
+
+    fn add_flag(&mut self, flag: &str) {
+        let word = flag.split(' ').next().unwrap_or_default();
+
+        match word {
+            "Beta" => self.beta = true,
+            "Proto" | "Prototype" => self.proto = true,
+            "Demo" | "Sample" | "Kiosk" => self.demo = true,
+            "Pirate" => self.pirate = true,
+            "Unl" => self.unlicensed = true,
+            "Rev" => self.revision = parse_revision(flag.trim_start_matches("Rev").trim()),
+            _ if is_version(flag) => self.revision = parse_revision(&flag[1..]),
+            _ => {
+                if let Some(disc) = parse_disc_number(flag) {
+                    self.disc = Some(disc);
+                }
+            }
+        }
+    }
+
+    /// Whether it's a beta, prototype or demo instead of a finished release
+    pub fn is_prerelease(&self) -> bool {
+        self.beta || self.proto || self.demo
+    }
+
+    /// The listed languages, or the ones implied by the regions when none are listed
+    pub fn implied_languages(&self) -> Vec<String> {
+        if !self.languages.is_empty() {
+            return self.languages.clone();
+        }
+
+        let mut languages: Vec<String> = Vec::new();
+        for language in self
+            .regions
+            .iter()
+            .filter_map(|region| region_language(region))
+        {
+            if !languages.iter().any(|known| known == language) {
+                languages.push(language.to_string());
+            }
+        }
+        languages
+    }
+}
+
+/// The contents of all parentheses in the name without its extension
+fn parenthesized(name: &str) -> impl Iterator<Item = &str> {
+    let stem = match Path::new(name).extension() {
+        // extensions don't contain spaces or parentheses, names of games may contain dots
+        Some(extension) if !extension.to_string_lossy().contains([' ', ')']) => {
+            &name[..name.len() - extension.len() - 1]
+        }
+        _ => name,
+    };
+
+    stem.split('(')
+        .skip(1)
+        .filter_map(|part| part.split_once(')').map(|(group, _)| group))
+}
+
+/// Codes like `En`, `Fr` or `En+Ja` for a bilingual release
+fn is_language_code(item: &str) -> bool {
+    item.split('+').all(|code| {
+        let mut chars = code.chars();
+        code.len() == 2
+            && chars.next().is_some_and(|c| c.is_ascii_uppercase())
+            && chars.next().is_some_and(|c| c.is_ascii_lowercase())
+    })
+}
+
+fn is_version(flag: &str) -> bool {
+    flag.strip_prefix('v')
+        .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Splits `1.10` into `[1, 10]` and maps revision letters to numbers, `A` to 1
+fn parse_revision(revision: &str) -> Vec<u32> {
+    revision
+        .split('.')
+        .map(|part| match part.parse() {
+            Ok(number) => number,
+            Err(_) => part
+                .chars()
+                .filter(|c| c.is_ascii_alphabetic())
+                .fold(0, |number, c| {
+                    number * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+                }),
+        })
+        .collect()
+}
+
+/// The language a region's releases are in when the DAT lists none
+fn region_language(region: &str) -> Option<&'static str> {
+    match region {
+        "USA" | "UK" | "Australia" | "Canada" | "Ireland" | "New Zealand" | "World" => Some("En"),
+        "Japan" => Some("Ja"),
+        "Germany" | "Austria" => Some("De"),
+        "France" => Some("Fr"),
+        "Spain" | "Mexico" | "Latin America" => Some("Es"),
+        "Italy" => Some("It"),
+        "Netherlands" => Some("Nl"),
+        "Sweden" => Some("Sv"),
+        "Brazil" | "Portugal" => Some("Pt"),
+        "Korea" => Some("Ko"),
+        "China" | "Taiwan" | "Hong Kong" => Some("Zh"),
+        "Russia" => Some("Ru"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let flags = NameFlags::parse("Secret of Mana (Europe) (En,Fr,De) (Rev 1).sfc");
+        assert_eq!(vec!["Europe"], flags.regions);
+        assert_eq!(vec!["En", "Fr", "De"], flags.languages);
+        assert_eq!(vec![1], flags.revision);
+        assert!(!flags.is_prerelease());
+
+        let flags = NameFlags::parse("Star Fox 2 (Japan) (Beta) (1994-05-13)");
+        assert_eq!(vec!["Ja"], flags.implied_languages());
+        assert!(flags.beta);
+
+        let flags = NameFlags::parse("Final Fantasy VII (USA, Europe) (Disc 2) (v1.10).cue");
+        assert_eq!(vec!["USA", "Europe"], flags.regions);
+        assert_eq!(vec!["En"], flags.implied_languages());
+        assert_eq!(Some(2), flags.disc);
+        assert_eq!(vec![1, 10], flags.revision);
+
+        let flags = NameFlags::parse("Super 3D Noah's Ark (USA) (Unl) (Rev A)");
+        assert!(flags.unlicensed);
+        assert_eq!(vec![1], flags.revision);
+    }
+}
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, fs, path::Path};
+use std::{collections::HashMap, fs, io, path::Path};
 
-use diesel::{insert_into, upsert::excluded, ExpressionMethods, RunQueryDsl};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, RunQueryDsl, SqliteConnection};
 use html_escape::decode_html_entities;
 use serde::Serialize;
 use winnow::{
@@ -12,12 +12,13 @@ use winnow::{
 };
 
 use crate::{
-    establish_connection,
-    models::{Console, Game, NewGame, NewRom, Rom},
+    models::{Console, Game, NewGame, NewRom, NewRomTrack, Rom, RomTrack},
     routes::console_routes::get_console_by_name,
     schemas::{
         games::{self, console_id},
         games_table,
+        rom_tracks::{self},
+        rom_tracks_table,
         roms::{self},
         roms_table,
     },
@@ -28,13 +29,33 @@ pub struct DatRom {
     pub name: String,
     pub md5: String,
     pub regions: Vec<String>,
-    size: u32,
+    pub size: u64,
+    pub disc: Option<i32>,
+    pub crc: String,
+    pub sha1: String,
+    /// the `<category>` of the entry, e.g. `Games`, `Demos` or `Applications`
+    pub category: String,
+    /// the track files a `.cue` or `.gdi` sheet references, as listed in Redump DATs
+    pub tracks: Vec<DatTrack>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DatTrack {
+    pub name: String,
+    pub size: u64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct DatGame {
     pub name: String,
     pub roms: Vec<DatRom>,
+    /// serials of the combined entries, from Redump's `<serial>` or No-Intro's rom attribute
+    pub serials: Vec<String>,
+    /// cleaned name of the parent entry, if it differs from the game's own
+    pub clone_of: Option<String>,
 }
 
 // TODO: expand with more information from name attribute, e.g. beta, bootleg, etc.
@@ -43,6 +64,7 @@ struct DatNameInfo {
     pub name: String,
     pub regions: Vec<String>,
     pub beta: bool,
+    pub disc: Option<i32>,
 }
 
 // TODO: maybe for cleaning the names of games/roms
@@ -98,7 +120,11 @@ fn name_parenthesis_parser<'s>(
             match s {
                 "Japan" | "USA" | "Europe" => name_info.regions.push(s.to_string()),
                 "Beta" => name_info.beta = true,
-                _ => (),
+                _ => {
+                    if let Some(disc) = parse_disc_number(s) {
+                        name_info.disc = Some(disc)
+                    }
+                }
             }
         }
 
@@ -115,6 +141,7 @@ fn name_parser(input: &mut &str) -> ModalResult<DatNameInfo> {
         name: decode_html_entities(name.trim()).to_string(),
         regions: Vec::new(),
         beta: false,
+        disc: None,
     };
 
     {
@@ -140,6 +167,11 @@ fn rom_builder<'s>(
         md5: String::new(),
         regions,
         size: 0,
+        disc: name_info.disc,
+        crc: String::new(),
+        sha1: String::new(),
+        category: String::new(),
+        tracks: Vec::new(),
     };
 
     for (attribute, value) in attributes {
@@ -147,6 +179,8 @@ fn rom_builder<'s>(
             "name" => rom.name = decode_html_entities(value).to_string(),
             "md5" => rom.md5 = value.to_owned(),
             "size" => rom.size = value.parse().unwrap_or_default(),
+            "crc" => rom.crc = value.to_lowercase(),
+            "sha1" => rom.sha1 = value.to_lowercase(),
             _ => (),
         }
     }
@@ -158,10 +192,57 @@ fn rom_builder<'s>(
     rom
 }
 
-fn game_builder(rom: DatRom, name_info: DatNameInfo) -> DatGame {
+fn track_builder(attributes: HashMap<&str, &str>) -> DatTrack {
+    let attribute = |key| attributes.get(key).copied().unwrap_or_default();
+
+    DatTrack {
+        name: decode_html_entities(attribute("name")).to_string(),
+        size: attribute("size").parse().unwrap_or_default(),
+        crc: attribute("crc").to_lowercase(),
+        md5: attribute("md5").to_lowercase(),
+        sha1: attribute("sha1").to_lowercase(),
+    }
+}
+
+/// Whether the rom is a cue sheet or GD-ROM descriptor referencing the disc's track files
+pub fn is_disc_sheet(name: &str) -> bool {
+    Path::new(name).extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("cue") || extension.eq_ignore_ascii_case("gdi")
+    })
+}
+
+/// Builds the rom of an entry. Disc entries list a sheet and its tracks as separate roms,
+/// these become a single rom of the sheet with its tracks. Otherwise the first rom is used.
+fn disc_builder<'s>(
+    mut roms: Vec<HashMap<&'s str, &'s str>>,
+    regions: Vec<String>,
+    name_info: &DatNameInfo,
+) -> DatRom {
+    let sheet_index = roms
+        .iter()
+        .position(|rom| rom.get("name").is_some_and(|name| is_disc_sheet(name)))
+        .unwrap_or(0);
+    let sheet = roms.remove(sheet_index);
+
+    let mut rom = rom_builder(sheet, regions, name_info);
+    if is_disc_sheet(&rom.name) {
+        rom.tracks = roms.into_iter().map(track_builder).collect();
+    }
+
+    rom
+}
+
+fn game_builder(
+    rom: DatRom,
+    name_info: DatNameInfo,
+    serials: Vec<String>,
+    clone_of: Option<String>,
+) -> DatGame {
     DatGame {
+        clone_of: clone_of.filter(|parent| !parent.eq_ignore_ascii_case(&name_info.name)),
         name: name_info.name.to_string(),
         roms: vec![rom],
+        serials,
     }
 }
 
@@ -199,22 +280,84 @@ fn game_parser<'s>(input: &mut &'s str) -> Result<HashMap<&'s str, &'s str>> {
     delimited(tag_start, attributes_parser, alt((">", "/>"))).parse_next(input)
 }
 
-fn rom_parser<'s>(input: &mut &'s str) -> Result<HashMap<&'s str, &'s str>> {
+pub(crate) fn rom_parser<'s>(input: &mut &'s str) -> Result<HashMap<&'s str, &'s str>> {
     let tag_start = preceded(take_until(0.., "<rom"), "<rom");
 
     delimited(tag_start, attributes_parser, alt((">", "/>"))).parse_next(input)
 }
 
+/// Position of the end of the current game, `None` if it's the last one without end tag
+fn game_end(input: &str) -> Option<usize> {
+    [input.find("</game"), input.find("<game")]
+        .into_iter()
+        .flatten()
+        .min()
+}
+
+/// Parses the further <rom> entries of the current game, e.g. the tracks of a disc
+fn additional_roms_parser<'s>(input: &mut &'s str) -> Result<Vec<HashMap<&'s str, &'s str>>> {
+    let mut roms = Vec::new();
+
+    loop {
+        let next_rom = input.find("<rom");
+
+        match next_rom {
+            Some(rom) if game_end(input).is_none_or(|end| rom < end) => {
+                roms.push(rom_parser(input)?)
+            }
+            _ => return Ok(roms),
+        }
+    }
+}
+
+/// Reads the `<serial>` elements of a game's body, which may list several serials
+fn serials_parser(game_body: &str) -> Vec<String> {
+    game_body
+        .split("<serial>")
+        .skip(1)
+        .filter_map(|element| element.split_once("</serial>"))
+        .flat_map(|(serials, _)| serials.split(','))
+        .map(|serial| decode_html_entities(serial.trim()).to_string())
+        .filter(|serial| !serial.is_empty())
+        .collect()
+}
+
+/// Reads the first `<category>` element of a game's body
+fn category_parser(game_body: &str) -> String {
+    game_body
+        .split_once("<category>")
+        .and_then(|(_, rest)| rest.split_once("</category>"))
+        .map(|(category, _)| decode_html_entities(category.trim()).to_string())
+        .unwrap_or_default()
+}
+
 /// Parses a single <game> entry in the DAT file
 fn entry_parser(input: &mut &str) -> Result<DatGame> {
-    let (game_data, regions, rom_data) =
-        (game_parser, releases_parser, rom_parser).parse_next(input)?;
+    let game_data = game_parser(input)?;
+    let game_body = &input[..game_end(input).unwrap_or(input.len())];
+    let mut serials = serials_parser(game_body);
+    let category = category_parser(game_body);
+
+    let (regions, rom_data, mut roms_data) =
+        (releases_parser, rom_parser, additional_roms_parser).parse_next(input)?;
 
     let mut name_raw = *game_data.get("name").unwrap();
     let name_info = name_parser(&mut name_raw).expect("error parsing name");
-    let rom = rom_builder(rom_data, regions, &name_info);
-
-    Ok(game_builder(rom, name_info))
+    roms_data.insert(0, rom_data);
+    serials.extend(
+        roms_data
+            .iter()
+            .filter_map(|rom| rom.get("serial"))
+            .map(|serial| decode_html_entities(serial).to_string()),
+    );
+    let mut rom = disc_builder(roms_data, regions, &name_info);
+    rom.category = category;
+    let clone_of = game_data
+        .get("cloneof")
+        .and_then(|parent| name_parser(&mut &parent[..]).ok())
+        .map(|parent| parent.name);
+
+    Ok(game_builder(rom, name_info, serials, clone_of))
 }
 
 /// Parses all <game> entries in the DAT file
@@ -222,17 +365,40 @@ fn entries_parser(input: &mut &str) -> Result<Vec<DatGame>> {
     repeat(1.., entry_parser).parse_next(input)
 }
 
+/// Parses the disc number of a name flag like `Disc 2` (Redump) or `Disc 2 of 3` (TOSEC)
+pub fn parse_disc_number(flag: &str) -> Option<i32> {
+    let number = flag
+        .strip_prefix("Disc ")
+        .or_else(|| flag.strip_prefix("Disk "))?;
+
+    number.split(" of ").next()?.trim().parse().ok()
+}
+
+/// Groups all entries with the same cleaned name into one game, so the regions,
+/// revisions and discs of a game end up as roms of the same logical game
 fn combine_game_entries(games: &mut Vec<DatGame>) {
-    games.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    if games.is_empty() {
+        return;
+    }
+
+    games.sort_by_key(|a| a.name.to_lowercase());
 
     let mut source_index = 0;
 
-    for read_index in 1..(games.len() - 1) {
+    for read_index in 1..games.len() {
         // TODO: could change to fuzzy match of names, or similar, for better detection of same games, if needed
         if games[source_index].name.to_lowercase() == games[read_index].name.to_lowercase() {
             // need to split games first, because otherwise we have two mutable references for games at the append
             let (left, right) = games.split_at_mut(read_index);
             left[source_index].roms.append(&mut right[0].roms);
+            for serial in right[0].serials.drain(..) {
+                if !left[source_index].serials.contains(&serial) {
+                    left[source_index].serials.push(serial);
+                }
+            }
+            if left[source_index].clone_of.is_none() {
+                left[source_index].clone_of = right[0].clone_of.take();
+            }
         } else {
             source_index += 1;
             if source_index != read_index {
@@ -245,28 +411,33 @@ fn combine_game_entries(games: &mut Vec<DatGame>) {
     games.truncate(source_index + 1);
 }
 
-fn write_data_to_db(console: Console, games: Vec<DatGame>) {
-    let conn = &mut establish_connection();
-
+fn write_data_to_db(conn: &mut SqliteConnection, console: Console, games: Vec<DatGame>) {
     let new_games: Vec<NewGame> = games
         .iter()
         .map(|db_game| NewGame::from_dat(db_game, Some(console.id)))
         .collect();
 
-    // insert games with dummy update, so new inserts always return the games' ids from db, because we need the ids
+    // upsert games updating their serials, so existing games are returned with their ids as well, because we need the ids.
+    // Removed games which were kept for their scanned files are restored.
     let inserted_games: Vec<Game> = insert_into(games_table)
         .values(&new_games)
         .on_conflict((games::title, console_id))
         .do_update()
-        .set(games::title.eq(games::title))
+        .set((
+            games::serial.eq(excluded(games::serial)),
+            games::clone_of.eq(excluded(games::clone_of)),
+            games::removed.eq(false),
+        ))
         .get_results::<Game>(conn)
         .expect("error saving games");
 
     let mut roms: Vec<NewRom> = Vec::new();
+    let mut dat_roms: Vec<&DatRom> = Vec::new();
 
     for (index, game) in games.iter().enumerate() {
         for rom in &game.roms {
             roms.push(NewRom::from_dat(rom, &inserted_games[index].id));
+            dat_roms.push(rom);
         }
     }
 
@@ -274,28 +445,80 @@ fn write_data_to_db(console: Console, games: Vec<DatGame>) {
         .values(roms)
         .on_conflict((roms::title, roms::game_id))
         .do_update()
-        .set(roms::title.eq(excluded(roms::title)))
+        .set((
+            roms::title.eq(excluded(roms::title)),
+            roms::size.eq(excluded(roms::size)),
+            roms::crc.eq(excluded(roms::crc)),
+            roms::sha1.eq(excluded(roms::sha1)),
+            roms::category.eq(excluded(roms::category)),
+            roms::removed.eq(false),
+        ))
         .get_results(conn)
         .expect("error saving roms");
 
     println!("saved {:?} roms in db", inserted_roms.len());
+
+    let tracks: Vec<NewRomTrack> = dat_roms
+        .iter()
+        .zip(&inserted_roms)
+        .flat_map(|(dat_rom, rom)| {
+            dat_rom
+                .tracks
+                .iter()
+                .map(|track| NewRomTrack::from_dat(track, rom.id))
+        })
+        .collect();
+
+    if !tracks.is_empty() {
+        insert_into(rom_tracks_table)
+            .values(tracks)
+            .on_conflict((rom_tracks::rom_id, rom_tracks::name))
+            .do_update()
+            .set((
+                rom_tracks::size.eq(excluded(rom_tracks::size)),
+                rom_tracks::crc.eq(excluded(rom_tracks::crc)),
+                rom_tracks::md5.eq(excluded(rom_tracks::md5)),
+                rom_tracks::sha1.eq(excluded(rom_tracks::sha1)),
+            ))
+            // sqlite only supports batch upserts with a returning clause
+            .get_results::<RomTrack>(conn)
+            .expect("error saving rom tracks");
+    }
 }
 
-pub fn parse_file(path_string: &str) -> Result<()> {
-    let path = Path::new(path_string);
+fn invalid_dat(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
 
-    let dat = fs::read_to_string(path).expect("error trying to read dat file");
-    let dat = &mut dat.as_str();
+/// Reads the console and the combined games of a DAT, without writing anything to the db
+pub fn parse_dat(conn: &mut SqliteConnection, dat: &str) -> io::Result<(Console, Vec<DatGame>)> {
+    let dat = &mut &dat[..];
 
-    let console = header_parser.parse_next(dat)?;
-    let mut games = entries_parser(dat).expect("error while parsing dat game entries");
+    let console_name = header_parser
+        .parse_next(dat)
+        .map_err(|e| invalid_dat(format!("error parsing the DAT header: {}", e)))?;
+    let console = get_console_by_name(conn, console_name)
+        .map_err(io::Error::other)?
+        .ok_or_else(|| invalid_dat(format!("unknown console in DAT: {}", console_name)))?;
+    let mut games = entries_parser(dat)
+        .map_err(|e| invalid_dat(format!("error parsing the DAT's games: {}", e)))?;
 
     combine_game_entries(&mut games);
-    write_data_to_db(console, games);
+    Ok((console, games))
+}
+
+pub fn parse_file(conn: &mut SqliteConnection, path_string: &str) -> io::Result<()> {
+    let path = Path::new(path_string);
+
+    let dat = fs::read_to_string(path)?;
+
+    let (console, games) = parse_dat(conn, &dat)?;
+    write_data_to_db(conn, console, games);
     Ok(())
 }
 
-fn console_parser(input: &mut &str) -> Result<Console> {
+/// The console's name, without the manufacturer and the DAT's flags
+fn console_parser<'s>(input: &mut &'s str) -> Result<&'s str> {
     let (_manufacturer, mut name) =
         separated_pair(take_until(1.., "-"), "-", take_until(0.., "</name")).parse_next(input)?;
 
@@ -309,18 +532,18 @@ fn console_parser(input: &mut &str) -> Result<Console> {
 
     println!("name: {:?}\n", &trimmed_name);
 
-    Ok(get_console_by_name(trimmed_name))
+    Ok(trimmed_name)
 }
 
-fn header_name_parser(input: &mut &str) -> Result<Console> {
-    println!("header_name: {:?}\n", &input[0..1000]);
+fn header_name_parser<'s>(input: &mut &'s str) -> Result<&'s str> {
+    println!("header_name: {:?}\n", &input[..input.len().min(1000)]);
 
     let header_name_start = preceded(multispace0, "<name>");
     // println!("header_name2: {:?}\n", header_name_start);
     delimited(header_name_start, console_parser, "</name>").parse_next(input)
 }
 
-fn header_parser(input: &mut &str) -> Result<Console> {
+fn header_parser<'s>(input: &mut &'s str) -> Result<&'s str> {
     let console =
     seq!(_:take_until(1.., "<header>"), _: "<header>",_:take_until(1.., "<name>"), _:multispace0, header_name_parser, _:terminated(take_until(1.., "</header>"), "</header>"))
     .parse_next(input)?.0;
@@ -362,6 +585,8 @@ fn header_parser(input: &mut &str) -> Result<Console> {
 mod tests {
     use html_escape::decode_html_entities;
 
+    use crate::db::test_connection;
+
     use super::*;
 
     #[test]
@@ -378,7 +603,7 @@ mod tests {
     fn test_dat_file_parse() {
         let dat_file_path = String::from("tests/SNES v Recommended.dat");
 
-        parse_file(&dat_file_path).expect("error parsing games from dat.");
+        parse_file(&mut test_connection(), &dat_file_path).expect("error parsing games from dat.");
 
         // println!("{:#?}", &games[0..2]);
     }
@@ -389,11 +614,21 @@ mod tests {
             "tests/Nintendo - Super Nintendo Entertainment System (20251012-045317).dat",
         );
 
-        parse_file(&dat_file_path).expect("error parsing games from dat.");
+        parse_file(&mut test_connection(), &dat_file_path).expect("error parsing games from dat.");
 
         // println!("{:#?}", &games[0..2]);
     }
 
+    #[test]
+    fn test_parse_dat_errors() {
+        let conn = &mut test_connection();
+
+        let unknown =
+            "<datafile>\n\t<header>\n\t\t<name>Acme - Toaster</name>\n\t</header>\n</datafile>";
+        assert!(parse_dat(conn, unknown).is_err());
+        assert!(parse_dat(conn, "not a dat").is_err());
+    }
+
     #[test]
     fn test_value() {
         let mut input = r#""test""#;
@@ -467,7 +702,14 @@ mod tests {
                 md5: "d273dd449b204a6eb90f611e5a72f80c".to_string(),
                 regions: vec!["Australia".to_string(), "Europe".to_string()],
                 size: 2097152,
+                disc: None,
+                crc: "de112322".to_string(),
+                sha1: "cf57dc4183c6e5aadba25019d82e61c44c0de113".to_string(),
+                category: String::new(),
+                tracks: Vec::new(),
             }],
+            serials: Vec::new(),
+            clone_of: None,
         };
 
         // println!("{:#?}", output);
@@ -495,7 +737,14 @@ mod tests {
                 md5: "d273dd449b204a6eb90f611e5a72f80c".to_string(),
                 regions: vec!["Europe".to_string()],
                 size: 2097152,
+                disc: None,
+                crc: "de112322".to_string(),
+                sha1: "cf57dc4183c6e5aadba25019d82e61c44c0de113".to_string(),
+                category: String::new(),
+                tracks: Vec::new(),
             }],
+            serials: Vec::new(),
+            clone_of: None,
         };
 
         println!("{:#?}", output);
@@ -529,7 +778,14 @@ mod tests {
                     md5: "d273dd449b204a6eb90f611e5a72f80c".to_string(),
                     regions: vec!["Australia".to_string(), "Europe".to_string()],
                     size: 2097152,
+                    disc: None,
+                    crc: "de112322".to_string(),
+                    sha1: "cf57dc4183c6e5aadba25019d82e61c44c0de113".to_string(),
+                    category: String::new(),
+                    tracks: Vec::new(),
                 }],
+                serials: Vec::new(),
+                clone_of: None,
             },
             DatGame {
                 name: "ActRaiser".to_string(),
@@ -538,7 +794,14 @@ mod tests {
                     md5: "9b36075b53dec1a506b1f9334e670c63".to_string(),
                     regions: vec!["Europe".to_string()],
                     size: 1048576,
+                    disc: None,
+                    crc: "09097b2b".to_string(),
+                    sha1: "b76621e0b9d882c8b8463203f5423ca7d45cc5bf".to_string(),
+                    category: String::new(),
+                    tracks: Vec::new(),
                 }],
+                serials: Vec::new(),
+                clone_of: None,
             },
         ];
 
@@ -547,6 +810,152 @@ mod tests {
         assert_eq!(correct, output)
     }
 
+    #[test]
+    fn test_disc_number() {
+        assert_eq!(Some(1), parse_disc_number("Disc 1"));
+        assert_eq!(Some(2), parse_disc_number("Disc 2 of 3"));
+        assert_eq!(None, parse_disc_number("Rev 1"));
+
+        let mut input = "Final Fantasy VII (USA) (Disc 2)";
+        let output = name_parser(&mut input).unwrap();
+
+        assert_eq!("Final Fantasy VII", output.name);
+        assert_eq!(Some(2), output.disc);
+    }
+
+    #[test]
+    fn test_disc_tracks() {
+        let mut input = r#"
+        <game name="Final Fantasy VII (USA) (Disc 2)">
+            <category>Games</category>
+            <description>Final Fantasy VII (USA) (Disc 2)</description>
+            <rom name="Final Fantasy VII (USA) (Disc 2).bin" size="717488112" crc="5C9E0E31" md5="c0d7bb2ae8c1b5b7bbcfc1a1efae41ac" sha1="fa4d4c1ccf4bb7a4f3a1bd3a2d4ac4a9d8c7a0fa"/>
+            <rom name="Final Fantasy VII (USA) (Disc 2).cue" size="101" crc="a1c1d2ef" md5="0d6d5a8e2a3b1d1e9c9f0c1f9a5b0e38" sha1="3f1a6e9b0c2f4a1e8d7c6b5a4f3e2d1c0b9a8f7e"/>
+        </game>
+        <game name="Final Fantasy VII (USA) (Disc 3)">
+            <rom name="Final Fantasy VII (USA) (Disc 3).cue" size="101"/>
+            <rom name="Final Fantasy VII (USA) (Disc 3) (Track 1).bin" size="2352"/>
+            <rom name="Final Fantasy VII (USA) (Disc 3) (Track 2).bin" size="4704"/>
+        </game>
+        "#;
+
+        let output = entries_parser(&mut input).unwrap();
+
+        assert_eq!(2, output.len());
+        let rom = &output[0].roms[0];
+        assert_eq!("Final Fantasy VII (USA) (Disc 2).cue", rom.name);
+        assert_eq!(Some(2), rom.disc);
+        assert_eq!("Games", rom.category);
+        assert_eq!("", output[1].roms[0].category);
+        assert_eq!(
+            vec![DatTrack {
+                name: "Final Fantasy VII (USA) (Disc 2).bin".to_string(),
+                size: 717488112,
+                crc: "5c9e0e31".to_string(),
+                md5: "c0d7bb2ae8c1b5b7bbcfc1a1efae41ac".to_string(),
+                sha1: "fa4d4c1ccf4bb7a4f3a1bd3a2d4ac4a9d8c7a0fa".to_string(),
+            }],
+            rom.tracks
+        );
+        assert_eq!(
+            vec![4704],
+            output[1].roms[0]
+                .tracks
+                .iter()
+                .skip(1)
+                .map(|track| track.size)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_serials() {
+        let mut input = r#"
+        <game name="Gran Turismo (USA)">
+            <description>Gran Turismo (USA)</description>
+            <serial>SCUS-94194, SCUS-94194GH</serial>
+            <rom name="Gran Turismo (USA).cue" size="95"/>
+        </game>
+        <game name="Gran Turismo (Europe)">
+            <rom name="Gran Turismo (Europe).cue" size="95" serial="SCES-00984"/>
+        </game>
+        <game name="Gran Turismo (Japan)">
+            <rom name="Gran Turismo (Japan).cue" size="95"/>
+            <serial>SCPS-10045</serial>
+        </game>
+        "#;
+
+        let mut output = entries_parser(&mut input).unwrap();
+        assert_eq!(vec!["SCUS-94194", "SCUS-94194GH"], output[0].serials);
+        assert_eq!(vec!["SCES-00984"], output[1].serials);
+        assert_eq!(vec!["SCPS-10045"], output[2].serials);
+
+        combine_game_entries(&mut output);
+        assert_eq!(
+            "SCUS-94194, SCUS-94194GH, SCES-00984, SCPS-10045",
+            NewGame::from_dat(&output[0], Some(1)).serial
+        );
+    }
+
+    #[test]
+    fn test_clone_of() {
+        let mut input = r#"
+        <game name="Pocket Monsters - Aka (Japan)" cloneof="Pokemon - Red Version (USA, Europe) (SGB Enhanced)">
+            <rom name="Pocket Monsters - Aka (Japan).gb" size="524288"/>
+        </game>
+        <game name="Pokemon - Red Version (USA, Europe) (SGB Enhanced)">
+            <rom name="Pokemon - Red Version (USA, Europe) (SGB Enhanced).gb" size="1048576"/>
+        </game>
+        <game name="Pokemon - Red Version (Germany)" cloneof="Pokemon - Red Version (USA, Europe) (SGB Enhanced)">
+            <rom name="Pokemon - Red Version (Germany).gb" size="1048576"/>
+        </game>
+        "#;
+
+        let mut output = entries_parser(&mut input).unwrap();
+        combine_game_entries(&mut output);
+
+        assert_eq!(2, output.len());
+        assert_eq!(
+            Some("Pokemon - Red Version".to_string()),
+            output[0].clone_of
+        );
+        assert_eq!(None, output[1].clone_of);
+    }
+
+    #[test]
+    fn test_combine_discs() {
+        let disc = |name: &str, disc: i32| DatGame {
+            name: "Final Fantasy VII".to_string(),
+            roms: vec![DatRom {
+                name: name.to_string(),
+                md5: String::new(),
+                regions: vec!["USA".to_string()],
+                size: 0,
+                disc: Some(disc),
+                crc: String::new(),
+                sha1: String::new(),
+                category: String::new(),
+                tracks: Vec::new(),
+            }],
+            serials: Vec::new(),
+            clone_of: None,
+        };
+
+        let mut games = vec![
+            disc("Final Fantasy VII (USA) (Disc 1).cue", 1),
+            disc("Final Fantasy VII (USA) (Disc 2).cue", 2),
+            disc("Final Fantasy VII (USA) (Disc 3).cue", 3),
+        ];
+
+        combine_game_entries(&mut games);
+
+        assert_eq!(1, games.len());
+        assert_eq!(
+            vec![Some(1), Some(2), Some(3)],
+            games[0].roms.iter().map(|rom| rom.disc).collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_name_parser() {
         let mut input = "Secret of Mana (Europe) (Rev 1)";
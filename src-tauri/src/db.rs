@@ -0,0 +1,200 @@
+// Pooled connections to the sqlite database, kept in tauri's managed state like the config.
+// The pragmas are applied once, when the pool opens a connection. The migrations are embedded,
+// so the app creates and upgrades the users' databases itself.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::{
+    connection::SimpleConnection,
+    r2d2::{ConnectionManager, CustomizeConnection, Error, Pool, PoolError, PooledConnection},
+    sql_query,
+    sql_types::Text,
+    RunQueryDsl, SqliteConnection,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use dotenvy::dotenv;
+
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+pub type DbConnection = PooledConnection<ConnectionManager<SqliteConnection>>;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// Every known console, inserted on each start so new ones reach existing databases
+const CONSOLES: &str = include_str!("../db/scripts/consoles.sql");
+
+const DATABASE_FILE: &str = "romana.db";
+
+const IN_MEMORY: &str = ":memory:";
+
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), Error> {
+        conn.batch_execute(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA foreign_keys = ON;
+             PRAGMA busy_timeout = 5000;",
+        )
+        .map_err(Error::QueryError)
+    }
+}
+
+/// `DATABASE_URL` from the environment or the `.env` file, if set
+fn env_database_url() -> Option<String> {
+    dotenv().ok();
+
+    env::var("DATABASE_URL").ok().filter(|url| !url.is_empty())
+}
+
+/// The database of `DATABASE_URL` if set, for development, otherwise `romana.db` in the
+/// app's data directory
+pub fn database_url(data_dir: &Path) -> String {
+    env_database_url().unwrap_or_else(|| data_dir.join(DATABASE_FILE).to_string_lossy().to_string())
+}
+
+/// Opens the pool. An in-memory database gets a single connection which is never closed,
+/// as every connection would open a database of its own.
+pub fn create_pool(database_url: &str) -> Result<DbPool, PoolError> {
+    let builder = Pool::builder().connection_customizer(Box::new(ConnectionOptions));
+
+    let builder = if database_url == IN_MEMORY {
+        builder.max_size(1).idle_timeout(None).max_lifetime(None)
+    } else {
+        builder.max_size(8)
+    };
+
+    builder.build(ConnectionManager::new(database_url))
+}
+
+/// Copies the database into the backup folder, named after its last migration. An existing
+/// backup of that version is kept.
+fn backup(conn: &mut SqliteConnection, backup_dir: &Path, version: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(backup_dir)?;
+
+    let path = backup_dir.join(format!("romana-{}.db", version));
+    if !path.exists() {
+        // unlike copying the file, this includes what is still in the WAL
+        sql_query("VACUUM INTO ?")
+            .bind::<Text, _>(path.to_string_lossy())
+            .execute(conn)
+            .map_err(io::Error::other)?;
+    }
+
+    Ok(path)
+}
+
+/// Brings the database up to date. An existing database is backed up into `backup_dir`
+/// before pending migrations run, then the consoles are seeded. Returns the backup's path.
+pub fn prepare(
+    conn: &mut SqliteConnection,
+    backup_dir: Option<&Path>,
+) -> io::Result<Option<PathBuf>> {
+    let applied = conn.applied_migrations().map_err(io::Error::other)?;
+    let pending = conn
+        .has_pending_migration(MIGRATIONS)
+        .map_err(io::Error::other)?;
+
+    let backup_path = match (applied.iter().max(), backup_dir) {
+        (Some(version), Some(backup_dir)) if pending => {
+            Some(backup(conn, backup_dir, &version.to_string())?)
+        }
+        _ => None,
+    };
+
+    // some migrations rebuild tables, which sqlite only allows without foreign key checks
+    conn.batch_execute("PRAGMA foreign_keys = OFF")
+        .map_err(io::Error::other)?;
+    let migrated = conn.run_pending_migrations(MIGRATIONS).map(|_| ());
+    conn.batch_execute("PRAGMA foreign_keys = ON")
+        .map_err(io::Error::other)?;
+    migrated.map_err(io::Error::other)?;
+    conn.batch_execute(CONSOLES).map_err(io::Error::other)?;
+
+    Ok(backup_path)
+}
+
+/// A connection to a new in-memory database, migrated and seeded with the consoles, for the
+/// tests to add their fixtures to
+#[cfg(test)]
+pub fn test_connection() -> DbConnection {
+    let pool = create_pool(IN_MEMORY).expect("error opening the test database");
+    let mut conn = pool.get().expect("error getting a test connection");
+    prepare(&mut conn, None).expect("error preparing the test database");
+
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::{prelude::*, sql_types::Integer};
+
+    use crate::{routes::console_routes, schemas::consoles_table};
+
+    use super::*;
+
+    #[derive(QueryableByName)]
+    struct Pragma {
+        #[diesel(sql_type = Integer)]
+        foreign_keys: i32,
+    }
+
+    #[test]
+    fn test_create_pool() {
+        let pool = create_pool(IN_MEMORY).unwrap();
+
+        pool.get()
+            .unwrap()
+            .batch_execute("CREATE TABLE test (id INTEGER PRIMARY KEY)")
+            .unwrap();
+        // the table is still there with the next connection from the pool
+        pool.get()
+            .unwrap()
+            .batch_execute("INSERT INTO test (id) VALUES (1)")
+            .unwrap();
+
+        let pragma: Pragma = sql_query("PRAGMA foreign_keys")
+            .get_result(&mut pool.get().unwrap())
+            .unwrap();
+        assert_eq!(1, pragma.foreign_keys);
+    }
+
+    #[test]
+    fn test_prepare() {
+        let dir = env::temp_dir().join("romana_db");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let pool = create_pool(&dir.join(DATABASE_FILE).to_string_lossy()).unwrap();
+        let conn = &mut pool.get().unwrap();
+
+        // a new database is not backed up
+        assert_eq!(None, prepare(conn, Some(&dir.join("backups"))).unwrap());
+        assert!(console_routes::get_consoles(conn)
+            .iter()
+            .any(|console| console.name == "Super Nintendo Entertainment System"));
+
+        // seeding again changes nothing
+        let count = |conn: &mut SqliteConnection| -> i64 {
+            consoles_table::table.count().get_result(conn).unwrap()
+        };
+        let consoles = count(conn);
+        prepare(conn, Some(&dir.join("backups"))).unwrap();
+        assert_eq!(consoles, count(conn));
+
+        // an upgrade is backed up first
+        conn.revert_last_migration(MIGRATIONS).unwrap();
+        let backup_path = prepare(conn, Some(&dir.join("backups")))
+            .unwrap()
+            .expect("no backup");
+        assert!(backup_path.is_file());
+        assert!(!conn.has_pending_migration(MIGRATIONS).unwrap());
+
+        drop(pool);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
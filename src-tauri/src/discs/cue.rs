@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Sync pattern at the start of every raw data sector
+const SYNC: [u8; 12] = [0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackMode {
+    Audio,
+    Mode1,
+    Mode2,
+}
+
+impl TrackMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TrackMode::Audio => "AUDIO",
+            TrackMode::Mode1 => "MODE1/2352",
+            TrackMode::Mode2 => "MODE2/2352",
+        }
+    }
+
+    /// Reads the mode byte of the first raw sector, tracks without sync pattern are audio
+    pub fn detect(path: &Path) -> io::Result<Self> {
+        let mut header = [0; 16];
+        let read = File::open(path)?.read(&mut header)?;
+
+        Ok(match (read, header[..12] == SYNC, header[15]) {
+            (16, true, 1) => TrackMode::Mode1,
+            (16, true, 2) => TrackMode::Mode2,
+            _ => TrackMode::Audio,
+        })
+    }
+}
+
+/// A `FILE` of a cue sheet with the numbers of the tracks it contains
+#[derive(Debug, PartialEq)]
+pub struct CueFile {
+    pub name: String,
+    pub tracks: Vec<u32>,
+}
+
+/// Reads the files a cue sheet references, in order
+pub fn parse(cue: &str) -> Vec<CueFile> {
+    let mut files: Vec<CueFile> = Vec::new();
+
+    for line in cue.lines().map(str::trim) {
+        let Some((command, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let rest = rest.trim();
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                // quoted names may contain spaces, unquoted ones end before the file type
+                let name = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                    None => rest.rsplit_once(' ').map_or(rest, |(name, _)| name),
+                };
+                files.push(CueFile {
+                    name: name.to_string(),
+                    tracks: Vec::new(),
+                });
+            }
+            "TRACK" => {
+                let number = rest.split_whitespace().next().and_then(|n| n.parse().ok());
+                if let (Some(file), Some(number)) = (files.last_mut(), number) {
+                    file.tracks.push(number);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    files
+}
+
+/// Writes a cue sheet with one track per file like Redump does. With `pregap` every track
+/// after the first starts with a two second `INDEX 00` pregap stored in its file.
+pub fn render(tracks: &[(&str, TrackMode)], pregap: bool, line_ending: &str) -> String {
+    let mut cue = String::new();
+
+    for (index, (name, mode)) in tracks.iter().enumerate() {
+        let mut line = |text: String| {
+            cue.push_str(&text);
+            cue.push_str(line_ending);
+        };
+
+        line(format!("FILE \"{}\" BINARY", name));
+        line(format!("  TRACK {:02} {}", index + 1, mode.as_str()));
+        if pregap && index > 0 {
+            line("    INDEX 00 00:00:00".to_string());
+            line("    INDEX 01 00:02:00".to_string());
+        } else {
+            line("    INDEX 01 00:00:00".to_string());
+        }
+    }
+
+    cue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let tracks = [
+            ("Game (Track 1).bin", TrackMode::Mode2),
+            ("Game (Track 2).bin", TrackMode::Audio),
+        ];
+        let cue = render(&tracks, true, "\r\n");
+
+        assert_eq!(
+            "FILE \"Game (Track 1).bin\" BINARY\r\n  TRACK 01 MODE2/2352\r\n    INDEX 01 00:00:00\r\n\
+             FILE \"Game (Track 2).bin\" BINARY\r\n  TRACK 02 AUDIO\r\n    INDEX 00 00:00:00\r\n    INDEX 01 00:02:00\r\n",
+            cue
+        );
+        assert_eq!(
+            vec![
+                CueFile {
+                    name: "Game (Track 1).bin".to_string(),
+                    tracks: vec![1],
+                },
+                CueFile {
+                    name: "Game (Track 2).bin".to_string(),
+                    tracks: vec![2],
+                },
+            ],
+            parse(&cue)
+        );
+        assert_eq!(
+            "game.bin",
+            parse("file game.bin binary\ntrack 1 mode1/2352")[0].name
+        );
+    }
+}
@@ -0,0 +1,41 @@
+/// Reads the track files of a GD-ROM descriptor. After the track count every line is
+/// `number lba type sector_size file offset`, with the file name quoted if it has spaces.
+pub fn parse(gdi: &str) -> Vec<String> {
+    gdi.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut rest = line.trim();
+            for _ in 0..4 {
+                rest = rest.split_once(char::is_whitespace)?.1.trim_start();
+            }
+
+            let name = match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split('"').next()?,
+                None => rest.split_whitespace().next()?,
+            };
+            Some(name.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let gdi = "3\r\n\
+                   1 0 4 2352 \"Crazy Taxi (USA) (Track 1).bin\" 0\r\n\
+                   2 756 0 2352 track02.raw 0\r\n\
+                   3 45000 4 2352 \"Crazy Taxi (USA) (Track 3).bin\" 0\r\n";
+
+        assert_eq!(
+            vec![
+                "Crazy Taxi (USA) (Track 1).bin",
+                "track02.raw",
+                "Crazy Taxi (USA) (Track 3).bin"
+            ],
+            parse(gdi)
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    chd::{self, Chd},
+    config::AppConfig,
+    hashing::{hash_file, hash_reader, FileHashes},
+    models::{Console, Rom, RomTrack, RomWithTracks},
+    routes::disc_routes,
+};
+
+pub mod cue;
+pub mod gdi;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackStatus {
+    Ok,
+    Missing,
+    WrongHash,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrackReport {
+    #[serde(flatten)]
+    pub track: RomTrack,
+    pub status: TrackStatus,
+    /// the file the sheet references for this track
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiscReport {
+    pub rom: Rom,
+    /// the `.cue` or `.gdi` file or the CHD image, `None` if neither was found nor regenerated
+    pub sheet: Option<PathBuf>,
+    /// the cue sheet was missing and has been written from the DAT's tracks
+    pub regenerated: bool,
+    /// the sheet references every track of the DAT with the right hashes
+    pub complete: bool,
+    pub tracks: Vec<TrackReport>,
+}
+
+/// Discs are either next to the console's other roms or in a folder named after the disc
+fn sheet_candidates(rom: &Rom, rom_dir: &Path) -> [PathBuf; 2] {
+    let stem = Path::new(&rom.title)
+        .file_stem()
+        .unwrap_or_default()
+        .to_os_string();
+
+    [
+        rom_dir.join(&rom.title),
+        rom_dir.join(stem).join(&rom.title),
+    ]
+}
+
+fn is_gdi(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gdi"))
+}
+
+/// Returns the track files the sheet references, resolved against the sheet's folder
+fn referenced_files(sheet: &Path) -> io::Result<Vec<PathBuf>> {
+    let text = String::from_utf8_lossy(&fs::read(sheet)?).to_string();
+    let names = if is_gdi(sheet) {
+        gdi::parse(&text)
+    } else {
+        cue::parse(&text)
+            .into_iter()
+            .map(|file| file.name)
+            .collect()
+    };
+    let dir = sheet.parent().unwrap_or(Path::new(""));
+
+    Ok(names.into_iter().map(|name| dir.join(name)).collect())
+}
+
+/// Writes the disc's cue sheet for the DAT's tracks in `dir`, `None` if a track is missing.
+/// Of the layouts Redump uses, the one matching the DAT's hash of the sheet is written,
+/// otherwise the most common one with pregaps and CRLF line endings.
+pub fn regenerate_cue(rom: &Rom, tracks: &[RomTrack], dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut modes = Vec::new();
+    for track in tracks {
+        let path = dir.join(&track.name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        modes.push((track.name.as_str(), cue::TrackMode::detect(&path)?));
+    }
+
+    let cues: Vec<String> = [(true, "\r\n"), (false, "\r\n"), (true, "\n"), (false, "\n")]
+        .into_iter()
+        .map(|(pregap, line_ending)| cue::render(&modes, pregap, line_ending))
+        .collect();
+    let cue = cues
+        .iter()
+        .find(|cue| {
+            hash_reader(&mut cue.as_bytes())
+                .is_ok_and(|hashes| hashes.md5.eq_ignore_ascii_case(&rom.md5))
+        })
+        .unwrap_or(&cues[0]);
+
+    let path = dir.join(&rom.title);
+    fs::write(&path, cue)?;
+
+    Ok(Some(path))
+}
+
+/// Finds the track among the sheet's files, by its DAT name first and by its hashes
+/// otherwise, so renamed track files still count
+fn verify_track(track: RomTrack, files: &[(PathBuf, FileHashes)]) -> TrackReport {
+    let named = files.iter().find(|(path, _)| {
+        path.file_name()
+            .is_some_and(|name| name == track.name.as_str())
+    });
+    let matching = named
+        .filter(|(_, hashes)| track.matches(hashes))
+        .or_else(|| files.iter().find(|(_, hashes)| track.matches(hashes)));
+
+    let (status, path) = match (matching, named) {
+        (Some((path, _)), _) => (TrackStatus::Ok, Some(path.clone())),
+        (None, Some((path, _))) => (TrackStatus::WrongHash, Some(path.clone())),
+        (None, None) => (TrackStatus::Missing, None),
+    };
+
+    TrackReport {
+        track,
+        status,
+        path,
+    }
+}
+
+/// Compares the tracks of a CHD image with the DAT's tracks in order
+fn verify_chd_disc(rom: Rom, tracks: Vec<RomTrack>, image: PathBuf) -> io::Result<DiscReport> {
+    let mut chd = Chd::open(&image)?;
+    let image_tracks = chd::cdrom::tracks(&mut chd)?;
+    let hashes = chd::track_hashes(&mut chd, &image_tracks)?;
+
+    let tracks: Vec<TrackReport> = tracks
+        .into_iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let status = match hashes.get(index) {
+                Some(hashes) if track.matches(hashes) => TrackStatus::Ok,
+                Some(_) => TrackStatus::WrongHash,
+                None => TrackStatus::Missing,
+            };
+            TrackReport {
+                path: hashes.get(index).map(|_| image.clone()),
+                track,
+                status,
+            }
+        })
+        .collect();
+    let complete = hashes.len() == tracks.len()
+        && tracks.iter().all(|report| report.status == TrackStatus::Ok);
+
+    Ok(DiscReport {
+        rom,
+        sheet: Some(image),
+        regenerated: false,
+        complete,
+        tracks,
+    })
+}
+
+/// Verifies a disc as a unit: the sheet is parsed for its track files, which are compared
+/// with the DAT's tracks. Without a sheet a CHD image of the disc is read instead, and only
+/// without either a missing cue sheet is regenerated, if `regenerate` is set.
+pub fn verify_disc(
+    disc: RomWithTracks,
+    rom_dir: &Path,
+    regenerate: bool,
+) -> io::Result<DiscReport> {
+    let RomWithTracks { rom, tracks } = disc;
+    let candidates = sheet_candidates(&rom, rom_dir);
+
+    let mut sheet = candidates.iter().find(|path| path.is_file()).cloned();
+    if sheet.is_none() {
+        let image = candidates
+            .iter()
+            .map(|path| path.with_extension("chd"))
+            .find(|path| path.is_file());
+        if let Some(image) = image {
+            return verify_chd_disc(rom, tracks, image);
+        }
+    }
+
+    let mut regenerated = false;
+    if sheet.is_none() && regenerate && !is_gdi(Path::new(&rom.title)) {
+        for dir in candidates.iter().filter_map(|path| path.parent()) {
+            if let Some(path) = regenerate_cue(&rom, &tracks, dir)? {
+                sheet = Some(path);
+                regenerated = true;
+                break;
+            }
+        }
+    }
+
+    let files: Vec<(PathBuf, FileHashes)> = match &sheet {
+        Some(sheet) => referenced_files(sheet)?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| hash_file(&path).map(|hashes| (path, hashes)))
+            .collect::<io::Result<_>>()?,
+        None => Vec::new(),
+    };
+
+    let tracks: Vec<TrackReport> = tracks
+        .into_iter()
+        .map(|track| verify_track(track, &files))
+        .collect();
+    let complete = sheet.is_some() && tracks.iter().all(|report| report.status == TrackStatus::Ok);
+
+    Ok(DiscReport {
+        rom,
+        sheet,
+        regenerated,
+        complete,
+        tracks,
+    })
+}
+
+/// Verifies every disc of the console in its rom folder
+pub fn scan_console(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    config: &AppConfig,
+    regenerate: bool,
+) -> io::Result<Vec<DiscReport>> {
+    let rom_dir = config.rom_dir(console)?;
+
+    disc_routes::get_disc_roms_for_console(conn, console.id)
+        .map_err(io::Error::other)?
+        .into_iter()
+        .map(|disc| verify_disc(disc, &rom_dir, regenerate))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: i32, name: &str, data: &[u8]) -> RomTrack {
+        let hashes = hash_reader(&mut &data[..]).unwrap();
+
+        RomTrack {
+            id,
+            rom_id: 1,
+            name: name.to_string(),
+            size: hashes.size as i64,
+            crc: hashes.crc32,
+            md5: hashes.md5,
+            sha1: hashes.sha1,
+        }
+    }
+
+    #[test]
+    fn test_verify_disc() {
+        let dir = std::env::temp_dir().join("romana_verify_disc");
+        let _ = fs::remove_dir_all(&dir);
+        let disc_dir = dir.join("Game (USA)");
+        fs::create_dir_all(&disc_dir).unwrap();
+
+        let mut data_track = vec![0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0];
+        data_track.extend([0, 2, 0, 2]);
+        data_track.resize(2352, 0);
+        let audio_track = vec![7; 2352];
+        fs::write(disc_dir.join("Game (USA) (Track 1).bin"), &data_track).unwrap();
+        fs::write(disc_dir.join("Game (USA) (Track 2).bin"), &audio_track).unwrap();
+
+        let expected_cue = cue::render(
+            &[
+                ("Game (USA) (Track 1).bin", cue::TrackMode::Mode2),
+                ("Game (USA) (Track 2).bin", cue::TrackMode::Audio),
+            ],
+            false,
+            "\r\n",
+        );
+        let rom = Rom {
+            id: 1,
+            title: "Game (USA).cue".to_string(),
+            md5: hash_reader(&mut expected_cue.as_bytes()).unwrap().md5,
+            game_id: 1,
+            ..Default::default()
+        };
+        let disc = || RomWithTracks {
+            rom: rom.clone(),
+            tracks: vec![
+                track(1, "Game (USA) (Track 1).bin", &data_track),
+                track(2, "Game (USA) (Track 2).bin", &audio_track),
+            ],
+        };
+
+        let missing = verify_disc(disc(), &dir, false).unwrap();
+        assert!(!missing.complete);
+        assert_eq!(None, missing.sheet);
+
+        let report = verify_disc(disc(), &dir, true).unwrap();
+        assert!(report.regenerated);
+        assert!(report.complete);
+        assert_eq!(
+            expected_cue,
+            fs::read_to_string(disc_dir.join("Game (USA).cue")).unwrap()
+        );
+
+        fs::write(disc_dir.join("Game (USA) (Track 2).bin"), b"bad dump").unwrap();
+        let report = verify_disc(disc(), &dir, true).unwrap();
+        assert!(!report.regenerated);
+        assert!(!report.complete);
+        assert_eq!(TrackStatus::Ok, report.tracks[0].status);
+        assert_eq!(TrackStatus::WrongHash, report.tracks[1].status);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
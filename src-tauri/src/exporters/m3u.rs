@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use crate::{dat_parser::parser::parse_disc_number, models::Rom};
+
+/// A single playable entry of a game, either one rom or all discs of a multi-disc release
+#[derive(Debug, PartialEq)]
+pub enum PlayableEntry<'a> {
+    Single(&'a Rom),
+    DiscSet(DiscSet<'a>),
+}
+
+/// All discs of one release of a game, e.g. the four discs of `Final Fantasy VIII (USA)`
+#[derive(Debug, PartialEq)]
+pub struct DiscSet<'a> {
+    /// rom name without the disc flag, used as playlist name
+    pub name: String,
+    /// discs sorted by their number
+    pub discs: Vec<&'a Rom>,
+}
+
+impl DiscSet<'_> {
+    pub fn playlist_file_name(&self) -> String {
+        format!("{}.m3u", self.name)
+    }
+
+    /// Directory used by ES-DE and Batocera for the directory-as-game layout,
+    /// which holds the discs together with the playlist of the same name
+    pub fn game_directory(&self, parent: &Path) -> PathBuf {
+        parent.join(self.playlist_file_name())
+    }
+
+    pub fn disc_files(&self) -> Vec<String> {
+        self.discs.iter().map(|rom| rom.title.clone()).collect()
+    }
+}
+
+/// Returns the disc number of a rom, falling back to the flags in its name for roms
+/// imported before the disc was stored
+pub fn disc_number(rom: &Rom) -> Option<i32> {
+    rom.disc
+        .or_else(|| name_flags(&rom.title).find_map(parse_disc_number))
+}
+
+/// Returns the rom's file stem with the disc flag removed, so all discs of a release share it
+pub fn disc_set_name(rom_title: &str) -> String {
+    let stem = Path::new(rom_title)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+
+    let mut name = stem.to_string();
+    if let Some(flag) = name_flags(&stem).find(|flag| parse_disc_number(flag).is_some()) {
+        name = name.replace(&format!(" ({})", flag), "");
+    }
+
+    name
+}
+
+/// Iterates over the contents of all parentheses in a name, e.g. `USA` and `Disc 1`
+fn name_flags(name: &str) -> impl Iterator<Item = &str> {
+    name.split('(')
+        .skip(1)
+        .filter_map(|part| part.split_once(')').map(|(flag, _)| flag))
+}
+
+/// Groups the roms of a game into playable entries. Discs of the same release become one
+/// disc set, a release with only a single disc stays a single entry.
+pub fn playable_entries(roms: &[Rom]) -> Vec<PlayableEntry<'_>> {
+    let mut entries: Vec<PlayableEntry> = Vec::new();
+
+    for rom in roms {
+        if disc_number(rom).is_none() {
+            entries.push(PlayableEntry::Single(rom));
+            continue;
+        }
+
+        let name = disc_set_name(&rom.title);
+        let existing = entries.iter_mut().find_map(|entry| match entry {
+            PlayableEntry::DiscSet(set) if set.name == name => Some(set),
+            _ => None,
+        });
+
+        match existing {
+            Some(set) => set.discs.push(rom),
+            None => entries.push(PlayableEntry::DiscSet(DiscSet {
+                name,
+                discs: vec![rom],
+            })),
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|entry| match entry {
+            PlayableEntry::DiscSet(set) if set.discs.len() == 1 => {
+                PlayableEntry::Single(set.discs[0])
+            }
+            PlayableEntry::DiscSet(mut set) => {
+                set.discs.sort_by_key(|rom| disc_number(rom));
+                PlayableEntry::DiscSet(set)
+            }
+            single => single,
+        })
+        .collect()
+}
+
+/// Renders an m3u playlist with one disc file per line
+pub fn render(files: &[String]) -> String {
+    files.iter().map(|file| format!("{}\n", file)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(id: i32, title: &str, disc: Option<i32>) -> Rom {
+        Rom {
+            id,
+            title: title.to_string(),
+            game_id: 1,
+            disc,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_disc_set_name() {
+        assert_eq!(
+            "Final Fantasy VII (USA)",
+            disc_set_name("Final Fantasy VII (USA) (Disc 1).cue")
+        );
+        assert_eq!(
+            "Policenauts (Japan) (Rev 1)",
+            disc_set_name("Policenauts (Japan) (Disc 2) (Rev 1).cue")
+        );
+    }
+
+    #[test]
+    fn test_playable_entries() {
+        let roms = vec![
+            rom(1, "Final Fantasy VII (USA) (Disc 2).cue", Some(2)),
+            rom(2, "Final Fantasy VII (USA) (Disc 1).cue", None),
+            rom(3, "Final Fantasy VII (Europe) (Disc 1).cue", Some(1)),
+            rom(4, "Final Fantasy VII (Japan) (Disc 1).cue", Some(1)),
+            rom(5, "Final Fantasy VII (Japan) (Disc 2).cue", Some(2)),
+        ];
+
+        let entries = playable_entries(&roms);
+
+        assert_eq!(
+            vec![
+                PlayableEntry::DiscSet(DiscSet {
+                    name: "Final Fantasy VII (USA)".to_string(),
+                    discs: vec![&roms[1], &roms[0]],
+                }),
+                PlayableEntry::Single(&roms[2]),
+                PlayableEntry::DiscSet(DiscSet {
+                    name: "Final Fantasy VII (Japan)".to_string(),
+                    discs: vec![&roms[3], &roms[4]],
+                }),
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let set = DiscSet {
+            name: "Final Fantasy VII (USA)".to_string(),
+            discs: Vec::new(),
+        };
+
+        assert_eq!("Final Fantasy VII (USA).m3u", set.playlist_file_name());
+        assert_eq!(
+            "Final Fantasy VII (USA) (Disc 1).cue\nFinal Fantasy VII (USA) (Disc 2).cue\n",
+            render(&[
+                "Final Fantasy VII (USA) (Disc 1).cue".to_string(),
+                "Final Fantasy VII (USA) (Disc 2).cue".to_string(),
+            ])
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::AppConfig,
+    filters::{Filter, FilterRuleset},
+    models::{Console, GameWithRoms},
+    routes::{console_routes, games_routes},
+};
+use diesel::SqliteConnection;
+
+use self::target::ExportTarget;
+
+pub mod m3u;
+pub mod one_game_one_rom;
+pub mod pegasus;
+pub mod retroarch;
+pub mod target;
+
+/// Returns all consoles with a configured rom path, together with that path
+pub fn configured_consoles(
+    conn: &mut SqliteConnection,
+    config: &AppConfig,
+) -> Vec<(Console, PathBuf)> {
+    console_routes::get_consoles(conn)
+        .into_iter()
+        .filter_map(|console| {
+            let rom_path = config.rom_paths.get(&console.abbreviation)?;
+
+            (!rom_path.is_empty()).then(|| (console, PathBuf::from(rom_path)))
+        })
+        .collect()
+}
+
+/// Loads the games of the console with the roms the filter keeps, narrowed down to a single
+/// release each if the target wants that
+pub fn games_for_target(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    rom_dir: &Path,
+    target: Option<&ExportTarget>,
+    filter: Option<&FilterRuleset>,
+) -> io::Result<Vec<GameWithRoms>> {
+    let mut games = games_routes::get_games_for_console(conn, &console.id);
+    if let Some(filter) = filter {
+        games = Filter::new(filter, Some(rom_dir.to_path_buf()))?.apply(games);
+    }
+
+    Ok(
+        match target.and_then(|target| target.one_game_one_rom.as_ref()) {
+            Some(rules) => rules.apply(&games),
+            None => games,
+        },
+    )
+}
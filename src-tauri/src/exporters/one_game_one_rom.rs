@@ -0,0 +1,275 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dat_parser::name_flags::NameFlags,
+    exporters::m3u::disc_set_name,
+    models::{Game, GameWithRoms, Rom},
+};
+
+/// Picks a single release of every game, e.g. for handhelds which should show every game once
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct OneGameOneRom {
+    /// regions in order of preference, e.g. `Europe`, `USA`, `World`, `Japan`
+    pub regions: Vec<String>,
+    /// language codes in order of preference, e.g. `En`, `De`
+    pub languages: Vec<String>,
+    pub exclude_betas: bool,
+    pub exclude_protos: bool,
+    /// demos, samples and kiosk versions
+    pub exclude_demos: bool,
+    pub exclude_pirates: bool,
+    pub exclude_unlicensed: bool,
+}
+
+/// The release picked for a game and its clones
+#[derive(Debug, Clone, Serialize)]
+pub struct OneGameOneRomPick {
+    /// the game of the picked release with only its roms, all discs for multi-disc releases
+    #[serde(flatten)]
+    pub selected: GameWithRoms,
+    /// the other roms of the game and its clones
+    pub dropped: Vec<Rom>,
+}
+
+/// One release of a game, with all its discs
+struct Release<'a> {
+    game: &'a Game,
+    roms: Vec<&'a Rom>,
+    flags: NameFlags,
+}
+
+impl OneGameOneRom {
+    fn excludes(&self, flags: &NameFlags) -> bool {
+        (self.exclude_betas && flags.beta)
+            || (self.exclude_protos && flags.proto)
+            || (self.exclude_demos && flags.demo)
+            || (self.exclude_pirates && flags.pirate)
+            || (self.exclude_unlicensed && flags.unlicensed)
+    }
+
+    /// Position of the best of the values in the priority list, unlisted ones rank last
+    fn rank(priority: &[String], values: &[String]) -> usize {
+        values
+            .iter()
+            .filter_map(|value| {
+                priority
+                    .iter()
+                    .position(|preferred| preferred.eq_ignore_ascii_case(value))
+            })
+            .min()
+            .unwrap_or(priority.len())
+    }
+
+    /// Finished releases first, then by region, language, the latest revision and the title
+    fn best<'a>(&self, releases: Vec<Release<'a>>) -> Option<Release<'a>> {
+        releases
+            .into_iter()
+            .filter(|release| !self.excludes(&release.flags))
+            .min_by_key(|release| {
+                (
+                    release.flags.is_prerelease(),
+                    release.flags.pirate || release.flags.unlicensed,
+                    Self::rank(&self.regions, &release.flags.regions),
+                    Self::rank(&self.languages, &release.flags.implied_languages()),
+                    Reverse(release.flags.revision.clone()),
+                    release.roms[0].title.clone(),
+                )
+            })
+    }
+
+    /// Picks a release of every parent/clone group, groups without an allowed release are
+    /// left out. The picks keep the order of the parents.
+    pub fn select(&self, games: &[GameWithRoms]) -> Vec<OneGameOneRomPick> {
+        let indices: HashMap<&str, usize> = games
+            .iter()
+            .enumerate()
+            .map(|(index, game_roms)| (game_roms.game.title.as_str(), index))
+            .collect();
+
+        // clones join the group of their parent, as far as it is part of the games
+        let mut groups: BTreeMap<usize, Vec<&GameWithRoms>> = BTreeMap::new();
+        for (index, game_roms) in games.iter().enumerate() {
+            let mut parent = index;
+            for _ in 0..games.len() {
+                match games[parent]
+                    .game
+                    .clone_of
+                    .as_deref()
+                    .and_then(|title| indices.get(title))
+                {
+                    Some(&grandparent) if grandparent != parent => parent = grandparent,
+                    _ => break,
+                }
+            }
+            groups.entry(parent).or_default().push(game_roms);
+        }
+
+        groups
+            .into_values()
+            .filter_map(|group| {
+                let mut releases: Vec<Release> = Vec::new();
+                for game_roms in &group {
+                    let mut sets: Vec<(String, Vec<&Rom>)> = Vec::new();
+                    for rom in &game_roms.roms {
+                        let name = disc_set_name(&rom.title);
+                        match sets.iter_mut().find(|(set, _)| *set == name) {
+                            Some((_, roms)) => roms.push(rom),
+                            None => sets.push((name, vec![rom])),
+                        }
+                    }
+
+                    releases.extend(sets.into_iter().map(|(name, roms)| Release {
+                        game: &game_roms.game,
+                        roms,
+                        flags: NameFlags::parse(&name),
+                    }));
+                }
+
+                let best = self.best(releases)?;
+                let dropped = group
+                    .iter()
+                    .flat_map(|game_roms| &game_roms.roms)
+                    .filter(|rom| !best.roms.iter().any(|picked| picked.id == rom.id))
+                    .cloned()
+                    .collect();
+
+                Some(OneGameOneRomPick {
+                    selected: GameWithRoms {
+                        game: best.game.clone(),
+                        roms: best.roms.into_iter().cloned().collect(),
+                        metadata: group
+                            .iter()
+                            .find(|game_roms| game_roms.game.id == best.game.id)
+                            .map(|game_roms| game_roms.metadata.clone())
+                            .unwrap_or_default(),
+                    },
+                    dropped,
+                })
+            })
+            .collect()
+    }
+
+    /// The picked games with their picked roms, for the exporters
+    pub fn apply(&self, games: &[GameWithRoms]) -> Vec<GameWithRoms> {
+        self.select(games)
+            .into_iter()
+            .map(|pick| pick.selected)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(id: i32, title: &str, clone_of: Option<&str>, roms: &[&str]) -> GameWithRoms {
+        GameWithRoms {
+            game: Game {
+                id,
+                title: title.to_string(),
+                console_id: 1,
+                clone_of: clone_of.map(str::to_string),
+                ..Default::default()
+            },
+            roms: roms
+                .iter()
+                .enumerate()
+                .map(|(index, title)| Rom {
+                    id: id * 100 + index as i32,
+                    title: title.to_string(),
+                    game_id: id,
+                    ..Default::default()
+                })
+                .collect(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn picked_titles(picks: &[GameWithRoms]) -> Vec<&str> {
+        picks
+            .iter()
+            .flat_map(|pick| &pick.roms)
+            .map(|rom| rom.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let games = vec![
+            game(
+                1,
+                "Secret of Mana",
+                None,
+                &[
+                    "Secret of Mana (Europe).sfc",
+                    "Secret of Mana (Europe) (Rev 1).sfc",
+                    "Secret of Mana (Germany).sfc",
+                    "Secret of Mana (USA).sfc",
+                ],
+            ),
+            game(
+                2,
+                "Pocket Monsters - Aka",
+                Some("Pokemon - Red Version"),
+                &["Pocket Monsters - Aka (Japan).gb"],
+            ),
+            game(
+                3,
+                "Pokemon - Red Version",
+                None,
+                &["Pokemon - Red Version (USA, Europe) (SGB Enhanced).gb"],
+            ),
+            game(
+                4,
+                "Final Fantasy VII",
+                None,
+                &[
+                    "Final Fantasy VII (Japan) (Disc 1).cue",
+                    "Final Fantasy VII (Japan) (Disc 2).cue",
+                    "Final Fantasy VII (USA) (Disc 1).cue",
+                    "Final Fantasy VII (USA) (Disc 2).cue",
+                ],
+            ),
+            game(5, "Star Fox 2", None, &["Star Fox 2 (Japan) (Beta).sfc"]),
+        ];
+
+        let mut rules = OneGameOneRom {
+            regions: vec!["Europe".to_string(), "USA".to_string()],
+            exclude_betas: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![
+                "Secret of Mana (Europe) (Rev 1).sfc",
+                "Pokemon - Red Version (USA, Europe) (SGB Enhanced).gb",
+                "Final Fantasy VII (USA) (Disc 1).cue",
+                "Final Fantasy VII (USA) (Disc 2).cue",
+            ],
+            picked_titles(&rules.apply(&games))
+        );
+
+        let picks = rules.select(&games);
+        assert_eq!(3, picks[0].dropped.len());
+        assert_eq!(1, picks[1].dropped.len());
+
+        rules.regions = vec!["Japan".to_string()];
+        rules.languages = vec!["De".to_string()];
+        rules.exclude_betas = false;
+        assert_eq!(
+            vec![
+                "Secret of Mana (Germany).sfc",
+                "Pocket Monsters - Aka (Japan).gb",
+                "Final Fantasy VII (Japan) (Disc 1).cue",
+                "Final Fantasy VII (Japan) (Disc 2).cue",
+                "Star Fox 2 (Japan) (Beta).sfc",
+            ],
+            picked_titles(&rules.apply(&games))
+        );
+    }
+}
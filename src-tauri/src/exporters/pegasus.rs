@@ -0,0 +1,490 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    config::{AppConfig, PegasusConfig},
+    exporters::{
+        configured_consoles, games_for_target,
+        m3u::{self, PlayableEntry},
+        target::{ExportTarget, PathIssue},
+    },
+    filters::FilterRuleset,
+    models::{Console, GameWithRoms},
+};
+
+pub const METADATA_FILE_NAME: &str = "metadata.pegasus.txt";
+
+/// Pegasus asset keys and the ES-DE style media folder they are looked up in,
+/// e.g. `<rom dir>/media/covers/<rom stem>.png` becomes `assets.boxFront`
+const ASSET_DIRS: [(&str, &str); 8] = [
+    ("boxFront", "covers"),
+    ("boxBack", "backcovers"),
+    ("cartridge", "physicalmedia"),
+    ("logo", "marquees"),
+    ("background", "fanart"),
+    ("screenshot", "screenshots"),
+    ("titlescreen", "titlescreens"),
+    ("video", "videos"),
+];
+
+const ASSET_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "mp4"];
+
+#[derive(Debug, PartialEq)]
+pub struct PegasusCollection {
+    pub name: String,
+    pub shortname: String,
+    pub extensions: Vec<String>,
+    pub launch: Option<String>,
+    pub games: Vec<PegasusGame>,
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct PegasusGame {
+    pub title: String,
+    /// rom files relative to the metadata file, the discs of a disc set are written as `files`
+    pub files: Vec<String>,
+    pub developers: Vec<String>,
+    pub publishers: Vec<String>,
+    pub genres: Vec<String>,
+    pub players: Option<i32>,
+    pub release: Option<String>,
+    pub description: Option<String>,
+    /// (asset key, path relative to the metadata file)
+    pub assets: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PegasusExportSummary {
+    pub console: String,
+    pub path: PathBuf,
+    pub games: usize,
+    pub path_issues: Vec<PathIssue>,
+}
+
+impl PegasusCollection {
+    /// Builds the collection for a console with one game per release or disc set, only keeping
+    /// the ones whose files all exist inside `rom_dir`.
+    /// With a target, files and assets are written as absolute paths rewritten for that device,
+    /// under the sanitised names a sync gives them.
+    pub fn build(
+        console: &Console,
+        games: &[GameWithRoms],
+        rom_dir: &Path,
+        config: &PegasusConfig,
+        target: Option<&ExportTarget>,
+    ) -> Self {
+        let mut extensions = BTreeSet::new();
+        let mut entries = Vec::new();
+
+        for game_roms in games {
+            // (name, files) of every release and disc set with all files in place
+            let releases: Vec<(String, Vec<String>)> = m3u::playable_entries(&game_roms.roms)
+                .into_iter()
+                .map(|entry| match entry {
+                    PlayableEntry::Single(rom) => (file_stem(&rom.title), vec![rom.title.clone()]),
+                    PlayableEntry::DiscSet(set) => {
+                        let files = set.disc_files();
+                        (set.name, files)
+                    }
+                })
+                .filter(|(_, files)| files.iter().all(|file| rom_dir.join(file).is_file()))
+                .collect();
+            // several releases are told apart by their names with the region and other flags
+            let several = releases.len() > 1;
+
+            for (name, files) in releases {
+                for file in &files {
+                    if let Some(extension) = Path::new(file).extension() {
+                        extensions.insert(extension.to_string_lossy().to_lowercase());
+                    }
+                }
+
+                let mut assets = find_assets(rom_dir, &files, &game_roms.game.title);
+
+                let files = match target {
+                    Some(target) => {
+                        for (_, path) in assets.iter_mut() {
+                            *path = target.file_path(rom_dir, path);
+                        }
+                        files
+                            .iter()
+                            .map(|file| target.file_path(rom_dir, file))
+                            .collect()
+                    }
+                    None => files,
+                };
+
+                let metadata = &game_roms.metadata;
+
+                entries.push(PegasusGame {
+                    title: if several {
+                        name
+                    } else {
+                        game_roms.game.title.clone()
+                    },
+                    files,
+                    developers: metadata.developers.clone(),
+                    publishers: metadata.publishers.clone(),
+                    genres: metadata.genres.clone(),
+                    players: metadata.players,
+                    release: metadata.first_release().map(str::to_string),
+                    description: metadata.description.clone(),
+                    assets,
+                });
+            }
+        }
+
+        PegasusCollection {
+            name: console.name.clone(),
+            shortname: console.abbreviation.clone(),
+            extensions: extensions.into_iter().collect(),
+            launch: config.launch_template(&console.abbreviation),
+            games: entries,
+        }
+    }
+
+    /// Renders the collection in the metadata.pegasus.txt format
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        write_entry(&mut output, "collection", &self.name);
+        write_entry(&mut output, "shortname", &self.shortname);
+        if !self.extensions.is_empty() {
+            write_entry(&mut output, "extensions", &self.extensions.join(", "));
+        }
+        if let Some(launch) = &self.launch {
+            write_entry(&mut output, "launch", launch);
+        }
+
+        for game in &self.games {
+            output.push('\n');
+            write_entry(&mut output, "game", &game.title);
+
+            match game.files.as_slice() {
+                [file] => write_entry(&mut output, "file", file),
+                files => write_list(&mut output, "files", files),
+            }
+
+            if !game.developers.is_empty() {
+                write_entry(&mut output, "developer", &game.developers.join(", "));
+            }
+            if !game.publishers.is_empty() {
+                write_entry(&mut output, "publisher", &game.publishers.join(", "));
+            }
+            if !game.genres.is_empty() {
+                write_entry(&mut output, "genre", &game.genres.join(", "));
+            }
+            if let Some(players) = game.players {
+                write_entry(&mut output, "players", &players.to_string());
+            }
+            if let Some(release) = &game.release {
+                write_entry(&mut output, "release", release);
+            }
+            if let Some(description) = &game.description {
+                write_entry(&mut output, "description", description);
+            }
+            for (asset, path) in &game.assets {
+                write_entry(&mut output, &format!("assets.{}", asset), path);
+            }
+        }
+
+        output
+    }
+}
+
+/// Writes a single `key: value` pair, continuing multi-line values on indented lines
+/// and marking empty lines with a single dot, as Pegasus expects
+fn write_entry(output: &mut String, key: &str, value: &str) {
+    let mut lines = value.trim().lines();
+
+    let _ = writeln!(
+        output,
+        "{}: {}",
+        key,
+        lines.next().unwrap_or_default().trim()
+    );
+
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            output.push_str("  .\n");
+        } else {
+            let _ = writeln!(output, "  {}", line);
+        }
+    }
+}
+
+fn write_list(output: &mut String, key: &str, values: &[String]) {
+    let _ = writeln!(output, "{}:", key);
+
+    for value in values {
+        let _ = writeln!(output, "  {}", value);
+    }
+}
+
+fn file_stem(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.to_string())
+}
+
+fn find_assets(rom_dir: &Path, files: &[String], title: &str) -> Vec<(String, String)> {
+    let mut stems: Vec<&str> = files
+        .iter()
+        .filter_map(|file| Path::new(file).file_stem()?.to_str())
+        .collect();
+    stems.push(title);
+
+    ASSET_DIRS
+        .iter()
+        .filter_map(|(asset, dir)| {
+            stems.iter().find_map(|stem| {
+                ASSET_EXTENSIONS.iter().find_map(|extension| {
+                    let relative = format!("media/{}/{}.{}", dir, stem, extension);
+                    rom_dir
+                        .join(&relative)
+                        .is_file()
+                        .then(|| (asset.to_string(), relative))
+                })
+            })
+        })
+        .collect()
+}
+
+/// Writes `metadata.pegasus.txt` into the console's rom directory
+pub fn export_console(
+    console: &Console,
+    games: &[GameWithRoms],
+    rom_dir: &Path,
+    config: &PegasusConfig,
+    target: Option<&ExportTarget>,
+) -> io::Result<PegasusExportSummary> {
+    let collection = PegasusCollection::build(console, games, rom_dir, config, target);
+    let path = rom_dir.join(METADATA_FILE_NAME);
+
+    fs::write(&path, collection.render())?;
+
+    let path_issues = match target {
+        Some(target) => collection
+            .games
+            .iter()
+            .flat_map(|game| {
+                game.files
+                    .iter()
+                    .chain(game.assets.iter().map(|(_, path)| path))
+            })
+            .flat_map(|path| target.check_path(path))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(PegasusExportSummary {
+        console: console.abbreviation.clone(),
+        path,
+        games: collection.games.len(),
+        path_issues,
+    })
+}
+
+/// Writes `metadata.pegasus.txt` for every console with a configured rom path
+pub fn export_all(
+    conn: &mut SqliteConnection,
+    config: &AppConfig,
+    target: Option<&ExportTarget>,
+    filter: Option<&FilterRuleset>,
+) -> io::Result<Vec<PegasusExportSummary>> {
+    configured_consoles(conn, config)
+        .into_iter()
+        .filter(|(_, rom_dir)| rom_dir.is_dir())
+        .map(|(console, rom_dir)| {
+            let games = games_for_target(conn, &console, &rom_dir, target, filter)?;
+            export_console(&console, &games, &rom_dir, &config.pegasus, target)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Game, Rom};
+
+    use super::*;
+
+    fn collection() -> PegasusCollection {
+        PegasusCollection {
+            name: "Super Nintendo Entertainment System".to_string(),
+            shortname: "snes".to_string(),
+            extensions: vec!["sfc".to_string(), "smc".to_string()],
+            launch: Some("retroarch -L snes9x_libretro.so \"{file.path}\"".to_string()),
+            games: vec![
+                PegasusGame {
+                    title: "ActRaiser".to_string(),
+                    files: vec!["ActRaiser (Europe).sfc".to_string()],
+                    developers: vec!["Quintet".to_string()],
+                    publishers: vec!["Enix".to_string()],
+                    genres: vec!["Action".to_string(), "Simulation".to_string()],
+                    players: Some(1),
+                    release: Some("1990-12-16".to_string()),
+                    description: Some("First line\n\nSecond paragraph".to_string()),
+                    assets: vec![(
+                        "boxFront".to_string(),
+                        "media/covers/ActRaiser (Europe).png".to_string(),
+                    )],
+                },
+                PegasusGame {
+                    title: "Final Fantasy VII".to_string(),
+                    files: vec![
+                        "Final Fantasy VII (USA) (Disc 1).cue".to_string(),
+                        "Final Fantasy VII (USA) (Disc 2).cue".to_string(),
+                    ],
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let correct = r#"collection: Super Nintendo Entertainment System
+shortname: snes
+extensions: sfc, smc
+launch: retroarch -L snes9x_libretro.so "{file.path}"
+
+game: ActRaiser
+file: ActRaiser (Europe).sfc
+developer: Quintet
+publisher: Enix
+genre: Action, Simulation
+players: 1
+release: 1990-12-16
+description: First line
+  .
+  Second paragraph
+assets.boxFront: media/covers/ActRaiser (Europe).png
+
+game: Final Fantasy VII
+files:
+  Final Fantasy VII (USA) (Disc 1).cue
+  Final Fantasy VII (USA) (Disc 2).cue
+"#;
+
+        assert_eq!(correct, collection().render())
+    }
+
+    #[test]
+    fn test_render_without_launch() {
+        let mut collection = collection();
+        collection.launch = None;
+        collection.games.clear();
+
+        assert_eq!(
+            "collection: Super Nintendo Entertainment System\nshortname: snes\nextensions: sfc, smc\n",
+            collection.render()
+        )
+    }
+
+    fn game(id: i32, title: &str, roms: &[&str]) -> GameWithRoms {
+        GameWithRoms {
+            game: Game {
+                id,
+                title: title.to_string(),
+                console_id: 1,
+                ..Default::default()
+            },
+            roms: roms
+                .iter()
+                .enumerate()
+                .map(|(index, title)| Rom {
+                    id: id * 10 + index as i32,
+                    title: title.to_string(),
+                    game_id: id,
+                    ..Default::default()
+                })
+                .collect(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_build_entry_per_release() {
+        let rom_dir = std::env::temp_dir().join("romana_pegasus_build");
+        let _ = fs::remove_dir_all(&rom_dir);
+        fs::create_dir_all(&rom_dir).unwrap();
+        for file in [
+            "Secret of Mana (Europe).sfc",
+            "Secret of Mana (USA).sfc",
+            "Final Fantasy VII (USA) (Disc 1).cue",
+            "Final Fantasy VII (USA) (Disc 2).cue",
+            "Star Fox (USA).sfc",
+        ] {
+            fs::write(rom_dir.join(file), b"").unwrap();
+        }
+
+        let console = Console {
+            id: 1,
+            name: "PlayStation".to_string(),
+            abbreviation: "psx".to_string(),
+            manufacturer: "Sony".to_string(),
+        };
+        let games = [
+            game(
+                1,
+                "Secret of Mana",
+                &["Secret of Mana (Europe).sfc", "Secret of Mana (USA).sfc"],
+            ),
+            game(
+                2,
+                "Final Fantasy VII",
+                &[
+                    "Final Fantasy VII (USA) (Disc 1).cue",
+                    "Final Fantasy VII (USA) (Disc 2).cue",
+                ],
+            ),
+            game(
+                3,
+                "Star Fox",
+                &["Star Fox (USA).sfc", "Star Fox (Europe).sfc"],
+            ),
+        ];
+        let collection =
+            PegasusCollection::build(&console, &games, &rom_dir, &PegasusConfig::default(), None);
+
+        let entries: Vec<(&str, Vec<&str>)> = collection
+            .games
+            .iter()
+            .map(|game| {
+                (
+                    game.title.as_str(),
+                    game.files.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "Secret of Mana (Europe)",
+                    vec!["Secret of Mana (Europe).sfc"]
+                ),
+                ("Secret of Mana (USA)", vec!["Secret of Mana (USA).sfc"]),
+                (
+                    "Final Fantasy VII",
+                    vec![
+                        "Final Fantasy VII (USA) (Disc 1).cue",
+                        "Final Fantasy VII (USA) (Disc 2).cue"
+                    ]
+                ),
+                ("Star Fox", vec!["Star Fox (USA).sfc"]),
+            ],
+            entries
+        );
+
+        fs::remove_dir_all(&rom_dir).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    config::AppConfig,
+    exporters::{
+        configured_consoles, games_for_target,
+        m3u::{self, PlayableEntry},
+        target::{ExportTarget, PathIssue},
+    },
+    filters::FilterRuleset,
+    models::{Console, GameWithRoms},
+};
+
+/// Lets RetroArch pick the core and database entry on its own
+const DETECT: &str = "DETECT";
+
+/// JSON playlist format used by RetroArch since 1.7.6
+#[derive(Debug, Serialize)]
+struct Playlist {
+    version: &'static str,
+    default_core_path: String,
+    default_core_name: String,
+    label_display_mode: u8,
+    right_thumbnail_mode: u8,
+    left_thumbnail_mode: u8,
+    sort_mode: u8,
+    items: Vec<PlaylistItem>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct PlaylistItem {
+    path: String,
+    label: String,
+    core_path: String,
+    core_name: String,
+    crc32: String,
+    db_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetroArchExportSummary {
+    pub console: String,
+    pub path: PathBuf,
+    pub entries: usize,
+    /// m3u playlists written for multi-disc games
+    pub disc_playlists: usize,
+    pub path_issues: Vec<PathIssue>,
+}
+
+/// libretro database names keyed by console abbreviation. The seeded consoles
+/// carry no manufacturer, so the names can't be derived from the console row.
+const LIBRETRO_SYSTEMS: &[(&str, &str)] = &[
+    ("3do", "The 3DO Company - 3DO"),
+    ("amiga", "Commodore - Amiga"),
+    ("amigacd32", "Commodore - CD32"),
+    ("amstradcpc", "Amstrad - CPC"),
+    ("atari2600", "Atari - 2600"),
+    ("atari5200", "Atari - 5200"),
+    ("atari7800", "Atari - 7800"),
+    ("atarijaguar", "Atari - Jaguar"),
+    ("atarilynx", "Atari - Lynx"),
+    ("atarist", "Atari - ST"),
+    ("c64", "Commodore - 64"),
+    ("colecovision", "Coleco - ColecoVision"),
+    ("dreamcast", "Sega - Dreamcast"),
+    ("fds", "Nintendo - Family Computer Disk System"),
+    ("gamegear", "Sega - Game Gear"),
+    ("gb", "Nintendo - Game Boy"),
+    ("gba", "Nintendo - Game Boy Advance"),
+    ("gbc", "Nintendo - Game Boy Color"),
+    ("gc", "Nintendo - GameCube"),
+    ("genesis", "Sega - Mega Drive - Genesis"),
+    ("intellivision", "Mattel - Intellivision"),
+    ("mastersystem", "Sega - Master System - Mark III"),
+    ("megacd", "Sega - Mega-CD - Sega CD"),
+    ("megadrive", "Sega - Mega Drive - Genesis"),
+    ("msx", "Microsoft - MSX"),
+    ("msx2", "Microsoft - MSX2"),
+    ("n3ds", "Nintendo - Nintendo 3DS"),
+    ("n64", "Nintendo - Nintendo 64"),
+    ("nds", "Nintendo - Nintendo DS"),
+    ("neogeo", "SNK - Neo Geo"),
+    ("neogeocd", "SNK - Neo Geo CD"),
+    ("nes", "Nintendo - Nintendo Entertainment System"),
+    ("ngp", "SNK - Neo Geo Pocket"),
+    ("ngpc", "SNK - Neo Geo Pocket Color"),
+    ("odyssey2", "Magnavox - Odyssey2"),
+    ("pcengine", "NEC - PC Engine - TurboGrafx 16"),
+    ("pcenginecd", "NEC - PC Engine CD - TurboGrafx-CD"),
+    ("pcfx", "NEC - PC-FX"),
+    ("pokemini", "Nintendo - Pokemon Mini"),
+    ("ps2", "Sony - PlayStation 2"),
+    ("psp", "Sony - PlayStation Portable"),
+    ("psx", "Sony - PlayStation"),
+    ("saturn", "Sega - Saturn"),
+    ("sega32x", "Sega - 32X"),
+    ("segacd", "Sega - Mega-CD - Sega CD"),
+    ("sfc", "Nintendo - Super Nintendo Entertainment System"),
+    ("sg-1000", "Sega - SG-1000"),
+    ("snes", "Nintendo - Super Nintendo Entertainment System"),
+    ("supergrafx", "NEC - PC Engine SuperGrafx"),
+    ("tg16", "NEC - PC Engine - TurboGrafx 16"),
+    ("vectrex", "GCE - Vectrex"),
+    ("virtualboy", "Nintendo - Virtual Boy"),
+    ("wii", "Nintendo - Wii"),
+    ("wonderswan", "Bandai - WonderSwan"),
+    ("wonderswancolor", "Bandai - WonderSwan Color"),
+    ("x68000", "Sharp - X68000"),
+    ("zxspectrum", "Sinclair - ZX Spectrum +3"),
+];
+
+/// Playlist name in the `Manufacturer - System` form of the libretro databases,
+/// which RetroArch uses to look up thumbnails. Consoles libretro doesn't know
+/// fall back to their manufacturer and name.
+pub fn playlist_name(console: &Console) -> String {
+    if let Some((_, system)) = LIBRETRO_SYSTEMS
+        .iter()
+        .find(|(abbreviation, _)| *abbreviation == console.abbreviation)
+    {
+        system.to_string()
+    } else if console.manufacturer.is_empty() {
+        console.name.clone()
+    } else {
+        format!("{} - {}", console.manufacturer, console.name)
+    }
+}
+
+fn label(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.to_string())
+}
+
+/// Writes the console's `.lpl` playlist. Multi-disc games get an m3u next to their discs
+/// and the playlist entry points at it. With a target, the entries use the sanitised names a
+/// sync gives the files, while the m3u next to the local discs keeps their real names.
+pub fn export_console(
+    console: &Console,
+    games: &[GameWithRoms],
+    rom_dir: &Path,
+    playlist_dir: &Path,
+    target: Option<&ExportTarget>,
+) -> io::Result<RetroArchExportSummary> {
+    let db_name = format!("{}.lpl", playlist_name(console));
+    let mut items = Vec::new();
+    let mut disc_playlists = 0;
+
+    let mut path_issues = Vec::new();
+
+    let target_path = |file: &str| match target {
+        Some(target) => target.file_path(rom_dir, file),
+        None => rom_dir.join(file).to_string_lossy().to_string(),
+    };
+
+    for game_roms in games {
+        for entry in m3u::playable_entries(&game_roms.roms) {
+            let (file, label) =
+                match entry {
+                    PlayableEntry::Single(rom) => (rom.title.clone(), label(&rom.title)),
+                    PlayableEntry::DiscSet(set) => {
+                        let discs = set.disc_files();
+                        if !discs.iter().all(|disc| rom_dir.join(disc).is_file()) {
+                            continue;
+                        }
+
+                        // the local m3u keeps the real names, a sync writes the target's own
+                        if let Some(target) = target {
+                            path_issues.extend(discs.iter().flat_map(|disc| {
+                                target.check_path(&target.sanitize_file_name(disc))
+                            }));
+                        }
+                        let playlist = set.playlist_file_name();
+                        fs::write(rom_dir.join(&playlist), m3u::render(&discs))?;
+                        disc_playlists += 1;
+
+                        (playlist, set.name)
+                    }
+                };
+
+            if !rom_dir.join(&file).is_file() {
+                continue;
+            }
+
+            items.push(PlaylistItem {
+                path: target_path(&file),
+                label,
+                core_path: DETECT.to_string(),
+                core_name: DETECT.to_string(),
+                crc32: DETECT.to_string(),
+                db_name: db_name.clone(),
+            });
+        }
+    }
+
+    if let Some(target) = target {
+        path_issues.extend(items.iter().flat_map(|item| target.check_path(&item.path)));
+    }
+
+    let entries = items.len();
+    let playlist = Playlist {
+        version: "1.5",
+        default_core_path: String::new(),
+        default_core_name: String::new(),
+        label_display_mode: 0,
+        right_thumbnail_mode: 0,
+        left_thumbnail_mode: 0,
+        sort_mode: 0,
+        items,
+    };
+
+    fs::create_dir_all(playlist_dir)?;
+    let path = playlist_dir.join(&db_name);
+    fs::write(&path, serde_json::to_string_pretty(&playlist)?)?;
+
+    Ok(RetroArchExportSummary {
+        console: console.abbreviation.clone(),
+        path,
+        entries,
+        disc_playlists,
+        path_issues,
+    })
+}
+
+/// Writes a playlist for every console with a configured rom path
+pub fn export_all(
+    conn: &mut SqliteConnection,
+    config: &AppConfig,
+    target: Option<&ExportTarget>,
+    filter: Option<&FilterRuleset>,
+) -> io::Result<Vec<RetroArchExportSummary>> {
+    if config.retroarch.playlist_dir.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "RetroArch playlist directory is not configured",
+        ));
+    }
+    let playlist_dir = PathBuf::from(&config.retroarch.playlist_dir);
+
+    configured_consoles(conn, config)
+        .into_iter()
+        .filter(|(_, rom_dir)| rom_dir.is_dir())
+        .map(|(console, rom_dir)| {
+            let games = games_for_target(conn, &console, &rom_dir, target, filter)?;
+            export_console(&console, &games, &rom_dir, &playlist_dir, target)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        exporters::target::TargetFilesystem,
+        models::{Game, Rom},
+        routes::console_routes,
+    };
+
+    use super::*;
+
+    fn disc_game(titles: &[&str]) -> GameWithRoms {
+        GameWithRoms {
+            game: Game {
+                id: 1,
+                title: "Final Fantasy VII".to_string(),
+                console_id: 1,
+                ..Default::default()
+            },
+            roms: titles
+                .iter()
+                .enumerate()
+                .map(|(index, title)| Rom {
+                    id: index as i32,
+                    title: title.to_string(),
+                    game_id: 1,
+                    disc: Some(index as i32 + 1),
+                    ..Default::default()
+                })
+                .collect(),
+            metadata: Default::default(),
+        }
+    }
+
+    /// Console as seeded by the migrations
+    fn seeded_console(abbreviation: &str) -> Console {
+        console_routes::get_consoles(&mut crate::db::test_connection())
+            .into_iter()
+            .find(|console| console.abbreviation == abbreviation)
+            .unwrap()
+    }
+
+    fn console() -> Console {
+        seeded_console("psx")
+    }
+
+    #[test]
+    fn test_playlist_name_of_seeded_consoles() {
+        assert_eq!(
+            "Nintendo - Super Nintendo Entertainment System",
+            playlist_name(&seeded_console("snes"))
+        );
+        assert_eq!("Sony - PlayStation", playlist_name(&seeded_console("psx")));
+        let mut unknown = seeded_console("psx");
+        unknown.abbreviation = "unknown".to_string();
+        assert_eq!("PlayStation", playlist_name(&unknown));
+    }
+
+    #[test]
+    fn test_multi_disc_entry_points_at_m3u() {
+        let dir = std::env::temp_dir().join("romana_retroarch_export");
+        let _ = fs::remove_dir_all(&dir);
+        let rom_dir = dir.join("psx");
+        fs::create_dir_all(&rom_dir).unwrap();
+
+        let titles = [
+            "Final Fantasy VII (USA) (Disc 1).cue",
+            "Final Fantasy VII (USA) (Disc 2).cue",
+        ];
+        for title in titles {
+            fs::write(rom_dir.join(title), b"").unwrap();
+        }
+
+        let console = console();
+        let games = vec![disc_game(&titles)];
+
+        let summary =
+            export_console(&console, &games, &rom_dir, &dir.join("playlists"), None).unwrap();
+
+        assert_eq!((1, 1), (summary.entries, summary.disc_playlists));
+        assert_eq!(
+            m3u::render(&titles.map(String::from)),
+            fs::read_to_string(rom_dir.join("Final Fantasy VII (USA).m3u")).unwrap()
+        );
+
+        let playlist: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&summary.path).unwrap()).unwrap();
+        assert_eq!(
+            rom_dir
+                .join("Final Fantasy VII (USA).m3u")
+                .to_string_lossy(),
+            playlist["items"][0]["path"].as_str().unwrap()
+        );
+        assert_eq!("Sony - PlayStation.lpl", playlist["items"][0]["db_name"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_target_names_are_sanitised() {
+        let dir = std::env::temp_dir().join("romana_retroarch_target");
+        let _ = fs::remove_dir_all(&dir);
+        let rom_dir = dir.join("psx");
+        fs::create_dir_all(&rom_dir).unwrap();
+
+        let titles = [
+            "Policenauts: Pilot Disk (Japan) (Disc 1).cue",
+            "Policenauts: Pilot Disk (Japan) (Disc 2).cue",
+        ];
+        for title in titles {
+            fs::write(rom_dir.join(title), b"").unwrap();
+        }
+        let target = ExportTarget {
+            filesystem: TargetFilesystem::Fat32,
+            ..Default::default()
+        };
+
+        let summary = export_console(
+            &console(),
+            &[disc_game(&titles)],
+            &rom_dir,
+            &dir.join("playlists"),
+            Some(&target),
+        )
+        .unwrap();
+
+        assert!(summary.path_issues.is_empty());
+        assert_eq!(
+            m3u::render(&titles.map(String::from)),
+            fs::read_to_string(rom_dir.join("Policenauts: Pilot Disk (Japan).m3u")).unwrap()
+        );
+        let playlist: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&summary.path).unwrap()).unwrap();
+        assert_eq!(
+            rom_dir
+                .join("Policenauts_ Pilot Disk (Japan).m3u")
+                .to_string_lossy(),
+            playlist["items"][0]["path"].as_str().unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,338 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::one_game_one_rom::OneGameOneRom;
+
+/// Characters which are not allowed in FAT32 and exFAT file names
+const FAT_RESERVED_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Describes the device an export is written for, e.g. a handheld mounting the roms
+/// under `/storage/roms` or a Switch SD card reached via `sdmc:/roms`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExportTarget {
+    /// prefix rewrites applied in order, the first matching rule wins
+    #[serde(default)]
+    pub rewrites: Vec<PathRewrite>,
+    #[serde(default)]
+    pub separator: PathSeparator,
+    #[serde(default)]
+    pub filesystem: TargetFilesystem,
+    /// overrides the maximum full path length of the filesystem
+    pub max_path_length: Option<usize>,
+    /// exports a single release of every game
+    #[serde(default)]
+    pub one_game_one_rom: Option<OneGameOneRom>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PathRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PathSeparator {
+    /// keep separators as they are
+    #[default]
+    Keep,
+    Forward,
+    Backslash,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetFilesystem {
+    /// no restrictions besides the ones of the host system
+    #[default]
+    Native,
+    Fat32,
+    ExFat,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PathIssue {
+    InvalidCharacters {
+        path: String,
+    },
+    NameTooLong {
+        path: String,
+        length: usize,
+        max: usize,
+    },
+    PathTooLong {
+        path: String,
+        length: usize,
+        max: usize,
+    },
+}
+
+impl TargetFilesystem {
+    /// Maximum length of a single file or directory name in UTF-16 code units
+    pub fn max_name_length(&self) -> Option<usize> {
+        match self {
+            TargetFilesystem::Native => None,
+            TargetFilesystem::Fat32 | TargetFilesystem::ExFat => Some(255),
+        }
+    }
+
+    /// Maximum length of a full path in UTF-16 code units
+    pub fn max_path_length(&self) -> Option<usize> {
+        match self {
+            TargetFilesystem::Native => None,
+            // long file name entries are limited to 260 characters including the drive
+            TargetFilesystem::Fat32 => Some(260),
+            TargetFilesystem::ExFat => Some(32760),
+        }
+    }
+
+    pub fn is_fat(&self) -> bool {
+        matches!(self, TargetFilesystem::Fat32 | TargetFilesystem::ExFat)
+    }
+}
+
+impl ExportTarget {
+    /// Rewrites a host path for the target, applying the first matching prefix rule
+    /// and normalising the separators afterwards
+    pub fn rewrite(&self, path: &Path) -> String {
+        let path = path.to_string_lossy();
+
+        let rewritten = self
+            .rewrites
+            .iter()
+            .find_map(|rule| {
+                let rest = path.strip_prefix(rule.from.as_str())?;
+                // only match whole path components, so `/mnt/roms` doesn't match `/mnt/roms2`
+                let at_boundary = rule.from.ends_with(['/', '\\'])
+                    || rest.is_empty()
+                    || rest.starts_with(['/', '\\']);
+
+                at_boundary.then(|| format!("{}{}", rule.to, rest))
+            })
+            .unwrap_or_else(|| path.to_string());
+
+        self.normalise_separators(&rewritten)
+    }
+
+    pub fn normalise_separators(&self, path: &str) -> String {
+        match self.separator {
+            PathSeparator::Keep => path.to_string(),
+            PathSeparator::Forward => path.replace('\\', "/"),
+            PathSeparator::Backslash => path.replace('/', "\\"),
+        }
+    }
+
+    /// Makes a single file name safe for the target filesystem
+    pub fn sanitize_file_name(&self, name: &str) -> String {
+        if !self.filesystem.is_fat() {
+            return name.to_string();
+        }
+
+        sanitize_fat_file_name(name)
+    }
+
+    /// Makes every component of a `/` separated relative path safe for the target filesystem
+    pub fn sanitize_relative_path(&self, path: &str) -> String {
+        path.split('/')
+            .map(|component| self.sanitize_file_name(component))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The target path of a file below `dir`, under the sanitised name a sync gives it
+    pub fn file_path(&self, dir: &Path, relative: &str) -> String {
+        self.rewrite(&dir.join(self.sanitize_relative_path(relative)))
+    }
+
+    /// Checks a rewritten target path against the filesystem limits
+    pub fn check_path(&self, path: &str) -> Vec<PathIssue> {
+        let mut issues = Vec::new();
+        let components: Vec<&str> = path
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty())
+            .collect();
+
+        if self.filesystem.is_fat()
+            && components
+                .iter()
+                // skip drive or device prefixes like `C:` or `sdmc:`
+                .skip_while(|component| component.ends_with(':'))
+                .any(|component| sanitize_fat_file_name(component) != *component)
+        {
+            issues.push(PathIssue::InvalidCharacters {
+                path: path.to_string(),
+            });
+        }
+
+        if let Some(max) = self.filesystem.max_name_length()
+            && let Some(length) = components
+                .iter()
+                .map(|component| component.encode_utf16().count())
+                .max()
+            && length > max
+        {
+            issues.push(PathIssue::NameTooLong {
+                path: path.to_string(),
+                length,
+                max,
+            });
+        }
+
+        if let Some(max) = self.max_path_length.or(self.filesystem.max_path_length()) {
+            let length = path.encode_utf16().count();
+            if length > max {
+                issues.push(PathIssue::PathTooLong {
+                    path: path.to_string(),
+                    length,
+                    max,
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+/// Replaces characters FAT32/exFAT don't accept and strips trailing dots and spaces,
+/// which Windows and most FAT drivers silently drop
+pub fn sanitize_fat_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || FAT_RESERVED_CHARACTERS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    let sanitized = sanitized.trim_end_matches(['.', ' ']);
+
+    if sanitized.is_empty() {
+        "_".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn switch_target() -> ExportTarget {
+        ExportTarget {
+            rewrites: vec![
+                PathRewrite {
+                    from: "/mnt/roms".to_string(),
+                    to: "sdmc:/roms".to_string(),
+                },
+                PathRewrite {
+                    from: "/mnt".to_string(),
+                    to: "sdmc:".to_string(),
+                },
+            ],
+            separator: PathSeparator::Forward,
+            filesystem: TargetFilesystem::ExFat,
+            max_path_length: None,
+            one_game_one_rom: None,
+        }
+    }
+
+    #[test]
+    fn test_rewrite_prefix() {
+        let target = switch_target();
+
+        assert_eq!(
+            "sdmc:/roms/snes/ActRaiser (Europe).sfc",
+            target.rewrite(&PathBuf::from("/mnt/roms/snes/ActRaiser (Europe).sfc"))
+        );
+        assert_eq!(
+            "sdmc:/roms2/snes/ActRaiser (Europe).sfc",
+            target.rewrite(&PathBuf::from("/mnt/roms2/snes/ActRaiser (Europe).sfc"))
+        );
+        assert_eq!(
+            "/home/roms/snes.sfc",
+            target.rewrite(&PathBuf::from("/home/roms/snes.sfc"))
+        );
+    }
+
+    #[test]
+    fn test_rewrite_separators() {
+        let target = ExportTarget {
+            rewrites: vec![PathRewrite {
+                from: "/mnt/roms/".to_string(),
+                to: "E:\\roms\\".to_string(),
+            }],
+            separator: PathSeparator::Backslash,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "E:\\roms\\snes\\ActRaiser (Europe).sfc",
+            target.rewrite(&PathBuf::from("/mnt/roms/snes/ActRaiser (Europe).sfc"))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        assert_eq!(
+            "Pitfall_ The Mayan Adventure (USA).sfc",
+            sanitize_fat_file_name("Pitfall: The Mayan Adventure (USA).sfc")
+        );
+        assert_eq!("What_ _Yes_", sanitize_fat_file_name("What? \"Yes\". "));
+        assert_eq!("_", sanitize_fat_file_name("..."));
+    }
+
+    #[test]
+    fn test_file_path() {
+        let target = switch_target();
+
+        assert_eq!(
+            "sdmc:/roms/snes/media/covers/Pitfall_ The Mayan Adventure (USA).png",
+            target.file_path(
+                &PathBuf::from("/mnt/roms/snes"),
+                "media/covers/Pitfall: The Mayan Adventure (USA).png"
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_path() {
+        let target = switch_target();
+
+        assert!(target
+            .check_path("sdmc:/roms/snes/ActRaiser.sfc")
+            .is_empty());
+        assert_eq!(
+            vec![PathIssue::InvalidCharacters {
+                path: "sdmc:/roms/snes/Pitfall: The Mayan Adventure.sfc".to_string()
+            }],
+            target.check_path("sdmc:/roms/snes/Pitfall: The Mayan Adventure.sfc")
+        );
+
+        let long_name = format!("sdmc:/roms/{}.sfc", "a".repeat(260));
+        assert!(matches!(
+            target.check_path(&long_name).as_slice(),
+            [PathIssue::NameTooLong {
+                length: 264,
+                max: 255,
+                ..
+            }]
+        ));
+
+        let fat32 = ExportTarget {
+            filesystem: TargetFilesystem::Fat32,
+            ..Default::default()
+        };
+        let long_path = format!("/{}/{}.sfc", "a".repeat(200), "b".repeat(100));
+        assert!(matches!(
+            fat32.check_path(&long_path).as_slice(),
+            [PathIssue::PathTooLong { max: 260, .. }]
+        ));
+    }
+}
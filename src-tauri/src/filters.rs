@@ -0,0 +1,321 @@
+// Saved rulesets narrowing down the roms of a console, used by the game lists, the exporters
+// and sync. The rules are evaluated per rom, games without any rom left are dropped.
+
+use diesel::SqliteConnection;
+use std::{collections::HashMap, io, path::PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AppConfig,
+    dat_parser::name_flags::NameFlags,
+    models::{Console, GameWithRoms, Rom},
+    routes::games_routes,
+};
+
+/// Flags parsed from the parentheses of a rom title
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameFlag {
+    Beta,
+    Proto,
+    /// demos, samples and kiosk versions
+    Demo,
+    Pirate,
+    Unlicensed,
+    /// a revision or version after the first release
+    Revision,
+    Disc,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FilterCondition {
+    /// every condition matches, true without conditions
+    All {
+        conditions: Vec<FilterCondition>,
+    },
+    /// at least one condition matches, false without conditions
+    Any {
+        conditions: Vec<FilterCondition>,
+    },
+    Not {
+        condition: Box<FilterCondition>,
+    },
+    /// the DAT's category, e.g. `Games`, `Applications`, `Demos`, `BIOS` or `Add-Ons`
+    Category {
+        categories: Vec<String>,
+    },
+    Flag {
+        flag: NameFlag,
+    },
+    /// one of the regions in the rom title
+    Region {
+        regions: Vec<String>,
+    },
+    /// regular expression searched in the rom title
+    Title {
+        pattern: String,
+    },
+    /// size in bytes, both bounds inclusive. Roms imported before sizes were stored have 0.
+    Size {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    /// the rom file is in the console's rom folder
+    Owned,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FilterRuleset {
+    /// keeps only the roms matching it, all roms if not set
+    pub include: Option<FilterCondition>,
+    /// removes the roms matching it from the included ones
+    pub exclude: Option<FilterCondition>,
+}
+
+/// A ruleset ready to be evaluated against the roms of a console
+pub struct Filter<'a> {
+    ruleset: &'a FilterRuleset,
+    patterns: HashMap<&'a str, Regex>,
+    rom_dir: Option<PathBuf>,
+}
+
+impl NameFlag {
+    fn is_set(&self, flags: &NameFlags) -> bool {
+        match self {
+            NameFlag::Beta => flags.beta,
+            NameFlag::Proto => flags.proto,
+            NameFlag::Demo => flags.demo,
+            NameFlag::Pirate => flags.pirate,
+            NameFlag::Unlicensed => flags.unlicensed,
+            NameFlag::Revision => flags.revision.iter().any(|number| *number > 0),
+            NameFlag::Disc => flags.disc.is_some(),
+        }
+    }
+}
+
+impl FilterCondition {
+    fn patterns<'a>(&'a self, patterns: &mut Vec<&'a str>) {
+        match self {
+            FilterCondition::All { conditions } | FilterCondition::Any { conditions } => {
+                conditions
+                    .iter()
+                    .for_each(|condition| condition.patterns(patterns));
+            }
+            FilterCondition::Not { condition } => condition.patterns(patterns),
+            FilterCondition::Title { pattern } => patterns.push(pattern),
+            _ => (),
+        }
+    }
+}
+
+impl<'a> Filter<'a> {
+    /// Compiles the title patterns, `rom_dir` is needed for the owned condition,
+    /// without it no rom counts as owned
+    pub fn new(ruleset: &'a FilterRuleset, rom_dir: Option<PathBuf>) -> io::Result<Self> {
+        let mut sources = Vec::new();
+        for condition in ruleset.include.iter().chain(&ruleset.exclude) {
+            condition.patterns(&mut sources);
+        }
+
+        let patterns = sources
+            .into_iter()
+            .map(|source| {
+                Regex::new(source)
+                    .map(|regex| (source, regex))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Filter {
+            ruleset,
+            patterns,
+            rom_dir,
+        })
+    }
+
+    fn evaluate(&self, condition: &FilterCondition, rom: &Rom, flags: &NameFlags) -> bool {
+        match condition {
+            FilterCondition::All { conditions } => conditions
+                .iter()
+                .all(|condition| self.evaluate(condition, rom, flags)),
+            FilterCondition::Any { conditions } => conditions
+                .iter()
+                .any(|condition| self.evaluate(condition, rom, flags)),
+            FilterCondition::Not { condition } => !self.evaluate(condition, rom, flags),
+            FilterCondition::Category { categories } => categories
+                .iter()
+                .any(|category| category.eq_ignore_ascii_case(&rom.category)),
+            FilterCondition::Flag { flag } => flag.is_set(flags),
+            FilterCondition::Region { regions } => regions.iter().any(|region| {
+                flags
+                    .regions
+                    .iter()
+                    .any(|rom_region| rom_region.eq_ignore_ascii_case(region))
+            }),
+            FilterCondition::Title { pattern } => {
+                self.patterns[pattern.as_str()].is_match(&rom.title)
+            }
+            FilterCondition::Size { min, max } => {
+                min.is_none_or(|min| rom.size >= min) && max.is_none_or(|max| rom.size <= max)
+            }
+            FilterCondition::Owned => self
+                .rom_dir
+                .as_ref()
+                .is_some_and(|rom_dir| rom_dir.join(&rom.title).is_file()),
+        }
+    }
+
+    pub fn matches(&self, rom: &Rom) -> bool {
+        let flags = NameFlags::parse(&rom.title);
+
+        self.ruleset
+            .include
+            .as_ref()
+            .is_none_or(|include| self.evaluate(include, rom, &flags))
+            && !self
+                .ruleset
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| self.evaluate(exclude, rom, &flags))
+    }
+
+    pub fn apply(&self, games: Vec<GameWithRoms>) -> Vec<GameWithRoms> {
+        games
+            .into_iter()
+            .filter_map(|mut game_roms| {
+                game_roms.roms.retain(|rom| self.matches(rom));
+                (!game_roms.roms.is_empty()).then_some(game_roms)
+            })
+            .collect()
+    }
+}
+
+/// Loads the games of the console with the roms the ruleset keeps, all of them without one
+pub fn filtered_games(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    ruleset: Option<&FilterRuleset>,
+    config: &AppConfig,
+) -> io::Result<Vec<GameWithRoms>> {
+    let games = games_routes::get_games_for_console(conn, &console.id);
+
+    match ruleset {
+        Some(ruleset) => Ok(Filter::new(ruleset, config.rom_dir(console).ok())?.apply(games)),
+        None => Ok(games),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Game;
+
+    use super::*;
+
+    fn rom(id: i32, title: &str, category: &str, size: i64) -> Rom {
+        Rom {
+            id,
+            title: title.to_string(),
+            size,
+            game_id: 1,
+            category: category.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let dir = std::env::temp_dir().join("romana_filters");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Super Metroid (Japan, USA) (En,Ja).sfc"), b"").unwrap();
+
+        let games = vec![GameWithRoms {
+            game: Game {
+                id: 1,
+                title: "Super Metroid".to_string(),
+                console_id: 1,
+                ..Default::default()
+            },
+            roms: vec![
+                rom(
+                    1,
+                    "Super Metroid (Japan, USA) (En,Ja).sfc",
+                    "Games",
+                    3145728,
+                ),
+                rom(2, "Super Metroid (Europe) (En,Fr,De).sfc", "Games", 3145728),
+                rom(3, "Super Metroid (USA) (Beta).sfc", "Games", 2097152),
+                rom(4, "Super Metroid (USA) (Demo).sfc", "Demos", 1048576),
+            ],
+            metadata: Default::default(),
+        }];
+
+        // USA or Europe games, without betas or roms named like a demo
+        let ruleset: FilterRuleset = serde_json::from_value(serde_json::json!({
+            "include": {
+                "kind": "all",
+                "conditions": [
+                    { "kind": "category", "categories": ["Games"] },
+                    { "kind": "region", "regions": ["USA", "Europe"] },
+                ],
+            },
+            "exclude": {
+                "kind": "any",
+                "conditions": [
+                    { "kind": "flag", "flag": "beta" },
+                    { "kind": "title", "pattern": "(?i)\\(demo\\)" },
+                ],
+            },
+        }))
+        .unwrap();
+        let filter = Filter::new(&ruleset, Some(dir.clone())).unwrap();
+        let ids = |games: Vec<GameWithRoms>| -> Vec<i32> {
+            games
+                .iter()
+                .flat_map(|game_roms| &game_roms.roms)
+                .map(|rom| rom.id)
+                .collect()
+        };
+        assert_eq!(vec![1, 2], ids(filter.apply(games.clone())));
+
+        let ruleset = FilterRuleset {
+            include: Some(FilterCondition::Any {
+                conditions: vec![
+                    FilterCondition::Owned,
+                    FilterCondition::Size {
+                        min: None,
+                        max: Some(1048576),
+                    },
+                ],
+            }),
+            exclude: None,
+        };
+        let filter = Filter::new(&ruleset, Some(dir.clone())).unwrap();
+        assert_eq!(vec![1, 4], ids(filter.apply(games.clone())));
+
+        let ruleset = FilterRuleset {
+            include: Some(FilterCondition::Owned),
+            exclude: Some(FilterCondition::Title {
+                pattern: "Metroid".to_string(),
+            }),
+        };
+        assert!(Filter::new(&ruleset, Some(dir.clone()))
+            .unwrap()
+            .apply(games)
+            .is_empty());
+
+        let invalid = FilterRuleset {
+            include: Some(FilterCondition::Title {
+                pattern: "(".to_string(),
+            }),
+            exclude: None,
+        };
+        assert!(Filter::new(&invalid, None).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use crc32fast::Hasher as Crc32;
+use md5::{Digest, Md5};
+use serde::Serialize;
+use sha1::Sha1;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// All hashes DATs use to identify a file, as lowercase hex
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileHashes {
+    pub size: u64,
+    pub crc32: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+/// Reads the whole input in chunks, passing each chunk to `update`
+fn read_chunks(reader: &mut impl Read, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        update(&buffer[..read]);
+    }
+}
+
+/// Returns the lowercase hex md5 of a file, the same format the DATs and the `roms` table use
+pub fn md5_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Md5::new();
+
+    read_chunks(&mut BufReader::new(File::open(path)?), |chunk| {
+        hasher.update(chunk)
+    })?;
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Calculates size, crc32, md5 and sha1 of data fed in chunks, for data that is processed
+/// otherwise at the same time
+#[derive(Default)]
+pub struct Hasher {
+    size: u64,
+    crc32: Crc32,
+    md5: Md5,
+    sha1: Sha1,
+}
+
+impl Hasher {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.size += chunk.len() as u64;
+        self.crc32.update(chunk);
+        self.md5.update(chunk);
+        self.sha1.update(chunk);
+    }
+
+    pub fn finish(self) -> FileHashes {
+        FileHashes {
+            size: self.size,
+            crc32: format!("{:08x}", self.crc32.finalize()),
+            md5: to_hex(&self.md5.finalize()),
+            sha1: to_hex(&self.sha1.finalize()),
+        }
+    }
+}
+
+/// Calculates size, crc32, md5 and sha1 in a single pass over the input
+pub fn hash_reader(reader: &mut impl Read) -> io::Result<FileHashes> {
+    let mut hasher = Hasher::default();
+    read_chunks(reader, |chunk| hasher.update(chunk))?;
+
+    Ok(hasher.finish())
+}
+
+pub fn hash_file(path: &Path) -> io::Result<FileHashes> {
+    hash_reader(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!("00ff10", to_hex(&[0x00, 0xff, 0x10]));
+    }
+
+    #[test]
+    fn test_md5_file() {
+        let path = std::env::temp_dir().join("romana_md5_test.bin");
+        std::fs::write(&path, b"romana").unwrap();
+
+        let hash = md5_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(to_hex(&Md5::digest(b"romana")), hash);
+    }
+
+    #[test]
+    fn test_hash_reader() {
+        let hashes =
+            hash_reader(&mut "The quick brown fox jumps over the lazy dog".as_bytes()).unwrap();
+
+        assert_eq!(
+            FileHashes {
+                size: 43,
+                crc32: "414fa339".to_string(),
+                md5: "9e107d9d372bb6826bd81d3542a419d6".to_string(),
+                sha1: "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12".to_string(),
+            },
+            hashes
+        );
+    }
+}
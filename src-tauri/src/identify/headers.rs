@@ -0,0 +1,165 @@
+use super::{image::SECTOR_SIZE, DiscId, DiscPlatform};
+
+/// Magic words of Nintendo's disc header, big endian
+const GAMECUBE_MAGIC: [u8; 4] = [0xc2, 0x33, 0x9f, 0x3d];
+const WII_MAGIC: [u8; 4] = [0x5d, 0x1c, 0x9e, 0xa3];
+
+fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string()
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.is_empty()).then_some(text)
+}
+
+/// Reads the system area header of Saturn, Sega CD and Dreamcast discs (the Dreamcast's
+/// `IP.BIN` at the start of the high density area)
+pub fn sega(sector: &[u8; SECTOR_SIZE]) -> Option<DiscId> {
+    let (platform, serial, title) = match &sector[..16] {
+        b"SEGA SEGASATURN " => (
+            DiscPlatform::Saturn,
+            text(&sector[0x20..0x2a]),
+            &sector[0x60..0xd0],
+        ),
+        b"SEGA SEGAKATANA " => (
+            DiscPlatform::Dreamcast,
+            text(&sector[0x40..0x4a]),
+            &sector[0x80..0x100],
+        ),
+        // like the cartridge header, e.g. `GM T-93185 -00`
+        b"SEGADISCSYSTEM  " => {
+            let serial = text(&sector[0x180..0x18e]);
+            let serial = serial.strip_prefix("GM").unwrap_or(&serial).trim();
+            let serial = serial.split_once(" -").map_or(serial, |(serial, _)| serial);
+            (
+                DiscPlatform::SegaCd,
+                serial.trim().to_string(),
+                &sector[0x150..0x180],
+            )
+        }
+        _ => return None,
+    };
+
+    Some(DiscId {
+        platform: Some(platform),
+        serial: non_empty(serial),
+        volume_id: non_empty(text(title)),
+    })
+}
+
+/// Reads the GameCube and Wii disc header: the six character game id, e.g. `GALE01`, and
+/// the internal name
+pub fn nintendo(sector: &[u8; SECTOR_SIZE]) -> Option<DiscId> {
+    let platform = if sector[0x1c..0x20] == GAMECUBE_MAGIC {
+        DiscPlatform::GameCube
+    } else if sector[0x18..0x1c] == WII_MAGIC {
+        DiscPlatform::Wii
+    } else {
+        return None;
+    };
+
+    let title_end = sector[0x20..0x400]
+        .iter()
+        .position(|&byte| byte == 0)
+        .map_or(0x400, |end| 0x20 + end);
+
+    Some(DiscId {
+        platform: Some(platform),
+        serial: non_empty(text(&sector[..6])),
+        volume_id: non_empty(text(&sector[0x20..title_end])),
+    })
+}
+
+/// Reads the boot executable of a PlayStation `SYSTEM.CNF`, e.g. `BOOT = cdrom:\SLUS_005.94;1`
+/// for the PS1 or `BOOT2 = cdrom0:\SLUS_201.23;1` for the PS2. The executable is named
+/// after the serial, `SLUS-00594` here.
+pub fn system_cnf(cnf: &[u8]) -> Option<(DiscPlatform, String)> {
+    let cnf = String::from_utf8_lossy(cnf);
+
+    cnf.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        let platform = match key.trim().to_ascii_uppercase().as_str() {
+            "BOOT2" => DiscPlatform::PlayStation2,
+            "BOOT" => DiscPlatform::PlayStation,
+            _ => return None,
+        };
+
+        let executable = value.trim().rsplit(['\\', '/', ':']).next()?;
+        let executable = executable.split(';').next()?;
+        let (prefix, number) = executable.split_once('_')?;
+        if prefix.len() != 4 || !prefix.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        Some((
+            platform,
+            format!(
+                "{}-{}",
+                prefix.to_ascii_uppercase(),
+                number.replace('.', "")
+            ),
+        ))
+    })
+}
+
+/// Reads the serial of a PSP `UMD_DATA.BIN`, which starts like `ULUS-10041|`
+pub fn umd_data(data: &[u8]) -> Option<String> {
+    let data = String::from_utf8_lossy(data);
+    let serial = data.split('|').next()?.trim();
+
+    (serial.len() >= 9 && serial.contains('-')).then(|| serial.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sega() {
+        let mut sector = [b' '; SECTOR_SIZE];
+        sector[..16].copy_from_slice(b"SEGA SEGASATURN ");
+        sector[0x20..0x2a].copy_from_slice(b"MK-81086  ");
+        sector[0x60..0x6e].copy_from_slice(b"SONIC 3D BLAST");
+
+        let id = sega(&sector).unwrap();
+        assert_eq!(Some(DiscPlatform::Saturn), id.platform);
+        assert_eq!(Some("MK-81086".to_string()), id.serial);
+        assert_eq!(Some("SONIC 3D BLAST".to_string()), id.volume_id);
+
+        sector[..16].copy_from_slice(b"SEGADISCSYSTEM  ");
+        sector[0x180..0x18e].copy_from_slice(b"GM T-93185 -00");
+        assert_eq!(Some("T-93185".to_string()), sega(&sector).unwrap().serial);
+    }
+
+    #[test]
+    fn test_nintendo() {
+        let mut sector = [0; SECTOR_SIZE];
+        sector[..6].copy_from_slice(b"GALE01");
+        sector[0x1c..0x20].copy_from_slice(&GAMECUBE_MAGIC);
+        sector[0x20..0x36].copy_from_slice(b"Super Smash Bros Melee");
+
+        let id = nintendo(&sector).unwrap();
+        assert_eq!(Some(DiscPlatform::GameCube), id.platform);
+        assert_eq!(Some("GALE01".to_string()), id.serial);
+        assert_eq!(Some("Super Smash Bros Melee".to_string()), id.volume_id);
+    }
+
+    #[test]
+    fn test_system_cnf() {
+        assert_eq!(
+            Some((DiscPlatform::PlayStation, "SLUS-00594".to_string())),
+            system_cnf(b"BOOT = cdrom:\\SLUS_005.94;1\r\nTCB = 4\r\n")
+        );
+        assert_eq!(
+            Some((DiscPlatform::PlayStation2, "SLES-12345".to_string())),
+            system_cnf(b"BOOT2 = cdrom0:\\SLES_123.45;1\nVER = 1.00\n")
+        );
+        assert_eq!(None, system_cnf(b"BOOT = cdrom:\\PSX.EXE;1"));
+        assert_eq!(
+            Some("ULUS-10041".to_string()),
+            umd_data(b"ULUS-10041|9A33D1A8DEE4E3C3|0001|G")
+        );
+    }
+}
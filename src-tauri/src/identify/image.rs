@@ -0,0 +1,164 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    chd::{cdrom, codecs::CD_FRAME_SIZE, Chd},
+    compression::ciso::CisoReader,
+    discs::{cue, gdi},
+};
+
+/// Bytes of user data in the sectors filesystems address
+pub const SECTOR_SIZE: usize = 2048;
+const RAW_SECTOR_SIZE: u64 = 2352;
+const SYNC: [u8; 12] = [0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0];
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+enum Source {
+    Stream(Box<dyn ReadSeek>),
+    Chd(Chd),
+}
+
+impl Source {
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        match self {
+            Source::Stream(stream) => {
+                stream.seek(SeekFrom::Start(offset))?;
+                stream.read_exact(buffer)
+            }
+            Source::Chd(chd) => chd.read_bytes(offset, buffer),
+        }
+    }
+}
+
+/// A data track of a disc image, read as the 2048 byte sectors of its filesystem whether
+/// the image stores cooked or raw sectors
+pub struct DataTrack {
+    source: Source,
+    /// byte offset of the track's first sector
+    start: u64,
+    sector_size: u64,
+    /// offset of the user data in a sector, after sync, header and mode 2 subheader
+    data_offset: u64,
+}
+
+impl DataTrack {
+    /// Detects raw sectors by the sync pattern of the first sector
+    fn new(mut source: Source, start: u64, sector_size: u64) -> io::Result<Self> {
+        let mut header = [0; 16];
+        source.read_at(start, &mut header)?;
+
+        let (sector_size, data_offset) = match (header[..12] == SYNC, header[15]) {
+            (true, 2) => (sector_size, 24),
+            (true, _) => (sector_size, 16),
+            // cooked sectors, CHD CD frames keep them at the start
+            (false, _) if sector_size == CD_FRAME_SIZE as u64 => (sector_size, 0),
+            (false, _) => (SECTOR_SIZE as u64, 0),
+        };
+
+        Ok(DataTrack {
+            source,
+            start,
+            sector_size,
+            data_offset,
+        })
+    }
+
+    fn from_file(path: &Path) -> io::Result<Self> {
+        DataTrack::new(
+            Source::Stream(Box::new(BufReader::new(File::open(path)?))),
+            0,
+            RAW_SECTOR_SIZE,
+        )
+    }
+
+    #[cfg(test)]
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        DataTrack::new(
+            Source::Stream(Box::new(io::Cursor::new(data))),
+            0,
+            RAW_SECTOR_SIZE,
+        )
+    }
+
+    pub fn read_sector(&mut self, lba: u64) -> io::Result<[u8; SECTOR_SIZE]> {
+        let mut sector = [0; SECTOR_SIZE];
+        let offset = self.start + lba * self.sector_size + self.data_offset;
+        self.source.read_at(offset, &mut sector)?;
+        Ok(sector)
+    }
+
+    /// Reads `length` bytes starting at a sector, across as many sectors as needed
+    pub fn read_extent(&mut self, lba: u64, length: usize) -> io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(length.next_multiple_of(SECTOR_SIZE));
+        for sector in 0..length.div_ceil(SECTOR_SIZE) as u64 {
+            data.extend(self.read_sector(lba + sector)?);
+        }
+        data.truncate(length);
+        Ok(data)
+    }
+}
+
+/// Opens the data tracks of a disc image: ISO, raw BIN, cue sheet, GDI, CHD, CSO or ZSO
+pub fn open(path: &Path) -> io::Result<Vec<DataTrack>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    match extension.as_str() {
+        "cue" => cue::parse(&fs::read_to_string(path)?)
+            .iter()
+            .map(|file| dir.join(&file.name))
+            .filter(|track| {
+                cue::TrackMode::detect(track).is_ok_and(|mode| mode != cue::TrackMode::Audio)
+            })
+            .map(|track| DataTrack::from_file(&track))
+            .collect(),
+        "gdi" => gdi::parse(&fs::read_to_string(path)?)
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|track| {
+                cue::TrackMode::detect(track).is_ok_and(|mode| mode != cue::TrackMode::Audio)
+            })
+            .map(|track| DataTrack::from_file(&track))
+            .collect(),
+        "chd" => {
+            let tracks = cdrom::tracks(&mut Chd::open(path)?)?;
+            if tracks.is_empty() {
+                // DVD images hold plain 2048 byte sectors
+                return Ok(vec![DataTrack::new(
+                    Source::Chd(Chd::open(path)?),
+                    0,
+                    SECTOR_SIZE as u64,
+                )?]);
+            }
+
+            tracks
+                .iter()
+                .filter(|track| !track.is_audio())
+                .map(|track| {
+                    DataTrack::new(
+                        Source::Chd(Chd::open(path)?),
+                        track.start_frame * CD_FRAME_SIZE as u64,
+                        CD_FRAME_SIZE as u64,
+                    )
+                })
+                .collect()
+        }
+        "cso" | "zso" => Ok(vec![DataTrack::new(
+            Source::Stream(Box::new(CisoReader::new(BufReader::new(File::open(
+                path,
+            )?))?)),
+            0,
+            SECTOR_SIZE as u64,
+        )?]),
+        _ => Ok(vec![DataTrack::from_file(path)?]),
+    }
+}
@@ -0,0 +1,187 @@
+use std::io;
+
+use super::image::{DataTrack, SECTOR_SIZE};
+
+/// The volume descriptors start after the 16 sectors of the system area
+const DESCRIPTORS_START: u64 = 16;
+const PRIMARY_VOLUME_DESCRIPTOR: u8 = 1;
+const DESCRIPTOR_SET_TERMINATOR: u8 = 255;
+const DIRECTORY_FLAG: u8 = 0x02;
+/// files inspected for serials are tiny, larger ones are not read
+const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+/// A directory record's extent
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Extent {
+    lba: u32,
+    length: u32,
+    directory: bool,
+}
+
+impl Extent {
+    /// Reads the little endian halves of the both-endian fields
+    fn parse(record: &[u8]) -> Self {
+        Extent {
+            lba: u32::from_le_bytes(record[2..6].try_into().unwrap()),
+            length: u32::from_le_bytes(record[10..14].try_into().unwrap()),
+            directory: record[25] & DIRECTORY_FLAG != 0,
+        }
+    }
+}
+
+pub struct Volume {
+    pub volume_id: String,
+    root: Extent,
+}
+
+impl Volume {
+    /// Finds the primary volume descriptor, `None` if the track has no ISO9660 filesystem
+    pub fn open(track: &mut DataTrack) -> io::Result<Option<Self>> {
+        for lba in DESCRIPTORS_START..DESCRIPTORS_START + 16 {
+            let Ok(sector) = track.read_sector(lba) else {
+                return Ok(None);
+            };
+            if &sector[1..6] != b"CD001" {
+                return Ok(None);
+            }
+
+            match sector[0] {
+                PRIMARY_VOLUME_DESCRIPTOR => {
+                    return Ok(Some(Volume {
+                        volume_id: String::from_utf8_lossy(&sector[40..72]).trim().to_string(),
+                        root: Extent::parse(&sector[156..190]),
+                    }))
+                }
+                DESCRIPTOR_SET_TERMINATOR => return Ok(None),
+                _ => (),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reads a file by its path like `PSP_GAME/PARAM.SFO`, ignoring case and versions
+    pub fn read_file(&self, track: &mut DataTrack, path: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut extent = self.root;
+
+        for name in path.split('/') {
+            if !extent.directory {
+                return Ok(None);
+            }
+            match find_entry(track, extent, name)? {
+                Some(entry) => extent = entry,
+                None => return Ok(None),
+            }
+        }
+
+        if extent.directory || extent.length as usize > MAX_FILE_SIZE {
+            return Ok(None);
+        }
+        track
+            .read_extent(extent.lba as u64, extent.length as usize)
+            .map(Some)
+    }
+}
+
+/// File identifiers end in a version like `;1`, some mastering tools leave a trailing dot
+fn matches_name(identifier: &[u8], name: &str) -> bool {
+    let identifier = String::from_utf8_lossy(identifier);
+    let identifier = identifier.split(';').next().unwrap_or_default();
+
+    identifier.trim_end_matches('.').eq_ignore_ascii_case(name)
+}
+
+fn find_entry(track: &mut DataTrack, directory: Extent, name: &str) -> io::Result<Option<Extent>> {
+    let data = track.read_extent(directory.lba as u64, directory.length as usize)?;
+
+    // records never cross sector boundaries, the rest of a sector is zero padded
+    for sector in data.chunks(SECTOR_SIZE) {
+        let mut offset = 0;
+        while offset < sector.len() {
+            let length = sector[offset] as usize;
+            if length < 34 || offset + length > sector.len() {
+                break;
+            }
+
+            let record = &sector[offset..offset + length];
+            let identifier_length = record[32] as usize;
+            if 33 + identifier_length <= length
+                && matches_name(&record[33..33 + identifier_length], name)
+            {
+                return Ok(Some(Extent::parse(record)));
+            }
+            offset += length;
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A directory record of a file or directory
+    pub fn record(name: &str, lba: u32, length: u32, directory: bool) -> Vec<u8> {
+        let mut record = vec![0; 33];
+        record[2..6].copy_from_slice(&lba.to_le_bytes());
+        record[6..10].copy_from_slice(&lba.to_be_bytes());
+        record[10..14].copy_from_slice(&length.to_le_bytes());
+        record[14..18].copy_from_slice(&length.to_be_bytes());
+        record[25] = if directory { 0x02 } else { 0 };
+        record[32] = name.len() as u8;
+        record.extend(name.as_bytes());
+        if record.len() % 2 == 1 {
+            record.push(0);
+        }
+        record[0] = record.len() as u8;
+        record
+    }
+
+    /// An ISO image with the root directory at sector 18 and the files from sector 19 on
+    pub fn image(volume_id: &str, files: &[(&str, &[u8])]) -> Vec<u8> {
+        let sectors = 19 + files.len();
+        let mut image = vec![0; sectors * 2048];
+
+        let descriptor = &mut image[16 * 2048..17 * 2048];
+        descriptor[0] = 1;
+        descriptor[1..6].copy_from_slice(b"CD001");
+        descriptor[40..72].fill(b' ');
+        descriptor[40..40 + volume_id.len()].copy_from_slice(volume_id.as_bytes());
+        descriptor[156..190].copy_from_slice(&record("\0", 18, 2048, true));
+        image[17 * 2048..17 * 2048 + 6].copy_from_slice(b"\xffCD001");
+
+        let mut directory = record("\0", 18, 2048, true);
+        directory.extend(record("\x01", 18, 2048, true));
+        for (index, (name, data)) in files.iter().enumerate() {
+            let lba = 19 + index as u32;
+            directory.extend(record(
+                &format!("{};1", name),
+                lba,
+                data.len() as u32,
+                false,
+            ));
+            image[lba as usize * 2048..lba as usize * 2048 + data.len()].copy_from_slice(data);
+        }
+        image[18 * 2048..18 * 2048 + directory.len()].copy_from_slice(&directory);
+
+        image
+    }
+
+    #[test]
+    fn test_read_file() {
+        let image = image(
+            "GAME_VOLUME",
+            &[("SYSTEM.CNF", b"BOOT = cdrom:\\SLUS_005.94;1\r\n")],
+        );
+        let mut track = DataTrack::from_bytes(image).unwrap();
+
+        let volume = Volume::open(&mut track).unwrap().unwrap();
+        assert_eq!("GAME_VOLUME", volume.volume_id);
+        assert_eq!(
+            Some(b"BOOT = cdrom:\\SLUS_005.94;1\r\n".to_vec()),
+            volume.read_file(&mut track, "system.cnf").unwrap()
+        );
+        assert_eq!(None, volume.read_file(&mut track, "UMD_DATA.BIN").unwrap());
+    }
+}
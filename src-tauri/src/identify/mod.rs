@@ -0,0 +1,427 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    config::AppConfig,
+    file_utils::walk_files,
+    models::{Console, Game},
+    routes::games_routes,
+};
+
+use self::image::DataTrack;
+
+mod headers;
+pub mod image;
+mod iso9660;
+mod udf;
+
+/// Extensions of the disc images that are inspected. Track `.bin`s are read through their
+/// cue sheet, only folders without one have their `.bin`s inspected directly.
+const IMAGE_EXTENSIONS: [&str; 8] = ["iso", "cue", "gdi", "chd", "cso", "zso", "gcm", "img"];
+/// word similarity needed to suggest a game for a disc without matching serial
+const PROBABLE_MATCH_SCORE: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscPlatform {
+    PlayStation,
+    PlayStation2,
+    Psp,
+    Saturn,
+    SegaCd,
+    Dreamcast,
+    GameCube,
+    Wii,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct DiscId {
+    pub platform: Option<DiscPlatform>,
+    pub serial: Option<String>,
+    /// the filesystem's volume id, or the internal name of Sega and Nintendo headers
+    pub volume_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// the disc's serial is one of the game's serials
+    Serial,
+    /// the volume id or file name resembles the game's title
+    Probable,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GameMatch {
+    pub game: Game,
+    pub kind: MatchKind,
+    /// 1 for serial matches, the word similarity for probable ones
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiscIdentification {
+    pub path: PathBuf,
+    /// `None` if the image could not be read
+    pub disc: Option<DiscId>,
+    pub game_match: Option<GameMatch>,
+}
+
+enum Filesystem {
+    Iso9660(iso9660::Volume),
+    Udf(udf::Volume),
+}
+
+impl Filesystem {
+    /// Prefers ISO9660, which PS2 DVDs carry next to UDF as well
+    fn open(track: &mut DataTrack) -> io::Result<Option<Self>> {
+        if let Some(volume) = iso9660::Volume::open(track)? {
+            return Ok(Some(Filesystem::Iso9660(volume)));
+        }
+        Ok(udf::Volume::open(track)?.map(Filesystem::Udf))
+    }
+
+    fn volume_id(&self) -> &str {
+        match self {
+            Filesystem::Iso9660(volume) => &volume.volume_id,
+            Filesystem::Udf(volume) => &volume.volume_id,
+        }
+    }
+
+    fn read_file(&self, track: &mut DataTrack, path: &str) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Filesystem::Iso9660(volume) => volume.read_file(track, path),
+            Filesystem::Udf(volume) => volume.read_file(track, path),
+        }
+    }
+}
+
+/// Reads a disc image's serial and volume id: from the Sega or Nintendo header of one of
+/// its data tracks, or from the PlayStation files of the first data track's filesystem
+pub fn identify(path: &Path) -> io::Result<DiscId> {
+    let mut tracks = image::open(path)?;
+
+    for track in &mut tracks {
+        let sector = track.read_sector(0)?;
+        if let Some(id) = headers::sega(&sector).or_else(|| headers::nintendo(&sector)) {
+            return Ok(id);
+        }
+    }
+
+    let Some(track) = tracks.first_mut() else {
+        return Ok(DiscId::default());
+    };
+    let Some(filesystem) = Filesystem::open(track)? else {
+        return Ok(DiscId::default());
+    };
+
+    let mut id = DiscId {
+        volume_id: Some(filesystem.volume_id().to_string()).filter(|id| !id.is_empty()),
+        ..Default::default()
+    };
+    if let Some(cnf) = filesystem.read_file(track, "SYSTEM.CNF")? {
+        if let Some((platform, serial)) = headers::system_cnf(&cnf) {
+            id.platform = Some(platform);
+            id.serial = Some(serial);
+        }
+    } else if let Some(umd_data) = filesystem.read_file(track, "UMD_DATA.BIN")? {
+        id.platform = Some(DiscPlatform::Psp);
+        id.serial = headers::umd_data(&umd_data);
+    }
+
+    Ok(id)
+}
+
+/// Serials compare without their separators, `SLUS_005.94` is `SLUS-00594`
+fn normalize_serial(serial: &str) -> String {
+    serial
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Whether the disc's serial is among the game's. DATs list Nintendo discs by their
+/// product code like `DL-DOL-GALE-USA`, which contains the first four characters of the
+/// game id.
+fn serial_matches(game: &Game, disc: &DiscId) -> bool {
+    let Some(serial) = disc.serial.as_deref() else {
+        return false;
+    };
+    let nintendo = matches!(
+        disc.platform,
+        Some(DiscPlatform::GameCube | DiscPlatform::Wii)
+    );
+    let serial = normalize_serial(serial);
+    // only a full game id names the product code, shorter serials are compared as they are
+    let game_code = serial.get(..4).filter(|_| nintendo);
+
+    game.serial
+        .split(',')
+        .filter(|game_serial| !game_serial.trim().is_empty())
+        .any(|game_serial| {
+            let code_matches = game_code.is_some_and(|code| {
+                game_serial
+                    .split('-')
+                    .any(|part| part.trim().eq_ignore_ascii_case(code))
+            });
+
+            code_matches || normalize_serial(game_serial) == serial
+        })
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_uppercase)
+        .collect()
+}
+
+/// Dice coefficient of the words, names without separators like `FINALFANTASY7` compare
+/// as a whole
+fn similarity(text: &str, title: &str) -> f32 {
+    let condensed = normalize_serial(text);
+    if !condensed.is_empty() && condensed == normalize_serial(title) {
+        return 1.0;
+    }
+
+    let (text, title) = (words(text), words(title));
+    if text.is_empty() || title.is_empty() {
+        return 0.0;
+    }
+    2.0 * text.intersection(&title).count() as f32 / (text.len() + title.len()) as f32
+}
+
+/// Finds the game of a disc by serial, otherwise suggests the game whose title is most
+/// like the disc's volume id or the file's name without its flags
+fn match_game(path: &Path, disc: &DiscId, games: &[Game]) -> Option<GameMatch> {
+    if let Some(game) = games.iter().find(|game| serial_matches(game, disc)) {
+        return Some(GameMatch {
+            game: game.clone(),
+            kind: MatchKind::Serial,
+            score: 1.0,
+        });
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = stem.split('(').next().unwrap_or_default();
+    let texts: Vec<&str> = disc
+        .volume_id
+        .iter()
+        .map(String::as_str)
+        .chain([name])
+        .collect();
+
+    games
+        .iter()
+        .map(|game| {
+            let score = texts
+                .iter()
+                .map(|text| similarity(text, &game.title))
+                .fold(0.0, f32::max);
+            (game, score)
+        })
+        .filter(|(_, score)| *score >= PROBABLE_MATCH_SCORE)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(game, score)| GameMatch {
+            game: game.clone(),
+            kind: MatchKind::Probable,
+            score,
+        })
+}
+
+/// Identifies a disc image and matches it against the games
+pub fn identify_file(path: &Path, games: &[Game]) -> io::Result<DiscIdentification> {
+    let disc = match identify(path) {
+        Ok(disc) => Some(disc),
+        // truncated or foreign files are reported as unreadable
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    let game_match = disc.as_ref().and_then(|disc| match_game(path, disc, games));
+
+    Ok(DiscIdentification {
+        path: path.to_path_buf(),
+        disc,
+        game_match,
+    })
+}
+
+/// Identifies every disc image in the console's rom folder against its games
+pub fn scan_console(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    config: &AppConfig,
+) -> io::Result<Vec<DiscIdentification>> {
+    let files = walk_files(&config.rom_dir(console)?)?;
+    let games: Vec<Game> = games_routes::get_games_for_console(conn, &console.id)
+        .into_iter()
+        .map(|game_roms| game_roms.game)
+        .collect();
+
+    let has_extension = |path: &Path, extensions: &[&str]| {
+        path.extension().is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        })
+    };
+    let sheet_dirs: HashSet<&Path> = files
+        .iter()
+        .filter(|path| has_extension(path, &["cue", "gdi"]))
+        .filter_map(|path| path.parent())
+        .collect();
+
+    files
+        .iter()
+        .filter(|path| {
+            has_extension(path, &IMAGE_EXTENSIONS)
+                || (has_extension(path, &["bin"])
+                    && path.parent().is_some_and(|dir| !sheet_dirs.contains(dir)))
+        })
+        .map(|path| identify_file(path, &games))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn game(id: i32, title: &str, serial: &str) -> Game {
+        Game {
+            id,
+            title: title.to_string(),
+            console_id: 1,
+            serial: serial.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identify() {
+        let dir = std::env::temp_dir().join("romana_identify");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let iso = dir.join("ff7.iso");
+        let image = iso9660::tests::image(
+            "FINAL_FANTASY_VII",
+            &[("SYSTEM.CNF", b"BOOT = cdrom:\\SCUS_941.63;1\r\n")],
+        );
+        fs::write(&iso, &image).unwrap();
+
+        // the same disc as raw mode 2 sectors
+        let bin = dir.join("track.bin");
+        let raw: Vec<u8> = image
+            .chunks(2048)
+            .flat_map(|sector| {
+                let mut raw = vec![
+                    0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 2, 0, 2,
+                ];
+                raw.extend([0; 8]);
+                raw.extend(sector);
+                raw.resize(2352, 0);
+                raw
+            })
+            .collect();
+        fs::write(&bin, raw).unwrap();
+
+        let udf = dir.join("udf.iso");
+        fs::write(
+            &udf,
+            udf::tests::image("UDF", "UMD_DATA.BIN", b"ULUS-10041|0001|G"),
+        )
+        .unwrap();
+
+        let expected = DiscId {
+            platform: Some(DiscPlatform::PlayStation),
+            serial: Some("SCUS-94163".to_string()),
+            volume_id: Some("FINAL_FANTASY_VII".to_string()),
+        };
+        assert_eq!(expected, identify(&iso).unwrap());
+        assert_eq!(expected, identify(&bin).unwrap());
+        assert_eq!(
+            Some("ULUS-10041".to_string()),
+            identify(&udf).unwrap().serial
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_match_game() {
+        let games = vec![
+            game(1, "Final Fantasy VII", "SCUS-94163, SCUS-94164, SCUS-94165"),
+            game(
+                2,
+                "Super Smash Bros. Melee",
+                "DL-DOL-GALE-USA, DL-DOL-GALP-EUR",
+            ),
+            game(3, "Final Fantasy VIII", "SLUS-00892"),
+        ];
+        let disc = |platform, serial: &str, volume_id: &str| DiscId {
+            platform: Some(platform),
+            serial: Some(serial.to_string()),
+            volume_id: Some(volume_id.to_string()),
+        };
+
+        let found = match_game(
+            Path::new("renamed.iso"),
+            &disc(DiscPlatform::PlayStation, "SCUS-94164", "FF7_DISC2"),
+            &games,
+        )
+        .unwrap();
+        assert_eq!((1, MatchKind::Serial), (found.game.id, found.kind));
+
+        let found = match_game(
+            Path::new("melee.iso"),
+            &disc(DiscPlatform::GameCube, "GALE01", "Super Smash Bros Melee"),
+            &games,
+        )
+        .unwrap();
+        assert_eq!((2, MatchKind::Serial), (found.game.id, found.kind));
+
+        // a truncated game id doesn't match the parts of the product code
+        assert!(match_game(
+            Path::new("truncated.iso"),
+            &disc(DiscPlatform::GameCube, "DOL", "UNKNOWN"),
+            &games,
+        )
+        .is_none());
+
+        // a serial missing from the DAT falls back to the volume id
+        let found = match_game(
+            Path::new("disc.iso"),
+            &disc(
+                DiscPlatform::PlayStation,
+                "SLES-02080",
+                "FINAL_FANTASY_VIII",
+            ),
+            &games,
+        )
+        .unwrap();
+        assert_eq!((3, MatchKind::Probable), (found.game.id, found.kind));
+
+        assert!(match_game(
+            Path::new("unknown.iso"),
+            &disc(DiscPlatform::PlayStation, "SLUS-99999", "DEMO"),
+            &games,
+        )
+        .is_none());
+    }
+}
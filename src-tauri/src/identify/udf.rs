@@ -0,0 +1,333 @@
+use std::io;
+
+use super::image::{DataTrack, SECTOR_SIZE};
+
+/// Anchor volume descriptor pointer, always at sector 256
+const ANCHOR_LBA: u64 = 256;
+/// descriptor tag identifiers of ECMA-167
+const ANCHOR_TAG: u16 = 2;
+const PRIMARY_VOLUME_TAG: u16 = 1;
+const PARTITION_TAG: u16 = 5;
+const LOGICAL_VOLUME_TAG: u16 = 6;
+const TERMINATING_TAG: u16 = 8;
+const FILE_SET_TAG: u16 = 256;
+const FILE_IDENTIFIER_TAG: u16 = 257;
+const FILE_ENTRY_TAG: u16 = 261;
+const EXTENDED_FILE_ENTRY_TAG: u16 = 266;
+const DIRECTORY_CHARACTERISTIC: u8 = 0x02;
+const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn tag(data: &[u8]) -> u16 {
+    u16_at(data, 0)
+}
+
+/// Decodes OSTA compressed unicode: a compression id of 8 for one byte or 16 for two big
+/// endian bytes per character
+fn decode_identifier(data: &[u8]) -> String {
+    match data.split_first() {
+        Some((8, characters)) => characters.iter().map(|&byte| byte as char).collect(),
+        Some((16, characters)) => String::from_utf16_lossy(
+            &characters
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        _ => String::new(),
+    }
+}
+
+/// Fixed size identifier fields store their used length in the last byte
+fn decode_dstring(field: &[u8]) -> String {
+    let length = (*field.last().unwrap_or(&0) as usize).min(field.len() - 1);
+    decode_identifier(&field[..length]).trim().to_string()
+}
+
+pub struct Volume {
+    pub volume_id: String,
+    /// first sector of the partition, logical blocks count from here
+    partition_start: u64,
+    root_icb: u32,
+}
+
+impl Volume {
+    /// Follows the anchor to the volume descriptors and the file set, `None` if the track
+    /// has no UDF filesystem
+    pub fn open(track: &mut DataTrack) -> io::Result<Option<Self>> {
+        let Ok(anchor) = track.read_sector(ANCHOR_LBA) else {
+            return Ok(None);
+        };
+        if tag(&anchor) != ANCHOR_TAG {
+            return Ok(None);
+        }
+
+        // main volume descriptor sequence extent
+        let sequence_length = u32_at(&anchor, 16) as u64;
+        let sequence_start = u32_at(&anchor, 20) as u64;
+
+        let mut volume_id = String::new();
+        let mut partition_start = None;
+        let mut file_set = None;
+
+        for lba in sequence_start..sequence_start + sequence_length / SECTOR_SIZE as u64 {
+            let descriptor = track.read_sector(lba)?;
+            match tag(&descriptor) {
+                PRIMARY_VOLUME_TAG if volume_id.is_empty() => {
+                    volume_id = decode_dstring(&descriptor[24..56]);
+                }
+                PARTITION_TAG => partition_start = Some(u32_at(&descriptor, 188) as u64),
+                LOGICAL_VOLUME_TAG => {
+                    let logical_volume_id = decode_dstring(&descriptor[84..212]);
+                    if !logical_volume_id.is_empty() {
+                        volume_id = logical_volume_id;
+                    }
+                    // the file set descriptor's long_ad in the logical volume contents use
+                    file_set = Some(u32_at(&descriptor, 252));
+                }
+                TERMINATING_TAG => break,
+                _ => (),
+            }
+        }
+
+        let (Some(partition_start), Some(file_set)) = (partition_start, file_set) else {
+            return Ok(None);
+        };
+        let file_set = track.read_sector(partition_start + file_set as u64)?;
+        if tag(&file_set) != FILE_SET_TAG {
+            return Ok(None);
+        }
+
+        Ok(Some(Volume {
+            volume_id,
+            partition_start,
+            // the root directory's long_ad
+            root_icb: u32_at(&file_set, 404),
+        }))
+    }
+
+    /// Reads the data of the file entry at the logical block
+    fn read_entry(&self, track: &mut DataTrack, block: u32) -> io::Result<Option<Vec<u8>>> {
+        let entry = track.read_sector(self.partition_start + block as u64)?;
+        let (extended_attributes_offset, descriptors_offset) = match tag(&entry) {
+            FILE_ENTRY_TAG => (168, 176),
+            EXTENDED_FILE_ENTRY_TAG => (208, 216),
+            _ => return Ok(None),
+        };
+
+        let length = u64_at(&entry, 56) as usize;
+        if length > MAX_FILE_SIZE {
+            return Ok(None);
+        }
+        let extended_attributes = u32_at(&entry, extended_attributes_offset) as usize;
+        let descriptors_length = u32_at(&entry, extended_attributes_offset + 4) as usize;
+        let start = descriptors_offset + extended_attributes;
+        let Some(descriptors) = entry.get(start..start + descriptors_length) else {
+            return Err(invalid_udf("file entry too long"));
+        };
+
+        // the ICB tag's flags tell the kind of allocation descriptors
+        let mut data = match u16_at(&entry, 34) & 0x07 {
+            // short_ad: length and block
+            0 => self.read_allocations(track, descriptors, 8)?,
+            // long_ad: length, block and partition
+            1 => self.read_allocations(track, descriptors, 16)?,
+            // the data is embedded in the entry
+            3 => descriptors.to_vec(),
+            _ => return Err(invalid_udf("unsupported allocation descriptors")),
+        };
+        data.truncate(length);
+        Ok(Some(data))
+    }
+
+    fn read_allocations(
+        &self,
+        track: &mut DataTrack,
+        descriptors: &[u8],
+        descriptor_size: usize,
+    ) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        for descriptor in descriptors.chunks_exact(descriptor_size) {
+            // the top two bits of the length tell whether the extent is recorded
+            let length = u32_at(descriptor, 0) & 0x3fff_ffff;
+            if length == 0 {
+                break;
+            }
+            let block = u32_at(descriptor, 4);
+            data.extend(track.read_extent(self.partition_start + block as u64, length as usize)?);
+            if data.len() > MAX_FILE_SIZE {
+                break;
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Reads a file by its path like `PSP_GAME/PARAM.SFO`, ignoring case
+    pub fn read_file(&self, track: &mut DataTrack, path: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut block = self.root_icb;
+        let mut directory = true;
+
+        for name in path.split('/') {
+            if !directory {
+                return Ok(None);
+            }
+            let Some(data) = self.read_entry(track, block)? else {
+                return Ok(None);
+            };
+            match find_identifier(&data, name) {
+                Some((entry, is_directory)) => (block, directory) = (entry, is_directory),
+                None => return Ok(None),
+            }
+        }
+
+        if directory {
+            return Ok(None);
+        }
+        self.read_entry(track, block)
+    }
+}
+
+fn invalid_udf(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Finds a name among a directory's file identifier descriptors, returning the block of
+/// its file entry and whether it's a directory
+fn find_identifier(directory: &[u8], name: &str) -> Option<(u32, bool)> {
+    let mut offset = 0;
+
+    while offset + 38 <= directory.len() {
+        let descriptor = &directory[offset..];
+        if tag(descriptor) != FILE_IDENTIFIER_TAG {
+            return None;
+        }
+
+        let characteristics = descriptor[18];
+        let identifier_length = descriptor[19] as usize;
+        let implementation_length = u16_at(descriptor, 36) as usize;
+        let identifier_start = 38 + implementation_length;
+        let identifier = descriptor.get(identifier_start..identifier_start + identifier_length)?;
+
+        if decode_identifier(identifier).eq_ignore_ascii_case(name) {
+            return Some((
+                u32_at(descriptor, 24),
+                characteristics & DIRECTORY_CHARACTERISTIC != 0,
+            ));
+        }
+        offset += (identifier_start + identifier_length).next_multiple_of(4);
+    }
+
+    None
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn sector(image: &mut [u8], lba: usize) -> &mut [u8] {
+        &mut image[lba * SECTOR_SIZE..(lba + 1) * SECTOR_SIZE]
+    }
+
+    fn file_entry(length: usize, block: u32, directory: bool) -> Vec<u8> {
+        let mut entry = vec![0; 184];
+        entry[..2].copy_from_slice(&FILE_ENTRY_TAG.to_le_bytes());
+        entry[27] = if directory { 4 } else { 5 };
+        entry[56..64].copy_from_slice(&(length as u64).to_le_bytes());
+        entry[172..176].copy_from_slice(&8u32.to_le_bytes());
+        entry[176..180].copy_from_slice(&(length as u32).to_le_bytes());
+        entry[180..184].copy_from_slice(&block.to_le_bytes());
+        entry
+    }
+
+    /// The descriptor of a file, or of the parent directory without a name
+    fn file_identifier(name: &str, block: u32, characteristics: u8) -> Vec<u8> {
+        let mut descriptor = vec![0; 38];
+        descriptor[..2].copy_from_slice(&FILE_IDENTIFIER_TAG.to_le_bytes());
+        descriptor[18] = characteristics;
+        descriptor[24..28].copy_from_slice(&block.to_le_bytes());
+        if !name.is_empty() {
+            descriptor[19] = name.len() as u8 + 1;
+            descriptor.push(8);
+            descriptor.extend(name.as_bytes());
+        }
+        descriptor.resize(descriptor.len().next_multiple_of(4), 0);
+        descriptor
+    }
+
+    /// A UDF image with its partition at sector 300 and a file in the root directory
+    pub fn image(volume_id: &str, name: &str, data: &[u8]) -> Vec<u8> {
+        let mut image = vec![0; 310 * SECTOR_SIZE];
+        let partition = 300;
+
+        let anchor = sector(&mut image, 256);
+        anchor[..2].copy_from_slice(&ANCHOR_TAG.to_le_bytes());
+        anchor[16..20].copy_from_slice(&(3 * SECTOR_SIZE as u32).to_le_bytes());
+        anchor[20..24].copy_from_slice(&257u32.to_le_bytes());
+
+        let partition_descriptor = sector(&mut image, 257);
+        partition_descriptor[..2].copy_from_slice(&PARTITION_TAG.to_le_bytes());
+        partition_descriptor[188..192].copy_from_slice(&(partition as u32).to_le_bytes());
+
+        let logical_volume = sector(&mut image, 258);
+        logical_volume[..2].copy_from_slice(&LOGICAL_VOLUME_TAG.to_le_bytes());
+        logical_volume[84] = 8;
+        logical_volume[85..85 + volume_id.len()].copy_from_slice(volume_id.as_bytes());
+        logical_volume[211] = volume_id.len() as u8 + 1;
+        logical_volume[252..256].copy_from_slice(&0u32.to_le_bytes());
+
+        sector(&mut image, 259)[..2].copy_from_slice(&TERMINATING_TAG.to_le_bytes());
+
+        // file set at block 0, root entry at 1, root directory data at 2, file entry at 3
+        // and the file's data at 4
+        let file_set = sector(&mut image, partition);
+        file_set[..2].copy_from_slice(&FILE_SET_TAG.to_le_bytes());
+        file_set[404..408].copy_from_slice(&1u32.to_le_bytes());
+
+        let mut directory = file_identifier("", 1, DIRECTORY_CHARACTERISTIC | 0x08);
+        directory.extend(file_identifier(name, 3, 0));
+
+        sector(&mut image, partition + 1)[..184].copy_from_slice(&file_entry(
+            directory.len(),
+            2,
+            true,
+        ));
+        sector(&mut image, partition + 2)[..directory.len()].copy_from_slice(&directory);
+        sector(&mut image, partition + 3)[..184].copy_from_slice(&file_entry(data.len(), 4, false));
+        sector(&mut image, partition + 4)[..data.len()].copy_from_slice(data);
+
+        image
+    }
+
+    #[test]
+    fn test_read_file() {
+        let mut track = DataTrack::from_bytes(image(
+            "PS2 DVD",
+            "SYSTEM.CNF",
+            b"BOOT2 = cdrom0:\\SLES_123.45;1",
+        ))
+        .unwrap();
+
+        let volume = Volume::open(&mut track).unwrap().unwrap();
+        assert_eq!("PS2 DVD", volume.volume_id);
+        assert_eq!(
+            Some(b"BOOT2 = cdrom0:\\SLES_123.45;1".to_vec()),
+            volume.read_file(&mut track, "system.cnf").unwrap()
+        );
+        assert_eq!(
+            None,
+            volume.read_file(&mut track, "SYSTEM.CNF/FILE").unwrap()
+        );
+    }
+}
@@ -1,42 +1,218 @@
-use diesel::prelude::*;
-use dotenvy::dotenv;
-use std::{env, sync::Mutex};
-use tauri::{Manager, State};
+use std::{fs, path::PathBuf, sync::Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 use crate::{
+    bios::{import::BiosImportSummary, BiosExportSummary, BiosLayout, ConsoleBiosReport},
+    cartridge::RomHeader,
+    chd::ChdReport,
+    compression::{CompressionFormat, RomFileReport},
     config::AppConfig,
-    models::{Console, ConsoleWithGameRoms, ConsoleWithGames, GameWithRoms},
+    dat_parser::{
+        dat::{Dat, DatEdit, DatHeader},
+        logiqx,
+        parser::parse_dat,
+    },
+    db::DbPool,
+    discs::DiscReport,
+    exporters::{
+        one_game_one_rom::{OneGameOneRom, OneGameOneRomPick},
+        pegasus::{self, PegasusExportSummary},
+        retroarch::{self, RetroArchExportSummary},
+    },
+    filters::Filter,
+    identify::DiscIdentification,
+    metadata::GamelistImportSummary,
+    models::{
+        BiosFile, CompressedRom, Console, ConsoleWithGameRoms, ConsoleWithGames, GameMetadata,
+        GameWithRoms, Patch, PatchWithPatchedRoms, PatchedRom, SaveWithBackups,
+    },
+    patching::{PatchFormat, PatchMetadata},
     routes::{
+        bios_routes,
         console_routes::{self, get_all_consoles_with_games, get_console_with_game_roms},
-        games_routes,
+        games_routes::{self, GamePage, GameQuery},
+        maintenance_routes::{self, CleanupSummary, RemovalSummary},
+        metadata_routes, patch_routes, save_routes,
+        search_routes::{self, SearchHit},
     },
+    saves::{
+        convert::{self, SaveFormat},
+        SaveScanSummary,
+    },
+    sync::{SyncPlan, SyncReport, SyncRequest},
 };
 
+pub mod bios;
+pub mod cartridge;
+pub mod chd;
+pub mod compression;
 pub mod config;
 pub mod dat_parser;
+pub mod db;
+pub mod discs;
+pub mod exporters;
+pub mod file_utils;
+pub mod filters;
+pub mod hashing;
+pub mod identify;
+pub mod metadata;
 pub mod models;
+pub mod patching;
 pub mod routes;
+pub mod saves;
 pub mod schemas;
+pub mod sync;
 
 // TODO: refactor tauri commands
 #[tauri::command]
-fn get_consoles() -> Vec<Console> {
-    console_routes::get_consoles()
+fn get_consoles(db: State<'_, DbPool>) -> Result<Vec<Console>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    Ok(console_routes::get_consoles(conn))
+}
+
+#[tauri::command]
+fn get_consoles_games(db: State<'_, DbPool>) -> Result<Vec<ConsoleWithGames>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    get_all_consoles_with_games(conn).map_err(|e| e.to_string())
+}
+
+/// Loads the console's games, with only the roms the saved filter keeps if one is named
+#[tauri::command]
+fn get_console_game_roms(
+    console_name: String,
+    filter_name: Option<String>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<ConsoleWithGameRoms, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let mut console_game_roms = get_console_with_game_roms(conn, &console_name);
+
+    if let Some(ruleset) = config.filter_ruleset(filter_name.as_deref())? {
+        let rom_dir = config.rom_dir(&console_game_roms.console).ok();
+        console_game_roms.games = Filter::new(ruleset, rom_dir)
+            .map_err(|e| e.to_string())?
+            .apply(console_game_roms.games);
+    }
+
+    Ok(console_game_roms)
+}
+
+/// Loads the console's games, with only the roms the saved filter keeps if one is named
+#[tauri::command]
+fn get_game_roms_for_console(
+    console_id: i32,
+    filter_name: Option<String>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<GameWithRoms>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+    let ruleset = config.filter_ruleset(filter_name.as_deref())?;
+
+    filters::filtered_games(conn, &console, ruleset, &config).map_err(|e| e.to_string())
+}
+
+/// Loads one page of the console's games for the game lists, searched and sorted in the
+/// database, with only the roms the saved filter keeps if one is named
+#[tauri::command]
+async fn query_games(
+    console_id: i32,
+    query: GameQuery,
+    filter_name: Option<String>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<GamePage, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+    let rom_dir = config.rom_dir(&console).ok();
+    let rom_filter = config
+        .filter_ruleset(filter_name.as_deref())?
+        .map(|ruleset| filters::Filter::new(ruleset, rom_dir.clone()))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    games_routes::query_games(
+        conn,
+        console_id,
+        &query,
+        rom_dir.as_deref(),
+        rom_filter.as_ref(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Searches the game and rom titles of all consoles, best matches first
+#[tauri::command]
+async fn search_titles(
+    search: String,
+    limit: Option<i64>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<SearchHit>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    search_routes::search_titles(conn, &search, limit.unwrap_or(50)).map_err(|e| e.to_string())
 }
 
+/// Removes orphaned rows and unused regions, developers, publishers and genres, reporting what
+/// was removed
 #[tauri::command]
-fn get_consoles_games() -> Vec<ConsoleWithGames> {
-    get_all_consoles_with_games().expect("Error getting games")
+async fn clean_up_database(db: State<'_, DbPool>) -> Result<CleanupSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    maintenance_routes::remove_orphans(conn).map_err(|e| e.to_string())
 }
 
+/// Removes all games, roms and BIOS files of the console, reporting what was removed
 #[tauri::command]
-fn get_console_game_roms(console_name: String) -> ConsoleWithGameRoms {
-    get_console_with_game_roms(&console_name)
+async fn remove_console(console_id: i32, db: State<'_, DbPool>) -> Result<RemovalSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    maintenance_routes::remove_console(conn, console_id).map_err(|e| e.to_string())
 }
 
+/// Removes the games and roms of the DAT file, reporting what was removed
 #[tauri::command]
-fn get_game_roms_for_console(console_id: i32) -> Vec<GameWithRoms> {
-    games_routes::get_games_for_console(&console_id)
+async fn remove_dat(path: PathBuf, db: State<'_, DbPool>) -> Result<RemovalSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let dat = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let (console, games) = parse_dat(conn, &dat).map_err(|e| e.to_string())?;
+
+    maintenance_routes::remove_dat_games(conn, console.id, &games).map_err(|e| e.to_string())
+}
+
+/// Replaces the developers, publishers, genres, releases, player count and description of the game
+#[tauri::command]
+async fn set_game_metadata(
+    game_id: i32,
+    metadata: GameMetadata,
+    db: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    metadata_routes::set_metadata(conn, game_id, &metadata).map_err(|e| e.to_string())
+}
+
+/// Fills the games' metadata from the `gamelist.xml` files scrapers put into the rom folders
+#[tauri::command]
+async fn import_gamelists(
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<GamelistImportSummary>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    metadata::import_all(conn, &config).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -56,11 +232,401 @@ fn save_app_config(
     state_config.save(Some(&app_handle));
 }
 
+#[tauri::command]
+fn export_pegasus_metadata(
+    target_name: Option<String>,
+    filter_name: Option<String>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<PegasusExportSummary>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let target = config.export_target(target_name.as_deref())?;
+    let filter = config.filter_ruleset(filter_name.as_deref())?;
+
+    pegasus::export_all(conn, &config, target, filter).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_retroarch_playlists(
+    target_name: Option<String>,
+    filter_name: Option<String>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<RetroArchExportSummary>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let target = config.export_target(target_name.as_deref())?;
+    let filter = config.filter_ruleset(filter_name.as_deref())?;
+
+    retroarch::export_all(conn, &config, target, filter).map_err(|e| e.to_string())
+}
+
+/// Shows which release of every game of the console the 1G1R rules pick
+#[tauri::command]
+async fn preview_one_game_one_rom(
+    console_id: i32,
+    rules: OneGameOneRom,
+    db: State<'_, DbPool>,
+) -> Result<Vec<OneGameOneRomPick>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let games = games_routes::get_games_for_console(conn, &console_id);
+
+    Ok(rules.select(&games))
+}
+
+#[tauri::command]
+async fn preview_sync(
+    request: SyncRequest,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<SyncPlan, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let target = config.export_target(request.target.as_deref())?;
+
+    sync::plan(conn, &request, &config, target).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn run_sync(
+    request: SyncRequest,
+    state: State<'_, Mutex<AppConfig>>,
+    app_handle: AppHandle,
+    db: State<'_, DbPool>,
+) -> Result<SyncReport, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let target = config.export_target(request.target.as_deref())?;
+
+    let plan = sync::plan(conn, &request, &config, target).map_err(|e| e.to_string())?;
+
+    Ok(sync::execute(&plan, |progress| {
+        let _ = app_handle.emit(sync::PROGRESS_EVENT, progress);
+    }))
+}
+
+#[tauri::command]
+async fn scan_saves(
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<SaveScanSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    let consoles = console_routes::get_consoles(conn);
+
+    saves::scan(conn, &config.saves, &consoles).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_saves_for_game(game_id: i32, db: State<'_, DbPool>) -> Result<Vec<SaveWithBackups>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    save_routes::get_saves_for_game(conn, game_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn restore_save_backup(
+    backup_id: i32,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<PathBuf, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    saves::restore(conn, backup_id, &config.saves).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn convert_save(path: PathBuf, format: SaveFormat) -> Result<PathBuf, String> {
+    convert::convert_file(&path, format).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_bios_dat(
+    path: PathBuf,
+    db: State<'_, DbPool>,
+) -> Result<BiosImportSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let consoles = console_routes::get_consoles(conn);
+
+    bios::import::import_dat(conn, &path, &consoles).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_bios_report(
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<ConsoleBiosReport>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    bios::verify(conn, &config.bios).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_bios_required(
+    bios_file_id: i32,
+    required: bool,
+    db: State<'_, DbPool>,
+) -> Result<BiosFile, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    bios_routes::set_bios_required(conn, bios_file_id, required).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_bios(
+    root: PathBuf,
+    layout: BiosLayout,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<BiosExportSummary, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let system_dir = config.bios.system_dir().map_err(|e| e.to_string())?;
+
+    let reports = bios::verify(conn, &config.bios).map_err(|e| e.to_string())?;
+    bios::export(&reports, &root, layout, &system_dir).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn apply_patch(
+    rom_id: i32,
+    patch_path: PathBuf,
+    output_path: Option<PathBuf>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<PatchedRom, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    patching::apply_to_rom(conn, rom_id, &patch_path, output_path, &config)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_patches_for_rom(
+    rom_id: i32,
+    db: State<'_, DbPool>,
+) -> Result<Vec<PatchWithPatchedRoms>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    patch_routes::get_patches_for_rom(conn, rom_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn export_softpatch(
+    patch_id: i32,
+    target_dir: Option<PathBuf>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<PathBuf, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    patching::export_softpatch(conn, patch_id, target_dir.as_deref(), &config)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_patch(
+    original_path: PathBuf,
+    modified_path: PathBuf,
+    format: PatchFormat,
+    metadata: Option<PatchMetadata>,
+    output_path: Option<PathBuf>,
+    db: State<'_, DbPool>,
+) -> Result<Patch, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    patching::create_for_rom(
+        conn,
+        &original_path,
+        &modified_path,
+        format,
+        &metadata.unwrap_or_default(),
+        output_path,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn open_dat(path: PathBuf) -> Result<Dat, String> {
+    logiqx::read_file(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_dat_from_folder(path: PathBuf, header: DatHeader) -> Result<Dat, String> {
+    Dat::from_folder(&path, header).map_err(|e| e.to_string())
+}
+
+/// Builds a DAT of the console's games, all games if `game_ids` is empty
+#[tauri::command]
+fn create_dat_from_games(
+    console_id: i32,
+    game_ids: Vec<i32>,
+    db: State<'_, DbPool>,
+) -> Result<Dat, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+
+    let games: Vec<GameWithRoms> = games_routes::get_games_for_console(conn, &console_id)
+        .into_iter()
+        .filter(|game_roms| game_ids.is_empty() || game_ids.contains(&game_roms.game.id))
+        .collect();
+
+    Ok(Dat::from_games(&console, &games))
+}
+
+#[tauri::command]
+fn edit_dat(mut dat: Dat, edit: DatEdit) -> Result<Dat, String> {
+    dat.apply(edit)?;
+
+    Ok(dat)
+}
+
+#[tauri::command]
+fn save_dat(path: PathBuf, dat: Dat) -> Result<(), String> {
+    logiqx::write_file(&dat, &path).map_err(|e| e.to_string())
+}
+
+/// Verifies the console's multi-track discs, writing missing cue sheets if `regenerate_cues`
+#[tauri::command]
+async fn scan_discs(
+    console_id: i32,
+    regenerate_cues: bool,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<DiscReport>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+
+    discs::scan_console(conn, &console, &config, regenerate_cues).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_chd(path: PathBuf, db: State<'_, DbPool>) -> Result<ChdReport, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+
+    chd::verify(conn, &path).map_err(|e| e.to_string())
+}
+
+/// Compresses the rom's ISO to CSO or ZSO, `block_size` defaults to 2048
+#[tauri::command]
+async fn compress_rom(
+    rom_id: i32,
+    format: CompressionFormat,
+    block_size: Option<u32>,
+    remove_original: bool,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<CompressedRom, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    compression::compress_rom(conn, rom_id, format, block_size, remove_original, &config)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn decompress_rom(
+    compressed_rom_id: i32,
+    output_path: Option<PathBuf>,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<PathBuf, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+
+    compression::decompress_rom(conn, compressed_rom_id, output_path, &config)
+        .map_err(|e| e.to_string())
+}
+
+/// Checks which of the console's roms are present, plain or compressed
+#[tauri::command]
+async fn scan_rom_files(
+    console_id: i32,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<RomFileReport>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+
+    compression::scan_console(conn, &console, &config).map_err(|e| e.to_string())
+}
+
+/// Identifies a disc image by its serial and volume id, against the console's games or
+/// all games
+#[tauri::command]
+async fn identify_disc(
+    path: PathBuf,
+    console_id: Option<i32>,
+    db: State<'_, DbPool>,
+) -> Result<DiscIdentification, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let games = match console_id {
+        Some(console_id) => games_routes::get_games_for_console(conn, &console_id)
+            .into_iter()
+            .map(|game_roms| game_roms.game)
+            .collect(),
+        None => games_routes::get_all_games(conn),
+    };
+
+    identify::identify_file(&path, &games).map_err(|e| e.to_string())
+}
+
+/// Identifies every disc image in the console's rom folder
+#[tauri::command]
+async fn identify_console_discs(
+    console_id: i32,
+    state: State<'_, Mutex<AppConfig>>,
+    db: State<'_, DbPool>,
+) -> Result<Vec<DiscIdentification>, String> {
+    let conn = &mut db.get().map_err(|e| e.to_string())?;
+    let config = state.lock().unwrap().clone();
+    let console = console_routes::get_consoles(conn)
+        .into_iter()
+        .find(|console| console.id == console_id)
+        .ok_or_else(|| format!("Unknown console: {}", console_id))?;
+
+    identify::scan_console(conn, &console, &config).map_err(|e| e.to_string())
+}
+
+/// Reads the internal header of a cartridge rom, `None` when no known header is found
+#[tauri::command]
+async fn read_rom_header(path: PathBuf) -> Result<Option<RomHeader>, String> {
+    cartridge::read_header(&path).map_err(|e| e.to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            let app_config = AppConfig::load(Some(app.app_handle()));
+            let data_dir = app.path().app_data_dir()?;
+            fs::create_dir_all(&data_dir)?;
+
+            let pool = db::create_pool(&db::database_url(&data_dir))?;
+            let consoles = {
+                let conn = &mut pool.get()?;
+                db::prepare(conn, Some(&data_dir.join("backups")))?;
+                console_routes::get_consoles(conn)
+            };
+            app.manage(pool);
+
+            let app_config = AppConfig::load(Some(app.app_handle()), &consoles);
             app.manage(Mutex::new(app_config));
 
             Ok(())
@@ -71,24 +637,46 @@ pub fn run() {
             get_consoles_games,
             get_console_game_roms,
             get_game_roms_for_console,
+            query_games,
+            search_titles,
+            clean_up_database,
+            remove_console,
+            remove_dat,
+            set_game_metadata,
+            import_gamelists,
             get_app_config,
-            save_app_config
+            save_app_config,
+            export_pegasus_metadata,
+            export_retroarch_playlists,
+            preview_one_game_one_rom,
+            preview_sync,
+            run_sync,
+            scan_saves,
+            get_saves_for_game,
+            restore_save_backup,
+            convert_save,
+            import_bios_dat,
+            get_bios_report,
+            set_bios_required,
+            export_bios,
+            apply_patch,
+            get_patches_for_rom,
+            export_softpatch,
+            create_patch,
+            open_dat,
+            create_dat_from_folder,
+            create_dat_from_games,
+            edit_dat,
+            save_dat,
+            scan_discs,
+            verify_chd,
+            compress_rom,
+            decompress_rom,
+            scan_rom_files,
+            identify_disc,
+            identify_console_discs,
+            read_rom_header
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
-
-pub fn establish_connection() -> SqliteConnection {
-    dotenv().ok();
-
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-
-    let mut connection = SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
-
-    diesel::sql_query("PRAGMA journal_mode = WAL;")
-        .execute(&mut connection)
-        .expect("Failed to enable WAL mode");
-
-    connection
-}
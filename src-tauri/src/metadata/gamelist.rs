@@ -0,0 +1,165 @@
+use std::{fs, io, path::Path};
+
+use winnow::Parser;
+
+use crate::{
+    dat_parser::{
+        logiqx::{document_parser, Element},
+        name_flags::NameFlags,
+    },
+    models::{GameMetadata, GameRelease},
+};
+
+pub const GAMELIST_FILE_NAME: &str = "gamelist.xml";
+
+/// A `<game>` of an EmulationStation gamelist, as written by scrapers like Skraper or ES-DE
+#[derive(Debug, PartialEq)]
+pub struct GamelistEntry {
+    /// the rom's path relative to the gamelist, e.g. `./Secret of Mana (Europe).sfc`
+    pub path: String,
+    pub name: Option<String>,
+    pub metadata: GameMetadata,
+}
+
+/// Turns a date like `19931001T000000` into `1993-10-01`, leaving out unknown months and days
+fn parse_date(date: &str) -> Option<String> {
+    let digits: String = date.chars().take_while(char::is_ascii_digit).collect();
+    let part = |range: std::ops::Range<usize>| digits.get(range).filter(|part| *part != "00");
+
+    let year = part(0..4).filter(|year| *year != "0000")?;
+
+    Some(match (part(4..6), part(6..8)) {
+        (Some(month), Some(day)) => format!("{}-{}-{}", year, month, day),
+        (Some(month), None) => format!("{}-{}", year, month),
+        _ => year.to_string(),
+    })
+}
+
+/// The highest player count of values like `2` or `1-4`
+fn parse_players(players: &str) -> Option<i32> {
+    players
+        .rsplit(['-', '+'])
+        .find_map(|count| count.trim().parse().ok())
+}
+
+fn entry(game: &Element) -> Option<GamelistEntry> {
+    let path = game.child_text("path")?;
+    let region = NameFlags::parse(&path)
+        .regions
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    let metadata = GameMetadata {
+        developers: game.child_text("developer").into_iter().collect(),
+        publishers: game.child_text("publisher").into_iter().collect(),
+        genres: game
+            .child_text("genre")
+            .map(|genres| {
+                genres
+                    .split([',', '/'])
+                    .map(str::trim)
+                    .filter(|genre| !genre.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        releases: game
+            .child_text("releasedate")
+            .and_then(|date| parse_date(&date))
+            .map(|date| vec![GameRelease { region, date }])
+            .unwrap_or_default(),
+        players: game
+            .child_text("players")
+            .and_then(|players| parse_players(&players)),
+        description: game.child_text("desc"),
+    };
+
+    Some(GamelistEntry {
+        path,
+        name: game.child_text("name"),
+        metadata,
+    })
+}
+
+pub fn parse(input: &str) -> Result<Vec<GamelistEntry>, String> {
+    let gamelist = document_parser
+        .parse(input.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("invalid gamelist: {}", e))?;
+    if gamelist.name != "gameList" {
+        return Err(format!(
+            "invalid gamelist: unexpected <{}> element",
+            gamelist.name
+        ));
+    }
+
+    Ok(gamelist.children("game").filter_map(entry).collect())
+}
+
+pub fn read_file(path: &Path) -> io::Result<Vec<GamelistEntry>> {
+    parse(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let gamelist = r#"<?xml version="1.0"?>
+<gameList>
+	<game>
+		<path>./Secret of Mana (Europe).sfc</path>
+		<name>Secret of Mana</name>
+		<desc>Three heroes.
+
+Rescue the Mana tree.</desc>
+		<releasedate>19931119T000000</releasedate>
+		<developer>Square</developer>
+		<publisher>Nintendo</publisher>
+		<genre>Action / RPG</genre>
+		<players>1-3</players>
+	</game>
+	<game>
+		<path>./Star Fox (USA).sfc</path>
+		<releasedate>19930000T000000</releasedate>
+	</game>
+	<folder>
+		<path>./media</path>
+	</folder>
+</gameList>
+"#;
+
+        assert_eq!(
+            vec![
+                GamelistEntry {
+                    path: "./Secret of Mana (Europe).sfc".to_string(),
+                    name: Some("Secret of Mana".to_string()),
+                    metadata: GameMetadata {
+                        developers: vec!["Square".to_string()],
+                        publishers: vec!["Nintendo".to_string()],
+                        genres: vec!["Action".to_string(), "RPG".to_string()],
+                        releases: vec![GameRelease {
+                            region: "Europe".to_string(),
+                            date: "1993-11-19".to_string(),
+                        }],
+                        players: Some(3),
+                        description: Some("Three heroes.\n\nRescue the Mana tree.".to_string()),
+                    },
+                },
+                GamelistEntry {
+                    path: "./Star Fox (USA).sfc".to_string(),
+                    name: None,
+                    metadata: GameMetadata {
+                        releases: vec![GameRelease {
+                            region: "USA".to_string(),
+                            date: "1993".to_string(),
+                        }],
+                        ..Default::default()
+                    },
+                },
+            ],
+            parse(gamelist).unwrap()
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::{
+    config::AppConfig,
+    exporters::configured_consoles,
+    models::{Console, GameMetadata},
+    routes::{games_routes, metadata_routes},
+};
+
+use self::gamelist::{GamelistEntry, GAMELIST_FILE_NAME};
+
+pub mod gamelist;
+
+#[derive(Debug, Serialize)]
+pub struct GamelistImportSummary {
+    pub console: String,
+    pub path: PathBuf,
+    /// games which got metadata from the gamelist
+    pub games: usize,
+    /// entries no game of the console matched
+    pub unmatched: Vec<String>,
+}
+
+fn file_stem(path: &str) -> Option<&str> {
+    Path::new(path).file_stem()?.to_str()
+}
+
+/// Titles compared case insensitively and without surrounding whitespace
+fn normalise_title(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// Matches the gamelist's entries to the console's games by their rom files, or by the game's
+/// title for entries like `.m3u` playlists, and stores what the gamelist knows about them.
+/// Values the gamelist doesn't have are kept.
+pub fn import_gamelist(
+    conn: &mut SqliteConnection,
+    console: &Console,
+    entries: Vec<GamelistEntry>,
+    path: &Path,
+) -> io::Result<GamelistImportSummary> {
+    let games = games_routes::get_games_for_console(conn, &console.id);
+
+    // the first game of each rom file stem and title, looked up for every entry
+    let mut by_stem: HashMap<&str, i32> = HashMap::new();
+    let mut by_title: HashMap<String, i32> = HashMap::new();
+    for game_roms in &games {
+        for rom in &game_roms.roms {
+            if let Some(stem) = file_stem(&rom.title) {
+                by_stem.entry(stem).or_insert(game_roms.game.id);
+            }
+        }
+        by_title
+            .entry(normalise_title(&game_roms.game.title))
+            .or_insert(game_roms.game.id);
+    }
+
+    let mut metadata: HashMap<i32, GameMetadata> = HashMap::new();
+    let mut unmatched = Vec::new();
+
+    for entry in entries {
+        let game = file_stem(&entry.path)
+            .and_then(|stem| by_stem.get(stem))
+            .or_else(|| {
+                entry
+                    .name
+                    .as_deref()
+                    .and_then(|name| by_title.get(&normalise_title(name)))
+            });
+
+        match game {
+            Some(game_id) => metadata.entry(*game_id).or_default().merge(entry.metadata),
+            None => unmatched.push(entry.path),
+        }
+    }
+
+    for game_roms in &games {
+        if let Some(game_metadata) = metadata.get_mut(&game_roms.game.id) {
+            game_metadata.merge(game_roms.metadata.clone());
+            metadata_routes::set_metadata(conn, game_roms.game.id, game_metadata)
+                .map_err(io::Error::other)?;
+        }
+    }
+
+    Ok(GamelistImportSummary {
+        console: console.abbreviation.clone(),
+        path: path.to_path_buf(),
+        games: metadata.len(),
+        unmatched,
+    })
+}
+
+/// Imports the `gamelist.xml` of every console with a configured rom path which has one
+pub fn import_all(
+    conn: &mut SqliteConnection,
+    config: &AppConfig,
+) -> io::Result<Vec<GamelistImportSummary>> {
+    configured_consoles(conn, config)
+        .into_iter()
+        .map(|(console, rom_dir)| (console, rom_dir.join(GAMELIST_FILE_NAME)))
+        .filter(|(_, path)| path.is_file())
+        .map(|(console, path)| {
+            let entries = gamelist::read_file(&path)?;
+            import_gamelist(conn, &console, entries, &path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use crate::{
+        db::{create_pool, prepare},
+        models::GameRelease,
+        routes::console_routes::get_console_by_name,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_import_gamelist() {
+        let pool = create_pool(":memory:").unwrap();
+        let conn = &mut pool.get().unwrap();
+        prepare(conn, None).unwrap();
+        let console = get_console_by_name(conn, "Super Nintendo Entertainment System")
+            .unwrap()
+            .unwrap();
+
+        conn.batch_execute(&format!(
+            "INSERT INTO games (id, title, console_id) VALUES (1, 'Secret of Mana', {0});
+             INSERT INTO roms (title, md5, size, game_id) VALUES
+                 ('Secret of Mana (Europe).sfc', '', 0, 1), ('Secret of Mana (USA).sfc', '', 0, 1);
+             INSERT INTO games (id, title, console_id) VALUES (2, 'Super Metroid', {0});
+             INSERT INTO roms (title, md5, size, game_id) VALUES
+                 ('Super Metroid (Japan, USA).sfc', '', 0, 2);",
+            console.id
+        ))
+        .unwrap();
+        metadata_routes::set_metadata(
+            conn,
+            1,
+            &GameMetadata {
+                players: Some(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let entries = gamelist::parse(
+            r#"<gameList>
+	<game>
+		<path>./Secret of Mana (Europe).zip</path>
+		<releasedate>19931119T000000</releasedate>
+		<developer>Square</developer>
+	</game>
+	<game>
+		<path>./Secret of Mana (USA).sfc</path>
+		<releasedate>19931003T000000</releasedate>
+		<developer>Square</developer>
+		<genre>Action, RPG</genre>
+	</game>
+	<game>
+		<path>./Star Fox (USA).sfc</path>
+	</game>
+	<game>
+		<path>./Super Metroid.m3u</path>
+		<name>SUPER METROID</name>
+		<players>1</players>
+	</game>
+</gameList>"#,
+        )
+        .unwrap();
+
+        let summary =
+            import_gamelist(conn, &console, entries, Path::new(GAMELIST_FILE_NAME)).unwrap();
+        assert_eq!(2, summary.games);
+        assert_eq!(vec!["./Star Fox (USA).sfc".to_string()], summary.unmatched);
+
+        let games = games_routes::get_games_for_console(conn, &console.id);
+        assert_eq!(
+            GameMetadata {
+                developers: vec!["Square".to_string()],
+                genres: vec!["Action".to_string(), "RPG".to_string()],
+                releases: vec![
+                    GameRelease {
+                        region: "USA".to_string(),
+                        date: "1993-10-03".to_string(),
+                    },
+                    GameRelease {
+                        region: "Europe".to_string(),
+                        date: "1993-11-19".to_string(),
+                    },
+                ],
+                players: Some(3),
+                ..Default::default()
+            },
+            games[0].metadata
+        );
+        // matched by its title
+        assert_eq!(Some(1), games[1].metadata.players);
+    }
+}
@@ -0,0 +1,53 @@
+use ::diesel::prelude::*;
+use serde::Serialize;
+
+use crate::{hashing::FileHashes, models::Console, schemas::bios_files::*};
+
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Console))]
+#[diesel(table_name = bios_files)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BiosFile {
+    pub id: i32,
+    pub console_id: i32,
+    /// path relative to the emulator's system folder, e.g. `dc/dc_boot.bin`
+    pub name: String,
+    pub size: i64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
+    pub required: bool,
+}
+
+impl BiosFile {
+    /// Compares size and every hash the catalogue knows for this file. An entry without any
+    /// hash matches nothing.
+    pub fn matches(&self, hashes: &FileHashes) -> bool {
+        let listed: Vec<(&str, &str)> = [
+            (self.crc.as_str(), hashes.crc32.as_str()),
+            (self.md5.as_str(), hashes.md5.as_str()),
+            (self.sha1.as_str(), hashes.sha1.as_str()),
+        ]
+        .into_iter()
+        .filter(|(expected, _)| !expected.is_empty())
+        .collect();
+
+        (self.size == 0 || self.size as u64 == hashes.size)
+            && !listed.is_empty()
+            && listed
+                .iter()
+                .all(|(expected, actual)| expected.eq_ignore_ascii_case(actual))
+    }
+}
+
+#[derive(Insertable, Debug, PartialEq)]
+#[diesel(table_name = bios_files)]
+pub struct NewBiosFile {
+    pub console_id: i32,
+    pub name: String,
+    pub size: i64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
+    pub required: bool,
+}
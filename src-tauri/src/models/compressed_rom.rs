@@ -0,0 +1,49 @@
+use ::diesel::prelude::*;
+use serde::Serialize;
+
+use crate::{hashing::FileHashes, models::Rom, schemas::compressed_roms::*};
+
+/// A CSO or ZSO file of a rom, with the hashes of the original image it decompresses to
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Rom))]
+#[diesel(table_name = compressed_roms)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CompressedRom {
+    pub id: i32,
+    pub rom_id: i32,
+    pub path: String,
+    pub format: String,
+    pub block_size: i32,
+    /// size of the compressed file, to notice replaced files without decompressing them
+    pub compressed_size: i64,
+    pub size: i64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+impl CompressedRom {
+    /// The recorded hashes of the original image
+    pub fn hashes(&self) -> FileHashes {
+        FileHashes {
+            size: self.size as u64,
+            crc32: self.crc.clone(),
+            md5: self.md5.clone(),
+            sha1: self.sha1.clone(),
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = compressed_roms)]
+pub struct NewCompressedRom<'a> {
+    pub rom_id: i32,
+    pub path: &'a str,
+    pub format: &'a str,
+    pub block_size: i32,
+    pub compressed_size: i64,
+    pub size: i64,
+    pub crc: &'a str,
+    pub md5: &'a str,
+    pub sha1: &'a str,
+}
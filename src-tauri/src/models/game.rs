@@ -7,10 +7,12 @@ use ::diesel::prelude::*;
 use serde::Serialize;
 
 use crate::dat_parser::parser::DatGame;
-use crate::models::{Console, Rom};
+use crate::models::{Console, GameMetadata, Rom};
 use crate::schemas::games::*;
 
-#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[derive(
+    Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone, Default,
+)]
 #[diesel(belongs_to(Console))]
 #[diesel(table_name = games)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -18,6 +20,12 @@ pub struct Game {
     pub id: i32,
     pub title: String,
     pub console_id: i32,
+    /// the DAT's serials of all releases, comma separated
+    pub serial: String,
+    /// title of the parent game for clones with a different title
+    pub clone_of: Option<String>,
+    /// removed with its DAT or console, but kept for the files or roms referring to it
+    pub removed: bool,
 }
 
 #[derive(Insertable, Debug)]
@@ -26,6 +34,8 @@ pub struct Game {
 pub struct NewGame<'a> {
     pub title: &'a str,
     pub console_id: i32,
+    pub serial: String,
+    pub clone_of: Option<&'a str>,
 }
 
 impl<'a> NewGame<'a> {
@@ -33,6 +43,8 @@ impl<'a> NewGame<'a> {
         NewGame {
             title: &dat_game.name,
             console_id: console_db_id.unwrap_or(0),
+            serial: dat_game.serials.join(", "),
+            clone_of: dat_game.clone_of.as_deref(),
         }
     }
 }
@@ -42,4 +54,6 @@ pub struct GameWithRoms {
     #[serde(flatten)]
     pub game: Game,
     pub roms: Vec<Rom>,
+    #[serde(flatten)]
+    pub metadata: GameMetadata,
 }
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// What a metadata source like a scraped gamelist knows about a game
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct GameMetadata {
+    pub developers: Vec<String>,
+    pub publishers: Vec<String>,
+    pub genres: Vec<String>,
+    pub releases: Vec<GameRelease>,
+    /// maximum number of players
+    pub players: Option<i32>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GameRelease {
+    /// region as named in rom names, e.g. `Europe`, empty if unknown
+    pub region: String,
+    /// `YYYY-MM-DD`, or just `YYYY-MM` or `YYYY` if the day isn't known
+    pub date: String,
+}
+
+impl GameMetadata {
+    /// Adds what the other source knows and this one doesn't
+    pub fn merge(&mut self, other: GameMetadata) {
+        for (names, other_names) in [
+            (&mut self.developers, other.developers),
+            (&mut self.publishers, other.publishers),
+            (&mut self.genres, other.genres),
+        ] {
+            for name in other_names {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        for release in other.releases {
+            if !self
+                .releases
+                .iter()
+                .any(|existing| existing.region == release.region)
+            {
+                self.releases.push(release);
+            }
+        }
+
+        self.players = self.players.or(other.players);
+        self.description = self.description.take().or(other.description);
+    }
+
+    /// The earliest release date
+    pub fn first_release(&self) -> Option<&str> {
+        self.releases
+            .iter()
+            .map(|release| release.date.as_str())
+            .min()
+    }
+}
@@ -1,13 +1,25 @@
+pub mod bios_file;
+pub mod compressed_rom;
 pub mod console;
 pub mod developer;
 pub mod game;
+pub mod game_metadata;
+pub mod patch;
 pub mod region;
 pub mod rom;
 pub mod rom_region;
+pub mod rom_track;
+pub mod save;
 
+pub use bios_file::*;
+pub use compressed_rom::*;
 pub use console::*;
 pub use developer::*;
 pub use game::*;
+pub use game_metadata::*;
+pub use patch::*;
 pub use region::*;
 pub use rom::*;
 pub use rom_region::*;
+pub use rom_track::*;
+pub use save::*;
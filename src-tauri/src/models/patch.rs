@@ -0,0 +1,65 @@
+use ::diesel::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    models::Rom,
+    schemas::{patched_roms::*, patches::*},
+};
+
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Rom))]
+#[diesel(table_name = patches)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Patch {
+    pub id: i32,
+    /// the rom the patch applies to
+    pub rom_id: i32,
+    pub path: String,
+    pub format: String,
+    pub md5: String,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = patches)]
+pub struct NewPatch<'a> {
+    pub rom_id: i32,
+    pub path: &'a str,
+    pub format: &'a str,
+    pub md5: &'a str,
+}
+
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Rom))]
+#[diesel(belongs_to(Patch))]
+#[diesel(table_name = patched_roms)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PatchedRom {
+    pub id: i32,
+    /// the base rom the patch was applied to
+    pub rom_id: i32,
+    pub patch_id: i32,
+    pub path: String,
+    pub size: i64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = patched_roms)]
+pub struct NewPatchedRom<'a> {
+    pub rom_id: i32,
+    pub patch_id: i32,
+    pub path: &'a str,
+    pub size: i64,
+    pub crc: &'a str,
+    pub md5: &'a str,
+    pub sha1: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PatchWithPatchedRoms {
+    #[serde(flatten)]
+    pub patch: Patch,
+    pub patched_roms: Vec<PatchedRom>,
+}
@@ -12,7 +12,9 @@ use crate::{
     schemas::roms::*,
 };
 
-#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[derive(
+    Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone, Default,
+)]
 #[diesel(belongs_to(Game))]
 #[diesel(table_name = roms)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -20,8 +22,15 @@ pub struct Rom {
     pub id: i32,
     pub title: String,
     pub md5: String,
-    pub size: i32,
+    pub size: i64,
     pub game_id: i32,
+    pub disc: Option<i32>,
+    pub crc: String,
+    pub sha1: String,
+    /// the DAT's category of the entry, e.g. `Games` or `Demos`
+    pub category: String,
+    /// removed with its DAT or console, but kept for the files referring to it
+    pub removed: bool,
 }
 
 #[derive(Serialize, Debug)]
@@ -36,8 +45,12 @@ pub struct RomWithRegion {
 pub struct NewRom<'a> {
     pub title: &'a str,
     pub md5: &'a str,
-    pub size: &'a i32,
+    pub size: i64,
     pub game_id: &'a i32,
+    pub disc: Option<i32>,
+    pub crc: &'a str,
+    pub sha1: &'a str,
+    pub category: &'a str,
 }
 
 impl<'a> NewRom<'a> {
@@ -45,8 +58,12 @@ impl<'a> NewRom<'a> {
         NewRom {
             title: &dat_rom.name,
             md5: &dat_rom.md5,
-            size: &0,
+            size: dat_rom.size as i64,
             game_id: &game_db_id,
+            disc: dat_rom.disc,
+            crc: &dat_rom.crc,
+            sha1: &dat_rom.sha1,
+            category: &dat_rom.category,
         }
     }
 }
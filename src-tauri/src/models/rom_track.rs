@@ -0,0 +1,66 @@
+use ::diesel::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    dat_parser::parser::DatTrack, hashing::FileHashes, models::Rom, schemas::rom_tracks::*,
+};
+
+/// A track file of a disc rom, whose `Rom` is the `.cue` or `.gdi` sheet referencing it
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Rom))]
+#[diesel(table_name = rom_tracks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RomTrack {
+    pub id: i32,
+    pub rom_id: i32,
+    pub name: String,
+    pub size: i64,
+    pub crc: String,
+    pub md5: String,
+    pub sha1: String,
+}
+
+impl RomTrack {
+    /// Compares size and every hash the DAT lists for this track
+    pub fn matches(&self, hashes: &FileHashes) -> bool {
+        let hash_matches = |expected: &str, actual: &str| {
+            expected.is_empty() || expected.eq_ignore_ascii_case(actual)
+        };
+
+        (self.size == 0 || self.size as u64 == hashes.size)
+            && hash_matches(&self.crc, &hashes.crc32)
+            && hash_matches(&self.md5, &hashes.md5)
+            && hash_matches(&self.sha1, &hashes.sha1)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = rom_tracks)]
+pub struct NewRomTrack<'a> {
+    pub rom_id: i32,
+    pub name: &'a str,
+    pub size: i64,
+    pub crc: &'a str,
+    pub md5: &'a str,
+    pub sha1: &'a str,
+}
+
+impl<'a> NewRomTrack<'a> {
+    pub fn from_dat(dat_track: &'a DatTrack, rom_db_id: i32) -> Self {
+        NewRomTrack {
+            rom_id: rom_db_id,
+            name: &dat_track.name,
+            size: dat_track.size as i64,
+            crc: &dat_track.crc,
+            md5: &dat_track.md5,
+            sha1: &dat_track.sha1,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RomWithTracks {
+    #[serde(flatten)]
+    pub rom: Rom,
+    pub tracks: Vec<RomTrack>,
+}
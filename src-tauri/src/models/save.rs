@@ -0,0 +1,64 @@
+use ::diesel::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    models::{Game, Rom},
+    schemas::{save_backups::*, saves::*},
+};
+
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Rom))]
+#[diesel(belongs_to(Game))]
+#[diesel(table_name = saves)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Save {
+    pub id: i32,
+    pub path: String,
+    pub kind: String,
+    pub md5: String,
+    pub size: i64,
+    pub modified_at: i64,
+    pub rom_id: Option<i32>,
+    pub game_id: Option<i32>,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = saves)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewSave<'a> {
+    pub path: &'a str,
+    pub kind: &'a str,
+    pub md5: &'a str,
+    pub size: i64,
+    pub modified_at: i64,
+    pub rom_id: Option<i32>,
+    pub game_id: Option<i32>,
+}
+
+#[derive(Queryable, Debug, Selectable, Serialize, Identifiable, Associations, PartialEq, Clone)]
+#[diesel(belongs_to(Save))]
+#[diesel(table_name = save_backups)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SaveBackup {
+    pub id: i32,
+    pub save_id: i32,
+    pub path: String,
+    pub md5: String,
+    pub created_at: i64,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = save_backups)]
+pub struct NewSaveBackup<'a> {
+    pub save_id: i32,
+    pub path: &'a str,
+    pub md5: &'a str,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SaveWithBackups {
+    #[serde(flatten)]
+    pub save: Save,
+    pub backups: Vec<SaveBackup>,
+}
@@ -0,0 +1,351 @@
+use std::io;
+
+use crate::patching::{check_crc, encode_number, invalid_patch, with_checksums, PatchReader};
+
+pub const MAGIC: &[u8] = b"BPS1";
+
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+/// Shortest copy worth its offset, shorter matches are stored as literal bytes
+const MIN_COPY: usize = 4;
+const HASH_BITS: u32 = 16;
+/// How many earlier positions with the same hash are compared before giving up
+const MAX_CANDIDATES: usize = 32;
+const NONE: u32 = u32::MAX;
+
+/// Hash chains over all 4 byte windows of a buffer, to find copy candidates quickly
+struct MatchIndex {
+    heads: Vec<u32>,
+    previous: Vec<u32>,
+}
+
+impl MatchIndex {
+    fn new(size: usize) -> Self {
+        MatchIndex {
+            heads: vec![NONE; 1 << HASH_BITS],
+            previous: vec![NONE; size],
+        }
+    }
+
+    fn hash(window: &[u8]) -> usize {
+        let value = u32::from_le_bytes(window[..MIN_COPY].try_into().unwrap());
+
+        (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], position: usize) {
+        if position + MIN_COPY > data.len() {
+            return;
+        }
+        let hash = Self::hash(&data[position..]);
+
+        self.previous[position] = self.heads[hash];
+        self.heads[hash] = position as u32;
+    }
+
+    /// Longest match for `target[position..]` in `data`, as start and length
+    fn longest_match(&self, data: &[u8], target: &[u8], position: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        if position + MIN_COPY > target.len() {
+            return best;
+        }
+
+        let mut candidate = self.heads[Self::hash(&target[position..])];
+        for _ in 0..MAX_CANDIDATES {
+            if candidate == NONE {
+                break;
+            }
+            let start = candidate as usize;
+            let length = data[start..]
+                .iter()
+                .zip(&target[position..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.1 {
+                best = (start, length);
+            }
+            candidate = self.previous[start];
+        }
+
+        best
+    }
+}
+
+/// Encodes how far a copy moves its relative offset, sign in the lowest bit
+fn encode_offset(from: usize, to: usize) -> Vec<u8> {
+    if to >= from {
+        encode_number((to - from) << 1)
+    } else {
+        encode_number((from - to) << 1 | 1)
+    }
+}
+
+fn push_action(patch: &mut Vec<u8>, kind: usize, length: usize) {
+    patch.extend(encode_number((length - 1) << 2 | kind));
+}
+
+/// Creates a BPS delta patch turning `source` into `target`, using reads of unchanged source
+/// bytes and copies from anywhere in source and target, so moved data stays small
+pub fn create(source: &[u8], target: &[u8], metadata: &str) -> Vec<u8> {
+    let mut patch = MAGIC.to_vec();
+    patch.extend(encode_number(source.len()));
+    patch.extend(encode_number(target.len()));
+    patch.extend(encode_number(metadata.len()));
+    patch.extend_from_slice(metadata.as_bytes());
+
+    let mut source_index = MatchIndex::new(source.len());
+    for position in 0..source.len() {
+        source_index.insert(source, position);
+    }
+    let mut target_index = MatchIndex::new(target.len());
+
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    let mut literal_start = 0;
+    let mut position = 0;
+
+    while position < target.len() {
+        let source_read = source
+            .get(position..)
+            .unwrap_or_default()
+            .iter()
+            .zip(&target[position..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        let source_copy = source_index.longest_match(source, target, position);
+        let target_copy = target_index.longest_match(target, target, position);
+
+        let (kind, start, length) = if source_read >= MIN_COPY
+            || (source_read > 0 && source_read >= source_copy.1 && source_read >= target_copy.1)
+        {
+            (SOURCE_READ, position, source_read)
+        } else if source_copy.1 >= MIN_COPY && source_copy.1 >= target_copy.1 {
+            (SOURCE_COPY, source_copy.0, source_copy.1)
+        } else if target_copy.1 >= MIN_COPY {
+            (TARGET_COPY, target_copy.0, target_copy.1)
+        } else {
+            target_index.insert(target, position);
+            position += 1;
+            continue;
+        };
+
+        if literal_start < position {
+            push_action(&mut patch, TARGET_READ, position - literal_start);
+            patch.extend_from_slice(&target[literal_start..position]);
+        }
+
+        push_action(&mut patch, kind, length);
+        match kind {
+            SOURCE_COPY => {
+                patch.extend(encode_offset(source_offset, start));
+                source_offset = start + length;
+            }
+            TARGET_COPY => {
+                patch.extend(encode_offset(target_offset, start));
+                target_offset = start + length;
+            }
+            _ => {}
+        }
+
+        for inserted in position..position + length {
+            target_index.insert(target, inserted);
+        }
+        position += length;
+        literal_start = position;
+    }
+
+    if literal_start < target.len() {
+        push_action(&mut patch, TARGET_READ, target.len() - literal_start);
+        patch.extend_from_slice(&target[literal_start..]);
+    }
+
+    with_checksums(patch, source, target)
+}
+
+/// Reads the signed offsets of the copy actions, sign in the lowest bit
+fn relative_offset(reader: &mut PatchReader, offset: &mut usize) -> io::Result<()> {
+    let data = reader.number()?;
+    let distance = data >> 1;
+
+    *offset = if data & 1 == 1 {
+        offset.checked_sub(distance)
+    } else {
+        offset.checked_add(distance)
+    }
+    .ok_or_else(|| invalid_patch("BPS copy offset out of range"))?;
+
+    Ok(())
+}
+
+/// Applies a BPS patch, validating the source, target and patch checksums
+pub fn apply(source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let (body, checksums) = patch
+        .strip_prefix(MAGIC)
+        .filter(|body| body.len() >= 12)
+        .map(|body| body.split_at(body.len() - 12))
+        .ok_or_else(|| invalid_patch("missing BPS header"))?;
+    let checksum =
+        |index: usize| u32::from_le_bytes(checksums[index * 4..index * 4 + 4].try_into().unwrap());
+
+    check_crc("patch", &patch[..patch.len() - 4], checksum(2))?;
+    check_crc("source", source, checksum(0))?;
+
+    let mut reader = PatchReader::new(body);
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    if source_size != source.len() {
+        return Err(invalid_patch("source size does not match the BPS patch"));
+    }
+
+    // the header's size is only trusted as far as the patch could produce it
+    let mut target = Vec::with_capacity(target_size.min(source.len() + patch.len()));
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+
+    while !reader.rest().is_empty() {
+        let data = reader.number()?;
+        let length = (data >> 2) + 1;
+        if target
+            .len()
+            .checked_add(length)
+            .is_none_or(|size| size > target_size)
+        {
+            return Err(invalid_patch("BPS patch writes past the target size"));
+        }
+
+        match data & 3 {
+            // SourceRead
+            0 => target.extend_from_slice(
+                source
+                    .get(target.len()..target.len() + length)
+                    .ok_or_else(|| invalid_patch("BPS source read out of range"))?,
+            ),
+            // TargetRead
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            // SourceCopy
+            2 => {
+                relative_offset(&mut reader, &mut source_offset)?;
+                target.extend_from_slice(
+                    source
+                        .get(source_offset..source_offset + length)
+                        .ok_or_else(|| invalid_patch("BPS source copy out of range"))?,
+                );
+                source_offset += length;
+            }
+            // TargetCopy, byte by byte as source and destination may overlap
+            _ => {
+                relative_offset(&mut reader, &mut target_offset)?;
+                for _ in 0..length {
+                    let byte = *target
+                        .get(target_offset)
+                        .ok_or_else(|| invalid_patch("BPS target copy out of range"))?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(invalid_patch("target size does not match the BPS patch"));
+    }
+    check_crc("target", &target, checksum(1))?;
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::patching::{encode_number, with_checksums};
+
+    use super::*;
+
+    fn patch(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target.len()));
+        patch.extend(encode_number(0));
+        patch.extend_from_slice(actions);
+
+        with_checksums(patch, source, target)
+    }
+
+    #[test]
+    fn test_apply() {
+        let source = b"hello world";
+        let target = b"hello wonderful world world";
+
+        let actions = [
+            // SourceRead "hello "
+            encode_number((6 - 1) << 2),
+            // TargetRead "wonderful "
+            encode_number((10 - 1) << 2 | 1),
+            b"wonderful ".to_vec(),
+            // SourceCopy "world", 6 bytes forward in the source
+            encode_number((5 - 1) << 2 | 2),
+            encode_number(6 << 1),
+            // TargetCopy " world", 15 bytes forward in the target
+            encode_number((6 - 1) << 2 | 3),
+            encode_number(15 << 1),
+        ]
+        .concat();
+
+        let source = &source[..];
+        let patch = patch(source, target, &actions);
+
+        assert_eq!(target.to_vec(), apply(source, &patch).unwrap());
+    }
+
+    #[test]
+    fn test_create_round_trip() {
+        let source: Vec<u8> = (0..20000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+        let mut target = source.clone();
+        target[100..110].copy_from_slice(b"translated");
+        target.splice(5000..5000, b"inserted text".iter().copied());
+        target.drain(12000..12500);
+        target.extend_from_slice(&source[..3000]);
+        target.extend_from_slice(&[0; 64]);
+
+        let patch = create(&source, &target, "<patch></patch>");
+
+        assert_eq!(target, apply(&source, &patch).unwrap());
+        assert!(patch.len() < 200, "patch is {} bytes", patch.len());
+    }
+
+    #[test]
+    fn test_create_from_empty_source() {
+        let patch = create(b"", b"new file", "");
+
+        assert_eq!(b"new file".to_vec(), apply(b"", &patch).unwrap());
+    }
+
+    #[test]
+    fn test_apply_rejects_wrong_source() {
+        let actions = [encode_number((2 - 1) << 2 | 1), b"ab".to_vec()].concat();
+        let patch = patch(b"source", b"ab", &actions);
+
+        assert_eq!(b"ab".to_vec(), apply(b"source", &patch).unwrap());
+        assert!(apply(b"SOURCE", &patch).is_err());
+    }
+
+    #[test]
+    fn test_apply_rejects_forged_target_size() {
+        // a huge target size in the header must not be allocated up front
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(0));
+        patch.extend(encode_number(usize::MAX >> 8));
+        patch.extend(encode_number(0));
+        patch.extend(encode_number(1));
+        patch.push(b'a');
+        let patch = with_checksums(patch, b"", b"a");
+
+        assert!(apply(b"", &patch).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::io;
+
+use crate::patching::{invalid_patch, PatchReader};
+
+pub const MAGIC: &[u8] = b"PATCH";
+const EOF: &[u8] = b"EOF";
+/// IPS offsets are 24 bit, so patched files can't be larger than 16 MiB
+pub const MAX_SIZE: usize = 1 << 24;
+const MAX_RECORD: usize = 0xffff;
+/// Offset which would read as the `EOF` marker
+const EOF_OFFSET: usize = 0x454f46;
+
+fn big_endian(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |number, byte| number << 8 | *byte as usize)
+}
+
+/// Applies an IPS patch, including RLE records and the optional truncation size after `EOF`
+pub fn apply(source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = PatchReader::new(
+        patch
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid_patch("missing IPS header"))?,
+    );
+    let mut target = source.to_vec();
+
+    loop {
+        let rest = reader.rest();
+        if let Some(rest) = rest.strip_prefix(EOF)
+            && (rest.is_empty() || rest.len() == 3)
+        {
+            if !rest.is_empty() {
+                target.truncate(big_endian(rest));
+            }
+            return Ok(target);
+        }
+
+        let offset = big_endian(reader.bytes(3)?);
+        let size = big_endian(reader.bytes(2)?);
+
+        let data = if size == 0 {
+            let count = big_endian(reader.bytes(2)?);
+            vec![reader.byte()?; count]
+        } else {
+            reader.bytes(size)?.to_vec()
+        };
+
+        if target.len() < offset + data.len() {
+            target.resize(offset + data.len(), 0);
+        }
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+}
+
+/// Creates an IPS patch, `None` if the target is too large for the format's 24 bit offsets
+pub fn create(source: &[u8], target: &[u8]) -> Option<Vec<u8>> {
+    let truncates = target.len() < source.len();
+    if target.len() > MAX_SIZE || (truncates && target.len() == MAX_SIZE) {
+        return None;
+    }
+
+    // a grown target needs its last byte written, even if it's 0
+    let must_write = |position: usize| {
+        source.get(position) != Some(&target[position])
+            || (position + 1 == target.len() && target.len() > source.len())
+    };
+    let mut patch = MAGIC.to_vec();
+    let mut position = 0;
+
+    while position < target.len() {
+        if !must_write(position) {
+            position += 1;
+            continue;
+        }
+
+        let mut start = position;
+        if start == EOF_OFFSET {
+            start -= 1;
+        }
+        let mut end = position + 1;
+        while end < target.len() && end - start < MAX_RECORD && must_write(end) {
+            end += 1;
+        }
+
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&target[start..end]);
+        position = end;
+    }
+
+    patch.extend_from_slice(EOF);
+    if truncates {
+        patch.extend_from_slice(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+
+    Some(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let patch = [
+            b"PATCH".as_slice(),
+            // "abc" at offset 2
+            &[0, 0, 2, 0, 3],
+            b"abc",
+            // RLE record, four times 'z' at offset 8, growing the file
+            &[0, 0, 8, 0, 0, 0, 4, b'z'],
+            b"EOF",
+        ]
+        .concat();
+
+        assert_eq!(
+            b"01abc567zzzz".to_vec(),
+            apply(b"0123456789", &patch).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_apply_truncates() {
+        let patch = [b"PATCH".as_slice(), b"EOF", &[0, 0, 4]].concat();
+
+        assert_eq!(b"0123".to_vec(), apply(b"0123456789", &patch).unwrap());
+    }
+
+    #[test]
+    fn test_create_round_trip() {
+        let source: Vec<u8> = (0..100000u32).map(|i| (i % 253) as u8).collect();
+
+        let mut grown = source.clone();
+        grown[10] = 0xff;
+        grown.extend(vec![0; 70000]);
+        let mut shrunk = source[..5000].to_vec();
+        shrunk[4999] = 0;
+
+        for target in [grown, shrunk, source.clone()] {
+            let patch = create(&source, &target).unwrap();
+
+            assert_eq!(target, apply(&source, &patch).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_create_avoids_eof_offset() {
+        let source = vec![0; EOF_OFFSET + 2];
+        let mut target = source.clone();
+        target[EOF_OFFSET] = 1;
+
+        let patch = create(&source, &target).unwrap();
+
+        assert_eq!(&[0x45, 0x4f, 0x45], &patch[5..8]);
+        assert_eq!(target, apply(&source, &patch).unwrap());
+    }
+
+    #[test]
+    fn test_create_rejects_large_files() {
+        assert_eq!(None, create(&[], &vec![0; MAX_SIZE + 1]));
+    }
+
+    #[test]
+    fn test_apply_rejects_broken_patch() {
+        assert!(apply(b"0123", b"PATCH\0\0\x01\0\x05ab").is_err());
+        assert!(apply(b"0123", b"NOT A PATCH").is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AppConfig,
+    hashing::{hash_reader, md5_file},
+    models::{NewPatch, NewPatchedRom, Patch, PatchedRom},
+    routes::patch_routes,
+};
+
+pub mod bps;
+pub mod ips;
+pub mod ups;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Ups,
+}
+
+impl PatchFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "ips",
+            PatchFormat::Bps => "bps",
+            PatchFormat::Ups => "ups",
+        }
+    }
+
+    /// Detects the format by the patch's magic bytes
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(ips::MAGIC) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(bps::MAGIC) {
+            Some(PatchFormat::Bps)
+        } else if patch.starts_with(ups::MAGIC) {
+            Some(PatchFormat::Ups)
+        } else {
+            None
+        }
+    }
+
+    pub fn apply(&self, source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            PatchFormat::Ips => ips::apply(source, patch),
+            PatchFormat::Bps => bps::apply(source, patch),
+            PatchFormat::Ups => ups::apply(source, patch),
+        }
+    }
+}
+
+/// Information stored in created BPS patches
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PatchMetadata {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+}
+
+impl PatchMetadata {
+    /// Renders the metadata as the XML document beat suggests for BPS, empty without metadata
+    pub fn render(&self) -> String {
+        let fields = [
+            ("title", &self.title),
+            ("author", &self.author),
+            ("description", &self.description),
+        ];
+        if fields.iter().all(|(_, value)| value.is_empty()) {
+            return String::new();
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<patch>\n");
+        for (name, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+            xml.push_str(&format!(
+                "  <{name}>{}</{name}>\n",
+                html_escape::encode_text(value)
+            ));
+        }
+        xml.push_str("</patch>\n");
+
+        xml
+    }
+}
+
+fn invalid_patch(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn check_crc(name: &str, data: &[u8], expected: u32) -> io::Result<()> {
+    if crc32fast::hash(data) != expected {
+        return Err(invalid_patch(&format!(
+            "{} checksum does not match the patch",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Encodes a number the way BPS and UPS store them
+fn encode_number(mut number: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    loop {
+        let byte = (number & 0x7f) as u8;
+        number >>= 7;
+        if number == 0 {
+            bytes.push(byte | 0x80);
+            return bytes;
+        }
+        bytes.push(byte);
+        number -= 1;
+    }
+}
+
+/// Appends the source, target and patch crc32 footer of BPS and UPS patches
+fn with_checksums(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend(crc32fast::hash(source).to_le_bytes());
+    patch.extend(crc32fast::hash(target).to_le_bytes());
+    patch.extend(crc32fast::hash(&patch).to_le_bytes());
+
+    patch
+}
+
+/// Cursor over the patch data, reading the variable length numbers BPS and UPS use
+struct PatchReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        PatchReader { data }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(invalid_patch("patch ends unexpectedly"));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+
+        loop {
+            let byte = self.byte()?;
+            number = (byte as usize & 0x7f)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or_else(|| invalid_patch("number in patch is too large"))?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            // bits shifted out would wrap the shift around to 0
+            if shift.leading_zeros() < 7 {
+                return Err(invalid_patch("number in patch is too large"));
+            }
+            shift <<= 7;
+            number = number
+                .checked_add(shift)
+                .ok_or_else(|| invalid_patch("number in patch is too large"))?;
+        }
+    }
+}
+
+/// Applies the patch to the rom's file from the library, writes the result and registers it
+/// as a patched rom of the base rom. The output defaults to the patch name with the rom's
+/// extension next to the rom.
+pub fn apply_to_rom(
+    conn: &mut SqliteConnection,
+    rom_id: i32,
+    patch_path: &Path,
+    output: Option<PathBuf>,
+    config: &AppConfig,
+) -> io::Result<PatchedRom> {
+    let (rom, console) =
+        patch_routes::get_rom_with_console(conn, rom_id).map_err(io::Error::other)?;
+    let rom_path = config.rom_dir(&console)?.join(&rom.title);
+
+    let source = fs::read(&rom_path)?;
+    let source_md5 = hash_reader(&mut source.as_slice())?.md5;
+    if !rom.md5.is_empty() && !rom.md5.eq_ignore_ascii_case(&source_md5) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} does not match the DAT's md5", rom_path.display()),
+        ));
+    }
+
+    let patch = fs::read(patch_path)?;
+    let format = PatchFormat::detect(&patch).ok_or_else(|| {
+        invalid_patch(&format!(
+            "unsupported patch format: {}",
+            patch_path.display()
+        ))
+    })?;
+    let patched = format.apply(&source, &patch)?;
+
+    let output = output.unwrap_or_else(|| {
+        let mut file_name = patch_path.file_stem().unwrap_or_default().to_os_string();
+        if let Some(extension) = rom_path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        rom_path.with_file_name(file_name)
+    });
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+    fs::write(&output, &patched)?;
+
+    let patch = patch_routes::upsert_patch(
+        conn,
+        &NewPatch {
+            rom_id,
+            path: &patch_path.to_string_lossy(),
+            format: format.extension(),
+            md5: &md5_file(patch_path)?,
+        },
+    )
+    .map_err(io::Error::other)?;
+
+    let hashes = hash_reader(&mut patched.as_slice())?;
+    patch_routes::upsert_patched_rom(
+        conn,
+        &NewPatchedRom {
+            rom_id,
+            patch_id: patch.id,
+            path: &output.to_string_lossy(),
+            size: hashes.size as i64,
+            crc: &hashes.crc32,
+            md5: &hashes.md5,
+            sha1: &hashes.sha1,
+        },
+    )
+    .map_err(io::Error::other)
+}
+
+/// Creates a patch from an original rom of the library to a modified file and registers it,
+/// together with the modified file as its patched rom. The output defaults to the modified
+/// file's path with the patch extension.
+pub fn create_for_rom(
+    conn: &mut SqliteConnection,
+    original: &Path,
+    modified: &Path,
+    format: PatchFormat,
+    metadata: &PatchMetadata,
+    output: Option<PathBuf>,
+) -> io::Result<Patch> {
+    let source = fs::read(original)?;
+    let source_md5 = hash_reader(&mut source.as_slice())?.md5;
+    let rom = patch_routes::find_rom_by_md5(conn, &source_md5)
+        .map_err(io::Error::other)?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} does not match any rom in the library",
+                    original.display()
+                ),
+            )
+        })?;
+
+    let target = fs::read(modified)?;
+    let patch = match format {
+        PatchFormat::Bps => bps::create(&source, &target, &metadata.render()),
+        PatchFormat::Ips => ips::create(&source, &target).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "modified file is too large for IPS, use BPS instead",
+            )
+        })?,
+        PatchFormat::Ups => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "creating UPS patches is not supported, use BPS instead",
+            ))
+        }
+    };
+
+    let output = output.unwrap_or_else(|| modified.with_extension(format.extension()));
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+    fs::write(&output, &patch)?;
+
+    let patch = patch_routes::upsert_patch(
+        conn,
+        &NewPatch {
+            rom_id: rom.id,
+            path: &output.to_string_lossy(),
+            format: format.extension(),
+            md5: &hash_reader(&mut patch.as_slice())?.md5,
+        },
+    )
+    .map_err(io::Error::other)?;
+
+    let hashes = hash_reader(&mut target.as_slice())?;
+    patch_routes::upsert_patched_rom(
+        conn,
+        &NewPatchedRom {
+            rom_id: rom.id,
+            patch_id: patch.id,
+            path: &modified.to_string_lossy(),
+            size: hashes.size as i64,
+            crc: &hashes.crc32,
+            md5: &hashes.md5,
+            sha1: &hashes.sha1,
+        },
+    )
+    .map_err(io::Error::other)?;
+
+    Ok(patch)
+}
+
+/// Path RetroArch looks for a softpatch at, the rom's path with the patch's extension
+pub fn softpatch_path(rom_path: &Path, format: PatchFormat) -> PathBuf {
+    let mut file_name = rom_path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(format.extension());
+
+    rom_path.with_file_name(file_name)
+}
+
+/// Copies the patch beside its rom with the rom's name, so RetroArch applies it on load
+/// instead of needing a patched copy of the rom
+pub fn export_softpatch(
+    conn: &mut SqliteConnection,
+    patch_id: i32,
+    target_dir: Option<&Path>,
+    config: &AppConfig,
+) -> io::Result<PathBuf> {
+    let patch = patch_routes::get_patch(conn, patch_id).map_err(io::Error::other)?;
+    let (rom, console) =
+        patch_routes::get_rom_with_console(conn, patch.rom_id).map_err(io::Error::other)?;
+
+    let rom_dir = match target_dir {
+        Some(target_dir) => target_dir.to_path_buf(),
+        None => config.rom_dir(&console)?,
+    };
+    let format = PatchFormat::detect(&fs::read(&patch.path)?)
+        .ok_or_else(|| invalid_patch(&format!("unsupported patch format: {}", patch.path)))?;
+
+    let destination = softpatch_path(&rom_dir.join(&rom.title), format);
+    fs::create_dir_all(&rom_dir)?;
+    fs::copy(&patch.path, &destination)?;
+
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_round_trip() {
+        for number in [0, 1, 127, 128, 255, 16511, 16512, 1 << 24, usize::MAX >> 8] {
+            let bytes = encode_number(number);
+
+            assert_eq!(number, PatchReader::new(&bytes).number().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_number_too_large() {
+        for length in [9, 10, 11, 20] {
+            let mut bytes = vec![0x7f; length];
+            bytes.push(0x80);
+
+            assert!(PatchReader::new(&bytes).number().is_err());
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Some(PatchFormat::Ips), PatchFormat::detect(b"PATCH..."));
+        assert_eq!(Some(PatchFormat::Bps), PatchFormat::detect(b"BPS1..."));
+        assert_eq!(Some(PatchFormat::Ups), PatchFormat::detect(b"UPS1..."));
+        assert_eq!(None, PatchFormat::detect(b"\0\0\0\0"));
+    }
+
+    #[test]
+    fn test_metadata_render() {
+        assert_eq!("", PatchMetadata::default().render());
+        assert_eq!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<patch>\n  <title>Mother 3 &amp; more</title>\n</patch>\n",
+            PatchMetadata {
+                title: "Mother 3 & more".to_string(),
+                ..Default::default()
+            }
+            .render()
+        );
+    }
+
+    #[test]
+    fn test_softpatch_path() {
+        assert_eq!(
+            PathBuf::from("/roms/snes/Secret of Mana (USA).bps"),
+            softpatch_path(
+                Path::new("/roms/snes/Secret of Mana (USA).sfc"),
+                PatchFormat::Bps
+            )
+        );
+    }
+}
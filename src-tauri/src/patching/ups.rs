@@ -0,0 +1,79 @@
+use std::io;
+
+use crate::patching::{check_crc, invalid_patch, PatchReader};
+
+pub const MAGIC: &[u8] = b"UPS1";
+
+/// Applies a UPS patch, validating the input, output and patch checksums
+pub fn apply(source: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let (body, checksums) = patch
+        .strip_prefix(MAGIC)
+        .filter(|body| body.len() >= 12)
+        .map(|body| body.split_at(body.len() - 12))
+        .ok_or_else(|| invalid_patch("missing UPS header"))?;
+    let checksum =
+        |index: usize| u32::from_le_bytes(checksums[index * 4..index * 4 + 4].try_into().unwrap());
+
+    check_crc("patch", &patch[..patch.len() - 4], checksum(2))?;
+    check_crc("source", source, checksum(0))?;
+
+    let mut reader = PatchReader::new(body);
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+
+    if source_size != source.len() {
+        return Err(invalid_patch("source size does not match the UPS patch"));
+    }
+
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+    let mut offset = 0;
+
+    while !reader.rest().is_empty() {
+        offset += reader.number()?;
+
+        // XOR hunk, terminated by a zero byte which still counts as a position
+        loop {
+            let byte = reader.byte()?;
+            if offset < target.len() {
+                target[offset] = source.get(offset).copied().unwrap_or(0) ^ byte;
+            }
+            offset += 1;
+
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+
+    check_crc("target", &target, checksum(1))?;
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::patching::{encode_number, with_checksums};
+
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let source = b"hello world";
+        let target = b"jello world!";
+
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target.len()));
+        // 'h' ^ 'j', then the end of the hunk
+        patch.extend(encode_number(0));
+        patch.extend([b'h' ^ b'j', 0]);
+        // skip to the new last byte, the source is read as 0 past its end
+        patch.extend(encode_number(9));
+        patch.extend([b'!', 0]);
+        let patch = with_checksums(patch, source, target);
+
+        assert_eq!(target.to_vec(), apply(source, &patch).unwrap());
+        assert!(apply(b"jello world", &patch).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use diesel::{insert_into, prelude::*, result::Error, upsert::excluded};
+
+use crate::{
+    models::{BiosFile, Console, NewBiosFile},
+    schemas::{bios_files_table, consoles_table},
+};
+
+/// Inserts the catalogue entries, updating sizes and hashes of known ones but keeping
+/// their required flag, as users may have changed it
+pub fn upsert_bios_files(
+    conn: &mut SqliteConnection,
+    files: Vec<NewBiosFile>,
+) -> Result<Vec<BiosFile>, Error> {
+    insert_into(bios_files_table::table)
+        .values(files)
+        .on_conflict((
+            bios_files_table::console_id,
+            bios_files_table::name,
+            bios_files_table::md5,
+        ))
+        .do_update()
+        .set((
+            bios_files_table::size.eq(excluded(bios_files_table::size)),
+            bios_files_table::crc.eq(excluded(bios_files_table::crc)),
+            bios_files_table::sha1.eq(excluded(bios_files_table::sha1)),
+        ))
+        .get_results(conn)
+}
+
+/// Returns every console with at least one catalogued BIOS file, together with its files
+pub fn get_consoles_with_bios_files(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<(Console, Vec<BiosFile>)>, Error> {
+    let consoles = consoles_table::table
+        .filter(diesel::dsl::exists(
+            bios_files_table::table.filter(bios_files_table::console_id.eq(consoles_table::id)),
+        ))
+        .order(consoles_table::name)
+        .select(Console::as_select())
+        .load(conn)?;
+
+    let files = BiosFile::belonging_to(&consoles)
+        .select(BiosFile::as_select())
+        .order(bios_files_table::name)
+        .load(conn)?;
+
+    Ok(files
+        .grouped_by(&consoles)
+        .into_iter()
+        .zip(consoles)
+        .map(|(files, console)| (console, files))
+        .collect())
+}
+
+pub fn set_bios_required(
+    conn: &mut SqliteConnection,
+    bios_file_id: i32,
+    required: bool,
+) -> Result<BiosFile, Error> {
+    diesel::update(bios_files_table::table.find(bios_file_id))
+        .set(bios_files_table::required.eq(required))
+        .get_result(conn)
+}
@@ -0,0 +1,54 @@
+use diesel::{insert_into, prelude::*, result::Error};
+
+use crate::{
+    models::{CompressedRom, NewCompressedRom, Rom},
+    schemas::{compressed_roms_table, games_table, roms_table},
+};
+
+pub fn upsert_compressed_rom(
+    conn: &mut SqliteConnection,
+    new_compressed_rom: &NewCompressedRom,
+) -> Result<CompressedRom, Error> {
+    insert_into(compressed_roms_table::table)
+        .values(new_compressed_rom)
+        .on_conflict(compressed_roms_table::path)
+        .do_update()
+        .set(new_compressed_rom)
+        .get_result(conn)
+}
+
+pub fn get_compressed_rom(
+    conn: &mut SqliteConnection,
+    compressed_rom_id: i32,
+) -> Result<CompressedRom, Error> {
+    compressed_roms_table::table
+        .find(compressed_rom_id)
+        .select(CompressedRom::as_select())
+        .first(conn)
+}
+
+/// Returns all roms of the console with their registered compressed files
+pub fn get_roms_with_compressed_roms(
+    conn: &mut SqliteConnection,
+    console_id: i32,
+) -> Result<Vec<(Rom, Vec<CompressedRom>)>, Error> {
+    let roms = roms_table::table
+        .inner_join(games_table::table)
+        .filter(games_table::console_id.eq(console_id))
+        .filter(roms_table::removed.eq(false))
+        .order(roms_table::title)
+        .select(Rom::as_select())
+        .load(conn)?;
+
+    let compressed_roms = CompressedRom::belonging_to(&roms)
+        .select(CompressedRom::as_select())
+        .order(compressed_roms_table::path)
+        .load(conn)?;
+
+    Ok(compressed_roms
+        .grouped_by(&roms)
+        .into_iter()
+        .zip(roms)
+        .map(|(compressed_roms, rom)| (rom, compressed_roms))
+        .collect())
+}
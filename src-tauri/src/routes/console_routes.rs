@@ -1,34 +1,35 @@
 use diesel::{prelude::*, result::Error};
 
 use crate::{
-    establish_connection,
-    models::{Console, ConsoleWithGameRoms, ConsoleWithGames, Game, GameWithRoms, Rom},
-    schemas::{consoles::name, consoles_table},
+    models::{
+        Console, ConsoleWithGameRoms, ConsoleWithGames, Game, GameMetadata, GameWithRoms, Rom,
+    },
+    routes::metadata_routes,
+    schemas::{consoles::name, consoles_table, games_table, roms_table},
 };
 
-pub fn get_consoles() -> Vec<Console> {
-    let connection = &mut establish_connection();
-
+pub fn get_consoles(conn: &mut SqliteConnection) -> Vec<Console> {
     consoles_table::table
         .filter(consoles_table::id.gt(0))
         .select(Console::as_select())
-        .load(connection)
+        .load(conn)
         .expect("Error getting consoles")
 }
 
-pub fn get_all_consoles_with_games() -> Result<Vec<ConsoleWithGames>, Error> {
-    let connection = &mut establish_connection();
-
+pub fn get_all_consoles_with_games(
+    conn: &mut SqliteConnection,
+) -> Result<Vec<ConsoleWithGames>, Error> {
     let all_consoles = consoles_table::table
         .select(Console::as_select())
-        .load(connection)?;
+        .load(conn)?;
 
     println!("consoles");
     println!("{:?}", all_consoles);
 
     let games = Game::belonging_to(&all_consoles)
+        .filter(games_table::removed.eq(false))
         .select(Game::as_select())
-        .load(connection)?;
+        .load(conn)?;
 
     let games_of_consoles = games
         .grouped_by(&all_consoles)
@@ -42,19 +43,22 @@ pub fn get_all_consoles_with_games() -> Result<Vec<ConsoleWithGames>, Error> {
     Ok(games_of_consoles)
 }
 
-pub fn get_console_by_name(console_name: &str) -> Console {
-    let conn = &mut establish_connection();
-
+/// The console of that name, `None` if there is no such console
+pub fn get_console_by_name(
+    conn: &mut SqliteConnection,
+    console_name: &str,
+) -> Result<Option<Console>, Error> {
     consoles_table::table
         .filter(name.eq(console_name))
         .select(Console::as_select())
         .first(conn)
-        .unwrap_or_else(|_| panic!("Error getting console with name: {}", console_name))
+        .optional()
 }
 
-pub fn get_console_with_game_roms(console_name: &str) -> ConsoleWithGameRoms {
-    let conn = &mut establish_connection();
-
+pub fn get_console_with_game_roms(
+    conn: &mut SqliteConnection,
+    console_name: &str,
+) -> ConsoleWithGameRoms {
     let console = consoles_table::table
         .filter(name.eq(console_name))
         .select(Console::as_select())
@@ -62,21 +66,28 @@ pub fn get_console_with_game_roms(console_name: &str) -> ConsoleWithGameRoms {
         .unwrap_or_else(|_| panic!("Error getting console with name: {}", console_name));
 
     let games = Game::belonging_to(&console)
+        .filter(games_table::removed.eq(false))
         .select(Game::as_select())
         .load(conn)
         .unwrap();
 
     let roms = Rom::belonging_to(&games)
+        .filter(roms_table::removed.eq(false))
         .select(Rom::as_select())
         .load(conn)
         .unwrap();
 
-    let game_roms = roms
+    let mut game_roms = roms
         .grouped_by(&games)
         .into_iter()
         .zip(games)
-        .map(|(roms, game)| GameWithRoms { game, roms })
+        .map(|(roms, game)| GameWithRoms {
+            game,
+            roms,
+            metadata: GameMetadata::default(),
+        })
         .collect::<Vec<GameWithRoms>>();
+    metadata_routes::add_metadata(conn, &mut game_roms).unwrap();
 
     ConsoleWithGameRoms {
         console,
@@ -86,12 +97,41 @@ pub fn get_console_with_game_roms(console_name: &str) -> ConsoleWithGameRoms {
 
 #[cfg(test)]
 mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use crate::db::test_connection;
+
     use super::*;
 
     #[test]
     fn test_console_game_roms() {
-        let mut console = get_console_with_game_roms("Super Nintendo Entertainment System");
-        console.games = console.games[..10].to_vec();
+        let conn = &mut test_connection();
+        conn.batch_execute(
+            "INSERT INTO games (id, title, console_id)
+                 SELECT 1, 'Secret of Mana', id FROM consoles WHERE abbreviation = 'snes';
+             INSERT INTO games (id, title, console_id)
+                 SELECT 2, 'Star Fox', id FROM consoles WHERE abbreviation = 'snes';
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES
+                 (1, 'Secret of Mana (Europe).sfc', '', 0, 1),
+                 (2, 'Secret of Mana (USA).sfc', '', 0, 1),
+                 (3, 'Star Fox (USA).sfc', '', 0, 2);",
+        )
+        .unwrap();
+
+        let console = get_console_with_game_roms(conn, "Super Nintendo Entertainment System");
+        let mut games: Vec<(String, usize)> = console
+            .games
+            .iter()
+            .map(|game_roms| (game_roms.game.title.clone(), game_roms.roms.len()))
+            .collect();
+        games.sort();
+        assert_eq!(
+            vec![
+                ("Secret of Mana".to_string(), 2),
+                ("Star Fox".to_string(), 1)
+            ],
+            games
+        );
 
         let json = serde_json::to_string_pretty(&console).unwrap();
         println!("{}", json);
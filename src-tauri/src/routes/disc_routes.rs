@@ -0,0 +1,67 @@
+use diesel::{prelude::*, result::Error};
+
+use crate::{
+    models::{Rom, RomTrack, RomWithTracks},
+    schemas::{games_table, rom_tracks_table, roms_table},
+};
+
+/// Returns the console's disc roms, the roms with tracks, with their tracks in DAT order
+pub fn get_disc_roms_for_console(
+    conn: &mut SqliteConnection,
+    console_id: i32,
+) -> Result<Vec<RomWithTracks>, Error> {
+    let roms = roms_table::table
+        .inner_join(games_table::table)
+        .filter(games_table::console_id.eq(console_id))
+        .filter(roms_table::removed.eq(false))
+        .filter(roms_table::id.eq_any(rom_tracks_table::table.select(rom_tracks_table::rom_id)))
+        .order(roms_table::title)
+        .select(Rom::as_select())
+        .load(conn)?;
+
+    let tracks = RomTrack::belonging_to(&roms)
+        .select(RomTrack::as_select())
+        .order(rom_tracks_table::id)
+        .load(conn)?;
+
+    Ok(tracks
+        .grouped_by(&roms)
+        .into_iter()
+        .zip(roms)
+        .map(|(tracks, rom)| RomWithTracks { rom, tracks })
+        .collect())
+}
+
+pub fn get_rom_with_tracks(
+    conn: &mut SqliteConnection,
+    rom_id: i32,
+) -> Result<RomWithTracks, Error> {
+    let rom = roms_table::table
+        .find(rom_id)
+        .select(Rom::as_select())
+        .first(conn)?;
+    let tracks = RomTrack::belonging_to(&rom)
+        .select(RomTrack::as_select())
+        .order(rom_tracks_table::id)
+        .load(conn)?;
+
+    Ok(RomWithTracks { rom, tracks })
+}
+
+pub fn find_rom_by_sha1(conn: &mut SqliteConnection, sha1: &str) -> Result<Option<Rom>, Error> {
+    roms_table::table
+        .filter(roms_table::sha1.eq(sha1.to_lowercase()))
+        .select(Rom::as_select())
+        .first(conn)
+        .optional()
+}
+
+pub fn find_tracks_by_sha1(
+    conn: &mut SqliteConnection,
+    sha1s: Vec<String>,
+) -> Result<Vec<RomTrack>, Error> {
+    rom_tracks_table::table
+        .filter(rom_tracks_table::sha1.eq_any(sha1s))
+        .select(RomTrack::as_select())
+        .load(conn)
+}
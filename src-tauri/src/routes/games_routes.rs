@@ -1,37 +1,467 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
 use crate::{
-    establish_connection,
-    models::{game::Game, GameWithRoms, Rom},
-    schemas::games_table,
+    filters::Filter,
+    models::{game::Game, GameMetadata, GameWithRoms, Rom},
+    routes::{metadata_routes, search_routes},
+    schemas::{games_table, roms_table},
 };
-use diesel::prelude::*;
+use diesel::{
+    prelude::*,
+    result::Error,
+    sql_query,
+    sql_types::{BigInt, Integer, Text},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameSort {
+    #[default]
+    Title,
+    /// total size of the game's roms
+    Size,
+    /// the first parentheses of the rom titles, which name the regions
+    Region,
+    /// games with a rom in the console's rom folder first
+    Owned,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GameQuery {
+    /// words which all have to start a word of the game title, case insensitive
+    pub search: String,
+    pub sort: GameSort,
+    pub descending: bool,
+    pub offset: i64,
+    pub limit: i64,
+}
 
-pub fn get_all_games() -> Vec<Game> {
-    let connection = &mut establish_connection();
+/// A page of the games matching a query
+#[derive(Debug, Clone, Serialize)]
+pub struct GamePage {
+    pub games: Vec<GameWithRoms>,
+    /// number of matching games over all pages
+    pub total: i64,
+    pub offset: i64,
+}
+
+impl Default for GameQuery {
+    fn default() -> Self {
+        GameQuery {
+            search: String::new(),
+            sort: GameSort::Title,
+            descending: false,
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
+
+pub fn get_all_games(conn: &mut SqliteConnection) -> Vec<Game> {
     let results: Vec<Game> = games_table
+        .filter(games_table::removed.eq(false))
         .select(Game::as_select())
-        .load(connection)
+        .load(conn)
         .expect("Error loading games");
 
     results
 }
 
-pub fn get_games_for_console(console_id: &i32) -> Vec<GameWithRoms> {
-    let conn = &mut establish_connection();
-
+pub fn get_games_for_console(conn: &mut SqliteConnection, console_id: &i32) -> Vec<GameWithRoms> {
     let games: Vec<Game> = games_table
         .filter(games_table::console_id.eq(console_id))
+        .filter(games_table::removed.eq(false))
         .select(Game::as_select())
         .load(conn)
         .unwrap_or_else(|_| panic!("Error loading games for console with id {}", console_id));
 
     let roms = Rom::belonging_to(&games)
+        .filter(roms_table::removed.eq(false))
         .select(Rom::as_select())
         .load(conn)
         .expect("error loading game roms");
 
-    roms.grouped_by(&games)
+    let mut game_roms = roms
+        .grouped_by(&games)
+        .into_iter()
+        .zip(games)
+        .map(|(roms, game)| GameWithRoms {
+            game,
+            roms,
+            metadata: GameMetadata::default(),
+        })
+        .collect::<Vec<GameWithRoms>>();
+    metadata_routes::add_metadata(conn, &mut game_roms).expect("error loading game metadata");
+
+    game_roms
+}
+
+#[derive(QueryableByName)]
+struct GameId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[derive(QueryableByName)]
+struct GameCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// A rom folder's file names, as of the folder's modification time
+struct RomListing {
+    dir: PathBuf,
+    modified: SystemTime,
+    files: Arc<Vec<String>>,
+}
+
+/// Listings of the rom folders, read again once a folder was modified
+static ROM_FILES: Mutex<Vec<RomListing>> = Mutex::new(Vec::new());
+
+/// Names of the files in the rom folder, from the last listing if the folder is unchanged
+fn rom_files(rom_dir: &Path) -> Arc<Vec<String>> {
+    let Some(modified) = fs::metadata(rom_dir)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+    else {
+        return Arc::default();
+    };
+
+    let mut listings = ROM_FILES.lock().unwrap();
+    if let Some(listing) = listings
+        .iter()
+        .find(|listing| listing.dir == rom_dir && listing.modified == modified)
+    {
+        return listing.files.clone();
+    }
+
+    let files: Arc<Vec<String>> = Arc::new(
+        fs::read_dir(rom_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    );
+    listings.retain(|listing| listing.dir != rom_dir);
+    listings.push(RomListing {
+        dir: rom_dir.to_path_buf(),
+        modified,
+        files: files.clone(),
+    });
+
+    files
+}
+
+/// Loads one page of the console's games matching the search, sorted by the query's key
+/// and then by title. The search goes through the `games_search` index, the sort keys are
+/// aggregated once per query. `rom_dir` is needed to sort by owned status, without it no
+/// game is owned. With a saved filter only the roms it keeps count, and games without
+/// any are left out before paging.
+pub fn query_games(
+    conn: &mut SqliteConnection,
+    console_id: i32,
+    query: &GameQuery,
+    rom_dir: Option<&Path>,
+    rom_filter: Option<&Filter>,
+) -> Result<GamePage, Error> {
+    let expression = search_routes::match_expression(&query.search);
+    // ids of the roms the filter keeps, bound as a json array
+    let kept_roms = match rom_filter {
+        Some(rom_filter) => Some(
+            roms_table::table
+                .inner_join(games_table::table)
+                .filter(games_table::console_id.eq(console_id))
+                .filter(roms_table::removed.eq(false))
+                .select(Rom::as_select())
+                .load(conn)?
+                .into_iter()
+                .filter(|rom| rom_filter.matches(rom))
+                .map(|rom| rom.id)
+                .collect::<Vec<i32>>(),
+        ),
+        None => None,
+    };
+    let kept_json = kept_roms
+        .as_ref()
+        .map(|ids| serde_json::to_string(ids).unwrap_or_default());
+
+    let mut filter = "WHERE games.console_id = ? AND NOT games.removed".to_string();
+    if expression.is_some() {
+        filter.push_str(
+            " AND games.id IN (SELECT rowid FROM games_search WHERE games_search MATCH ?)",
+        );
+    }
+    if kept_json.is_some() {
+        filter.push_str(
+            " AND games.id IN (SELECT game_id FROM roms \
+             WHERE roms.id IN (SELECT value FROM json_each(?)))",
+        );
+    }
+
+    let mut count = sql_query(format!("SELECT COUNT(*) AS count FROM games {filter}"))
+        .into_boxed()
+        .bind::<Integer, _>(console_id);
+    if let Some(expression) = &expression {
+        count = count.bind::<Text, _>(expression.clone());
+    }
+    if let Some(kept_json) = &kept_json {
+        count = count.bind::<Text, _>(kept_json.clone());
+    }
+    let total = count.get_result::<GameCount>(conn)?.count;
+
+    // the other sort keys are aggregated once over the roms of the console's games
+    let (keys, key) = match query.sort {
+        GameSort::Title => (None, "games.title COLLATE NOCASE"),
+        GameSort::Size => (Some("SUM(roms.size)"), "COALESCE(sort_keys.sort_key, 0)"),
+        GameSort::Region => (
+            Some("MIN(substr(roms.title, instr(roms.title, '(') + 1))"),
+            "sort_keys.sort_key",
+        ),
+        // owned games first
+        GameSort::Owned => (Some("1"), "sort_keys.sort_key IS NULL"),
+    };
+    let join = match keys {
+        Some(aggregate) => {
+            let owned = if query.sort == GameSort::Owned {
+                "AND roms.title IN (SELECT value FROM json_each(?))"
+            } else {
+                ""
+            };
+            let kept = if kept_json.is_some() {
+                "AND roms.id IN (SELECT value FROM json_each(?))"
+            } else {
+                ""
+            };
+            format!(
+                "LEFT JOIN (SELECT roms.game_id, {aggregate} AS sort_key FROM roms \
+                 INNER JOIN games ON games.id = roms.game_id \
+                 WHERE games.console_id = ? AND NOT roms.removed {owned} {kept} \
+                 GROUP BY roms.game_id) sort_keys ON sort_keys.game_id = games.id"
+            )
+        }
+        None => String::new(),
+    };
+    let direction = if query.descending { "DESC" } else { "ASC" };
+
+    let mut page = sql_query(format!(
+        "SELECT games.id FROM games {join} {filter} \
+         ORDER BY {key} {direction}, games.title COLLATE NOCASE LIMIT ? OFFSET ?"
+    ))
+    .into_boxed();
+    if keys.is_some() {
+        page = page.bind::<Integer, _>(console_id);
+    }
+    if query.sort == GameSort::Owned {
+        let files = rom_dir.map(rom_files).unwrap_or_default();
+        page = page.bind::<Text, _>(serde_json::to_string(&*files).unwrap_or_default());
+    }
+    if let (Some(_), Some(kept_json)) = (keys, &kept_json) {
+        page = page.bind::<Text, _>(kept_json.clone());
+    }
+    page = page.bind::<Integer, _>(console_id);
+    if let Some(expression) = expression {
+        page = page.bind::<Text, _>(expression);
+    }
+    if let Some(kept_json) = kept_json {
+        page = page.bind::<Text, _>(kept_json);
+    }
+    let ids: Vec<i32> = page
+        .bind::<BigInt, _>(query.limit.max(0))
+        .bind::<BigInt, _>(query.offset.max(0))
+        .load::<GameId>(conn)?
+        .into_iter()
+        .map(|game| game.id)
+        .collect();
+
+    let mut games: Vec<Game> = games_table
+        .filter(games_table::id.eq_any(&ids))
+        .select(Game::as_select())
+        .load(conn)?;
+    games.sort_by_key(|game| ids.iter().position(|id| *id == game.id));
+
+    let mut roms: Vec<Rom> = Rom::belonging_to(&games)
+        .filter(roms_table::removed.eq(false))
+        .select(Rom::as_select())
+        .order(roms_table::title)
+        .load(conn)?;
+    if let Some(kept_roms) = &kept_roms {
+        roms.retain(|rom| kept_roms.contains(&rom.id));
+    }
+
+    let mut games: Vec<GameWithRoms> = roms
+        .grouped_by(&games)
         .into_iter()
         .zip(games)
-        .map(|(roms, game)| GameWithRoms { game, roms })
-        .collect::<Vec<GameWithRoms>>()
+        .map(|(roms, game)| GameWithRoms {
+            game,
+            roms,
+            metadata: GameMetadata::default(),
+        })
+        .collect();
+    metadata_routes::add_metadata(conn, &mut games)?;
+
+    Ok(GamePage {
+        games,
+        total,
+        offset: query.offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{
+        dat_parser::parser::parse_file,
+        db::test_connection,
+        filters::{FilterCondition, FilterRuleset},
+        routes::console_routes,
+    };
+
+    use super::*;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<datafile>
+	<header>
+		<name>Nintendo - Super Nintendo Entertainment System</name>
+	</header>
+	<game name="Secret of Mana (Europe)">
+		<description>Secret of Mana (Europe)</description>
+		<rom name="Secret of Mana (Europe).sfc" size="2097152" crc="de112322" md5="7d51b2e3f4e5f7b5e8d1f7c33c1e4e11"/>
+	</game>
+	<game name="Secret of Mana (USA)">
+		<description>Secret of Mana (USA)</description>
+		<rom name="Secret of Mana (USA).sfc" size="2097152" crc="d0176b24" md5="8d29e6d3c1e2b4d1a7b6c5e4f3a2b1c0"/>
+	</game>
+	<game name="Trials of Mana (World) (Collection of Mana)">
+		<description>Trials of Mana (World) (Collection of Mana)</description>
+		<rom name="Trials of Mana (World) (Collection of Mana).sfc" size="6291456" crc="a1b2c3d4" md5="2e4f6a8c0b1d3f5e7a9c1e3b5d7f9a0c"/>
+	</game>
+	<game name="Star Fox (USA)">
+		<description>Star Fox (USA)</description>
+		<rom name="Star Fox (USA).sfc" size="1048576" crc="a5f7f1ad" md5="1c3b5f8e7d6a4b2c9e8f7a6b5c4d3e2f"/>
+	</game>
+	<game name="Super Metroid (Japan, USA)">
+		<description>Super Metroid (Japan, USA)</description>
+		<rom name="Super Metroid (Japan, USA).sfc" size="3145728" crc="d63ed5f8" md5="21f3e98df4780ee1c667b84e57d88675"/>
+	</game>
+	<game name="Mana Quest (Japan)">
+		<description>Mana Quest (Japan)</description>
+		<rom name="Mana Quest (Japan).sfc" size="524288" crc="b4c5d6e7" md5="3f5a7c9e1b3d5f7a9c1e3b5d7f9a1c3e"/>
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_query_games() {
+        let conn = &mut test_connection();
+        let path = env::temp_dir().join("romana_query_games.dat");
+        fs::write(&path, DAT).unwrap();
+        parse_file(conn, &path.to_string_lossy()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let console_id = console_routes::get_consoles(conn)
+            .into_iter()
+            .find(|console| console.name == "Super Nintendo Entertainment System")
+            .expect("SNES console missing")
+            .id;
+        let titles = |page: &GamePage| -> Vec<String> {
+            page.games
+                .iter()
+                .map(|game_roms| game_roms.game.title.clone())
+                .collect()
+        };
+
+        let query = GameQuery {
+            search: "of MANA".to_string(),
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, None, None).unwrap();
+        assert_eq!(vec!["Secret of Mana", "Trials of Mana"], titles(&page));
+        assert_eq!(2, page.games[0].roms.len());
+        assert_eq!(2, page.total);
+
+        let query = GameQuery {
+            sort: GameSort::Size,
+            descending: true,
+            offset: 1,
+            limit: 3,
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, None, None).unwrap();
+        assert_eq!(
+            vec!["Secret of Mana", "Super Metroid", "Star Fox"],
+            titles(&page)
+        );
+        assert_eq!(5, page.total);
+
+        let query = GameQuery {
+            sort: GameSort::Region,
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, None, None).unwrap();
+        assert_eq!(
+            vec![
+                "Secret of Mana",
+                "Mana Quest",
+                "Super Metroid",
+                "Star Fox",
+                "Trials of Mana"
+            ],
+            titles(&page)
+        );
+
+        let rom_dir = env::temp_dir().join("romana_query_games");
+        let _ = fs::remove_dir_all(&rom_dir);
+        fs::create_dir_all(&rom_dir).unwrap();
+        fs::write(rom_dir.join("Star Fox (USA).sfc"), b"").unwrap();
+        let query = GameQuery {
+            sort: GameSort::Owned,
+            limit: 2,
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, Some(&rom_dir), None).unwrap();
+        assert_eq!(vec!["Star Fox", "Mana Quest"], titles(&page));
+        fs::remove_dir_all(&rom_dir).unwrap();
+
+        // only the roms the filter keeps are sorted by, games without any are not counted
+        let ruleset = FilterRuleset {
+            include: Some(FilterCondition::Region {
+                regions: vec!["USA".to_string()],
+            }),
+            exclude: None,
+        };
+        let rom_filter = Filter::new(&ruleset, None).unwrap();
+        let query = GameQuery {
+            sort: GameSort::Size,
+            descending: true,
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, None, Some(&rom_filter)).unwrap();
+        assert_eq!(
+            vec!["Super Metroid", "Secret of Mana", "Star Fox"],
+            titles(&page)
+        );
+        assert_eq!(3, page.total);
+        assert_eq!(1, page.games[1].roms.len());
+
+        let query = GameQuery {
+            search: "mana".to_string(),
+            limit: 1,
+            ..Default::default()
+        };
+        let page = query_games(conn, console_id, &query, None, Some(&rom_filter)).unwrap();
+        assert_eq!(vec!["Secret of Mana"], titles(&page));
+        assert_eq!(1, page.total);
+    }
 }
@@ -0,0 +1,467 @@
+use diesel::{prelude::*, result::Error};
+use serde::Serialize;
+
+use crate::{dat_parser::parser::DatGame, schemas::*};
+
+/// Number of rows removed by the clean-up, per table. Rows removed by cascading
+/// deletes of the orphans are not counted.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct CleanupSummary {
+    pub games: usize,
+    pub roms: usize,
+    pub rom_regions: usize,
+    pub rom_tracks: usize,
+    pub compressed_roms: usize,
+    pub patches: usize,
+    pub patched_roms: usize,
+    pub save_backups: usize,
+    pub bios_files: usize,
+    /// saves of removed roms or games, kept but no longer linked
+    pub unlinked_saves: usize,
+    pub regions: usize,
+    pub developers: usize,
+    pub publishers: usize,
+    pub genres: usize,
+}
+
+/// Removes the rows left behind by deletes from before foreign keys were enforced, and the
+/// regions, developers, publishers and genres nothing refers to. Parents go first, so their cascades take the
+/// children along.
+pub fn remove_orphans(conn: &mut SqliteConnection) -> Result<CleanupSummary, Error> {
+    conn.transaction(|conn| {
+        let mut summary = CleanupSummary {
+            games: diesel::delete(games_table::table.filter(
+                games_table::console_id.ne_all(consoles_table::table.select(consoles_table::id)),
+            ))
+            .execute(conn)?,
+            ..Default::default()
+        };
+
+        summary.roms = diesel::delete(
+            roms_table::table
+                .filter(roms_table::game_id.ne_all(games_table::table.select(games_table::id))),
+        )
+        .execute(conn)?;
+
+        summary.rom_regions = diesel::delete(
+            rom_regions_table::table.filter(
+                rom_regions_table::rom_id
+                    .ne_all(roms_table::table.select(roms_table::id))
+                    .or(rom_regions_table::region_id
+                        .ne_all(regions_table::table.select(regions_table::id))),
+            ),
+        )
+        .execute(conn)?;
+
+        summary.rom_tracks = diesel::delete(
+            rom_tracks_table::table
+                .filter(rom_tracks_table::rom_id.ne_all(roms_table::table.select(roms_table::id))),
+        )
+        .execute(conn)?;
+
+        summary.compressed_roms = diesel::delete(compressed_roms_table::table.filter(
+            compressed_roms_table::rom_id.ne_all(roms_table::table.select(roms_table::id)),
+        ))
+        .execute(conn)?;
+
+        summary.patches = diesel::delete(
+            patches_table::table
+                .filter(patches_table::rom_id.ne_all(roms_table::table.select(roms_table::id))),
+        )
+        .execute(conn)?;
+
+        summary.patched_roms = diesel::delete(
+            patched_roms_table::table.filter(
+                patched_roms_table::rom_id
+                    .ne_all(roms_table::table.select(roms_table::id))
+                    .or(patched_roms_table::patch_id
+                        .ne_all(patches_table::table.select(patches_table::id))),
+            ),
+        )
+        .execute(conn)?;
+
+        summary.save_backups = diesel::delete(save_backups_table::table.filter(
+            save_backups_table::save_id.ne_all(saves_table::table.select(saves_table::id)),
+        ))
+        .execute(conn)?;
+
+        summary.bios_files = diesel::delete(bios_files_table::table.filter(
+            bios_files_table::console_id.ne_all(consoles_table::table.select(consoles_table::id)),
+        ))
+        .execute(conn)?;
+
+        // NOT IN is true for NULL when the table is empty
+        summary.unlinked_saves = diesel::update(saves_table::table.filter(
+            saves_table::rom_id.is_not_null().and(
+                saves_table::rom_id.ne_all(roms_table::table.select(roms_table::id.nullable())),
+            ),
+        ))
+        .set(saves_table::rom_id.eq(None::<i32>))
+        .execute(conn)?;
+        summary.unlinked_saves += diesel::update(saves_table::table.filter(
+            saves_table::game_id.is_not_null().and(
+                saves_table::game_id.ne_all(games_table::table.select(games_table::id.nullable())),
+            ),
+        ))
+        .set(saves_table::game_id.eq(None::<i32>))
+        .execute(conn)?;
+
+        summary.regions = diesel::delete(regions_table::table.filter(
+            regions_table::id.ne_all(rom_regions_table::table.select(rom_regions_table::region_id)),
+        ))
+        .execute(conn)?;
+
+        summary.developers =
+            diesel::delete(developers_table::table.filter(
+                developers_table::id.ne_all(
+                    game_developers_table::table.select(game_developers_table::developer_id),
+                ),
+            ))
+            .execute(conn)?;
+
+        summary.publishers =
+            diesel::delete(publishers_table::table.filter(
+                publishers_table::id.ne_all(
+                    game_publishers_table::table.select(game_publishers_table::publisher_id),
+                ),
+            ))
+            .execute(conn)?;
+
+        summary.genres = diesel::delete(genres_table::table.filter(
+            genres_table::id.ne_all(game_genres_table::table.select(game_genres_table::genre_id)),
+        ))
+        .execute(conn)?;
+
+        Ok(summary)
+    })
+}
+
+/// Games and roms removed with a console or a DAT. The ones still referenced by saves,
+/// compressed or patched files are flagged as removed instead, so importing the DAT again
+/// restores them.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct RemovalSummary {
+    pub games: usize,
+    pub roms: usize,
+    pub kept_games: usize,
+    pub kept_roms: usize,
+    pub bios_files: usize,
+}
+
+/// Deletes the console's games and roms flagged as removed which no save, patch or metadata
+/// refers to
+fn purge_removed(conn: &mut SqliteConnection, console_id: i32) -> Result<RemovalSummary, Error> {
+    let console_games = games_table::table
+        .filter(games_table::console_id.eq(console_id))
+        .select(games_table::id);
+
+    let kept_roms: Vec<i32> = roms_table::table
+        .filter(roms_table::removed.eq(true))
+        .filter(roms_table::game_id.eq_any(console_games))
+        .filter(
+            roms_table::id
+                .nullable()
+                .eq_any(
+                    saves_table::table
+                        .filter(saves_table::rom_id.is_not_null())
+                        .select(saves_table::rom_id),
+                )
+                .or(roms_table::id
+                    .eq_any(compressed_roms_table::table.select(compressed_roms_table::rom_id)))
+                .or(roms_table::id.eq_any(patches_table::table.select(patches_table::rom_id)))
+                .or(roms_table::id
+                    .eq_any(patched_roms_table::table.select(patched_roms_table::rom_id))),
+        )
+        .select(roms_table::id)
+        .load(conn)?;
+    let roms = diesel::delete(
+        roms_table::table
+            .filter(roms_table::removed.eq(true))
+            .filter(roms_table::game_id.eq_any(console_games))
+            .filter(roms_table::id.ne_all(&kept_roms)),
+    )
+    .execute(conn)?;
+
+    // a game is kept as long as any of its roms is, deleting it would take them along, and
+    // as long as the user gave it metadata
+    let kept_games: Vec<i32> = games_table::table
+        .filter(games_table::console_id.eq(console_id))
+        .filter(games_table::removed.eq(true))
+        .filter(
+            games_table::id
+                .eq_any(roms_table::table.select(roms_table::game_id))
+                .or(games_table::id.nullable().eq_any(
+                    saves_table::table
+                        .filter(saves_table::game_id.is_not_null())
+                        .select(saves_table::game_id),
+                ))
+                .or(games_table::id
+                    .eq_any(game_details_table::table.select(game_details_table::game_id)))
+                .or(games_table::id
+                    .eq_any(game_developers_table::table.select(game_developers_table::game_id)))
+                .or(games_table::id
+                    .eq_any(game_publishers_table::table.select(game_publishers_table::game_id)))
+                .or(games_table::id
+                    .eq_any(game_genres_table::table.select(game_genres_table::game_id)))
+                .or(games_table::id
+                    .eq_any(game_releases_table::table.select(game_releases_table::game_id))),
+        )
+        .select(games_table::id)
+        .load(conn)?;
+    let games = diesel::delete(
+        games_table::table
+            .filter(games_table::console_id.eq(console_id))
+            .filter(games_table::removed.eq(true))
+            .filter(games_table::id.ne_all(&kept_games)),
+    )
+    .execute(conn)?;
+
+    Ok(RemovalSummary {
+        games,
+        roms,
+        kept_games: kept_games.len(),
+        kept_roms: kept_roms.len(),
+        ..Default::default()
+    })
+}
+
+/// Removes all games, roms and BIOS files imported for the console. The console itself stays.
+pub fn remove_console(
+    conn: &mut SqliteConnection,
+    console_id: i32,
+) -> Result<RemovalSummary, Error> {
+    conn.transaction(|conn| {
+        let console_games = games_table::table
+            .filter(games_table::console_id.eq(console_id))
+            .select(games_table::id);
+
+        diesel::update(roms_table::table.filter(roms_table::game_id.eq_any(console_games)))
+            .set(roms_table::removed.eq(true))
+            .execute(conn)?;
+        diesel::update(games_table::table.filter(games_table::console_id.eq(console_id)))
+            .set(games_table::removed.eq(true))
+            .execute(conn)?;
+
+        let bios_files = diesel::delete(
+            bios_files_table::table.filter(bios_files_table::console_id.eq(console_id)),
+        )
+        .execute(conn)?;
+
+        Ok(RemovalSummary {
+            bios_files,
+            ..purge_removed(conn, console_id)?
+        })
+    })
+}
+
+/// Removes the roms of the DAT's games from the console, and the games left without roms.
+/// Roms of the same games from other DATs stay.
+pub fn remove_dat_games(
+    conn: &mut SqliteConnection,
+    console_id: i32,
+    dat_games: &[DatGame],
+) -> Result<RemovalSummary, Error> {
+    conn.transaction(|conn| {
+        for dat_game in dat_games {
+            let game_id = games_table::table
+                .filter(games_table::console_id.eq(console_id))
+                .filter(games_table::title.eq(&dat_game.name))
+                .select(games_table::id);
+
+            diesel::update(
+                roms_table::table
+                    .filter(roms_table::game_id.eq_any(game_id))
+                    .filter(roms_table::title.eq_any(dat_game.roms.iter().map(|rom| &rom.name))),
+            )
+            .set(roms_table::removed.eq(true))
+            .execute(conn)?;
+
+            diesel::update(
+                games_table::table
+                    .filter(games_table::console_id.eq(console_id))
+                    .filter(games_table::title.eq(&dat_game.name))
+                    .filter(
+                        games_table::id.ne_all(
+                            roms_table::table
+                                .filter(roms_table::removed.eq(false))
+                                .select(roms_table::game_id),
+                        ),
+                    ),
+            )
+            .set(games_table::removed.eq(true))
+            .execute(conn)?;
+        }
+
+        purge_removed(conn, console_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use std::{env, fs};
+
+    use crate::{
+        dat_parser::parser::{parse_dat, parse_file},
+        db::{create_pool, prepare},
+        models::GameMetadata,
+        routes::{
+            games_routes::get_games_for_console,
+            metadata_routes::{get_metadata, set_metadata},
+        },
+    };
+
+    use super::*;
+
+    const DAT: &str = r#"<?xml version="1.0"?>
+<datafile>
+	<header>
+		<name>Nintendo - Super Nintendo Entertainment System</name>
+	</header>
+	<game name="Secret of Mana (Europe)">
+		<description>Secret of Mana (Europe)</description>
+		<rom name="Secret of Mana (Europe).sfc" size="2097152" crc="de112322" md5="7d51b2e3f4e5f7b5e8d1f7c33c1e4e11"/>
+	</game>
+	<game name="Secret of Mana (USA)">
+		<description>Secret of Mana (USA)</description>
+		<rom name="Secret of Mana (USA).sfc" size="2097152" crc="d0176b24" md5="8d29e6d3c1e2b4d1a7b6c5e4f3a2b1c0"/>
+	</game>
+	<game name="Star Fox (USA)">
+		<description>Star Fox (USA)</description>
+		<rom name="Star Fox (USA).sfc" size="1048576" crc="a5f7f1ad" md5="1c3b5f8e7d6a4b2c9e8f7a6b5c4d3e2f"/>
+	</game>
+</datafile>
+"#;
+
+    #[test]
+    fn test_remove_orphans() {
+        let pool = create_pool(":memory:").unwrap();
+        let conn = &mut pool.get().unwrap();
+        prepare(conn, None).unwrap();
+
+        conn.batch_execute(
+            "INSERT INTO games (id, title, console_id)
+                 SELECT 1, 'Secret of Mana', id FROM consoles WHERE abbreviation = 'snes';
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES (1, 'Secret of Mana (Europe).sfc', '', 0, 1);
+             INSERT INTO regions (id, name, abbreviation) VALUES (1, 'Europe', 'EUR'), (2, 'Japan', 'JPN');
+             INSERT INTO rom_regions (rom_id, region_id) VALUES (1, 1);
+             INSERT INTO saves (path, kind, md5, size, modified_at, rom_id, game_id)
+                 VALUES ('Secret of Mana (Europe).srm', 'sram', '', 0, 0, 1, 1);",
+        )
+        .unwrap();
+
+        // deleting the console takes its games, roms and their regions along
+        diesel::delete(consoles_table::table.filter(consoles_table::abbreviation.eq("snes")))
+            .execute(conn)
+            .unwrap();
+        let roms: i64 = roms_table::table.count().get_result(conn).unwrap();
+        let rom_regions: i64 = rom_regions_table::table.count().get_result(conn).unwrap();
+        let save_rom: Option<i32> = saves_table::table
+            .select(saves_table::rom_id)
+            .first(conn)
+            .unwrap();
+        assert_eq!((0, 0, None), (roms, rom_regions, save_rom));
+
+        // rows from before foreign keys were enforced
+        conn.batch_execute(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO games (id, title, console_id) VALUES (2, 'Star Fox 2', 999);
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES (2, 'Star Fox 2 (Japan).sfc', '', 0, 2);
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES (3, 'Orphan.sfc', '', 0, 998);
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+
+        let summary = remove_orphans(conn).unwrap();
+        assert_eq!(
+            CleanupSummary {
+                games: 1,
+                roms: 1,
+                regions: 2,
+                ..Default::default()
+            },
+            summary
+        );
+        let roms: i64 = roms_table::table.count().get_result(conn).unwrap();
+        assert_eq!(0, roms);
+
+        assert_eq!(CleanupSummary::default(), remove_orphans(conn).unwrap());
+    }
+
+    #[test]
+    fn test_remove_dat_and_console() {
+        let pool = create_pool(":memory:").unwrap();
+        let conn = &mut pool.get().unwrap();
+        prepare(conn, None).unwrap();
+
+        let path = env::temp_dir().join("romana_remove_dat.dat");
+        fs::write(&path, DAT).unwrap();
+        parse_file(conn, &path.to_string_lossy()).unwrap();
+        let (console, dat_games) = parse_dat(conn, DAT).unwrap();
+
+        // the european rom has a save, which keeps it and its game
+        conn.batch_execute(
+            "INSERT INTO saves (path, kind, md5, size, modified_at, rom_id, game_id)
+                 SELECT 'Secret of Mana (Europe).srm', 'sram', '', 0, 0, id, game_id
+                 FROM roms WHERE title = 'Secret of Mana (Europe).sfc';",
+        )
+        .unwrap();
+
+        // metadata the user gave Star Fox keeps the game, but not its rom
+        let star_fox: i32 = games_table::table
+            .filter(games_table::title.eq("Star Fox"))
+            .select(games_table::id)
+            .first(conn)
+            .unwrap();
+        let metadata = GameMetadata {
+            genres: vec!["Shooter".to_string()],
+            ..Default::default()
+        };
+        set_metadata(conn, star_fox, &metadata).unwrap();
+
+        assert_eq!(
+            RemovalSummary {
+                games: 0,
+                roms: 2,
+                kept_games: 2,
+                kept_roms: 1,
+                bios_files: 0,
+            },
+            remove_dat_games(conn, console.id, &dat_games).unwrap()
+        );
+        assert!(get_games_for_console(conn, &console.id).is_empty());
+
+        // importing the DAT again restores the kept rom
+        parse_file(conn, &path.to_string_lossy()).unwrap();
+        let games = get_games_for_console(conn, &console.id);
+        assert_eq!(2, games.len());
+        assert_eq!(3, games.iter().map(|game| game.roms.len()).sum::<usize>());
+        let roms: i64 = roms_table::table.count().get_result(conn).unwrap();
+        assert_eq!(3, roms);
+
+        assert_eq!(star_fox, games[1].game.id);
+        assert_eq!(metadata, games[1].metadata);
+
+        assert_eq!(
+            RemovalSummary {
+                games: 0,
+                roms: 2,
+                kept_games: 2,
+                kept_roms: 1,
+                bios_files: 0,
+            },
+            remove_console(conn, console.id).unwrap()
+        );
+        assert!(get_games_for_console(conn, &console.id).is_empty());
+        assert_eq!(
+            metadata,
+            get_metadata(conn, &[star_fox])
+                .unwrap()
+                .remove(&star_fox)
+                .unwrap()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
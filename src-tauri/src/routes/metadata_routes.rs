@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use diesel::{
+    insert_into,
+    prelude::*,
+    result::Error,
+    sql_types::{Integer, Text},
+};
+
+use crate::{
+    models::{GameMetadata, GameRelease, GameWithRoms},
+    schemas::{
+        developers_table, game_details_table, game_developers_table, game_genres_table,
+        game_publishers_table, game_releases_table, genres_table, publishers_table,
+    },
+};
+
+/// Loads the metadata of the games
+pub fn get_metadata(
+    conn: &mut SqliteConnection,
+    game_ids: &[i32],
+) -> Result<HashMap<i32, GameMetadata>, Error> {
+    let mut metadata: HashMap<i32, GameMetadata> = HashMap::new();
+
+    let developers: Vec<(i32, String)> = game_developers_table::table
+        .inner_join(developers_table::table)
+        .filter(game_developers_table::game_id.eq_any(game_ids))
+        .select((game_developers_table::game_id, developers_table::name))
+        .order(developers_table::name)
+        .load(conn)?;
+    for (game_id, name) in developers {
+        metadata.entry(game_id).or_default().developers.push(name);
+    }
+
+    let publishers: Vec<(i32, String)> = game_publishers_table::table
+        .inner_join(publishers_table::table)
+        .filter(game_publishers_table::game_id.eq_any(game_ids))
+        .select((game_publishers_table::game_id, publishers_table::name))
+        .order(publishers_table::name)
+        .load(conn)?;
+    for (game_id, name) in publishers {
+        metadata.entry(game_id).or_default().publishers.push(name);
+    }
+
+    let genres: Vec<(i32, String)> = game_genres_table::table
+        .inner_join(genres_table::table)
+        .filter(game_genres_table::game_id.eq_any(game_ids))
+        .select((game_genres_table::game_id, genres_table::name))
+        .order(genres_table::name)
+        .load(conn)?;
+    for (game_id, name) in genres {
+        metadata.entry(game_id).or_default().genres.push(name);
+    }
+
+    let releases: Vec<(i32, String, String)> = game_releases_table::table
+        .filter(game_releases_table::game_id.eq_any(game_ids))
+        .select((
+            game_releases_table::game_id,
+            game_releases_table::region,
+            game_releases_table::date,
+        ))
+        .order((game_releases_table::date, game_releases_table::region))
+        .load(conn)?;
+    for (game_id, region, date) in releases {
+        metadata
+            .entry(game_id)
+            .or_default()
+            .releases
+            .push(GameRelease { region, date });
+    }
+
+    let details: Vec<(i32, Option<String>, Option<i32>)> = game_details_table::table
+        .filter(game_details_table::game_id.eq_any(game_ids))
+        .select((
+            game_details_table::game_id,
+            game_details_table::description,
+            game_details_table::players,
+        ))
+        .load(conn)?;
+    for (game_id, description, players) in details {
+        let entry = metadata.entry(game_id).or_default();
+        entry.description = description;
+        entry.players = players;
+    }
+
+    Ok(metadata)
+}
+
+/// Fills in the metadata of the loaded games
+pub fn add_metadata(conn: &mut SqliteConnection, games: &mut [GameWithRoms]) -> Result<(), Error> {
+    let ids: Vec<i32> = games.iter().map(|game_roms| game_roms.game.id).collect();
+    let mut metadata = get_metadata(conn, &ids)?;
+
+    for game_roms in games.iter_mut() {
+        game_roms.metadata = metadata.remove(&game_roms.game.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Links the game to the named rows of `table`, e.g. developers, creating missing ones
+fn link_names(
+    conn: &mut SqliteConnection,
+    game_id: i32,
+    names: &[String],
+    table: &str,
+    link_table: &str,
+    link_column: &str,
+) -> Result<(), Error> {
+    diesel::sql_query(format!("DELETE FROM {link_table} WHERE game_id = ?"))
+        .bind::<Integer, _>(game_id)
+        .execute(conn)?;
+
+    for name in names {
+        diesel::sql_query(format!(
+            "INSERT INTO {table} (name) VALUES (?) ON CONFLICT (name) DO NOTHING"
+        ))
+        .bind::<Text, _>(name)
+        .execute(conn)?;
+        diesel::sql_query(format!(
+            "INSERT OR IGNORE INTO {link_table} (game_id, {link_column}) \
+             SELECT ?, id FROM {table} WHERE name = ?"
+        ))
+        .bind::<Integer, _>(game_id)
+        .bind::<Text, _>(name)
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the metadata of the game. Developers, publishers and genres are created by name.
+pub fn set_metadata(
+    conn: &mut SqliteConnection,
+    game_id: i32,
+    metadata: &GameMetadata,
+) -> Result<(), Error> {
+    conn.transaction(|conn| {
+        link_names(
+            conn,
+            game_id,
+            &metadata.developers,
+            "developers",
+            "game_developers",
+            "developer_id",
+        )?;
+        link_names(
+            conn,
+            game_id,
+            &metadata.publishers,
+            "publishers",
+            "game_publishers",
+            "publisher_id",
+        )?;
+        link_names(
+            conn,
+            game_id,
+            &metadata.genres,
+            "genres",
+            "game_genres",
+            "genre_id",
+        )?;
+
+        diesel::delete(game_releases_table::table.filter(game_releases_table::game_id.eq(game_id)))
+            .execute(conn)?;
+        for release in &metadata.releases {
+            insert_into(game_releases_table::table)
+                .values((
+                    game_releases_table::game_id.eq(game_id),
+                    game_releases_table::region.eq(&release.region),
+                    game_releases_table::date.eq(&release.date),
+                ))
+                .on_conflict((game_releases_table::game_id, game_releases_table::region))
+                .do_update()
+                .set(game_releases_table::date.eq(&release.date))
+                .execute(conn)?;
+        }
+
+        insert_into(game_details_table::table)
+            .values((
+                game_details_table::game_id.eq(game_id),
+                game_details_table::description.eq(&metadata.description),
+                game_details_table::players.eq(metadata.players),
+            ))
+            .on_conflict(game_details_table::game_id)
+            .do_update()
+            .set((
+                game_details_table::description.eq(&metadata.description),
+                game_details_table::players.eq(metadata.players),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
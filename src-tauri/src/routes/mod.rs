@@ -1,3 +1,11 @@
+pub mod bios_routes;
+pub mod compression_routes;
 pub mod console_routes;
+pub mod disc_routes;
 pub mod games_routes;
+pub mod maintenance_routes;
+pub mod metadata_routes;
+pub mod patch_routes;
 pub mod rom_routes;
+pub mod save_routes;
+pub mod search_routes;
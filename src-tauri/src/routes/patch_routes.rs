@@ -0,0 +1,80 @@
+use diesel::{insert_into, prelude::*, result::Error};
+
+use crate::{
+    models::{Console, NewPatch, NewPatchedRom, Patch, PatchWithPatchedRoms, PatchedRom, Rom},
+    schemas::{consoles_table, games_table, patched_roms_table, patches_table, roms_table},
+};
+
+/// Returns the rom together with the console of its game, to find the rom's file
+pub fn get_rom_with_console(
+    conn: &mut SqliteConnection,
+    rom_id: i32,
+) -> Result<(Rom, Console), Error> {
+    roms_table::table
+        .find(rom_id)
+        .inner_join(games_table::table.inner_join(consoles_table::table))
+        .select((Rom::as_select(), Console::as_select()))
+        .first(conn)
+}
+
+pub fn find_rom_by_md5(conn: &mut SqliteConnection, md5: &str) -> Result<Option<Rom>, Error> {
+    roms_table::table
+        .filter(roms_table::md5.eq(md5.to_lowercase()))
+        .select(Rom::as_select())
+        .first(conn)
+        .optional()
+}
+
+pub fn upsert_patch(conn: &mut SqliteConnection, new_patch: &NewPatch) -> Result<Patch, Error> {
+    insert_into(patches_table::table)
+        .values(new_patch)
+        .on_conflict(patches_table::path)
+        .do_update()
+        .set(new_patch)
+        .get_result(conn)
+}
+
+pub fn upsert_patched_rom(
+    conn: &mut SqliteConnection,
+    new_patched_rom: &NewPatchedRom,
+) -> Result<PatchedRom, Error> {
+    insert_into(patched_roms_table::table)
+        .values(new_patched_rom)
+        .on_conflict(patched_roms_table::path)
+        .do_update()
+        .set(new_patched_rom)
+        .get_result(conn)
+}
+
+pub fn get_patch(conn: &mut SqliteConnection, patch_id: i32) -> Result<Patch, Error> {
+    patches_table::table
+        .find(patch_id)
+        .select(Patch::as_select())
+        .first(conn)
+}
+
+pub fn get_patches_for_rom(
+    conn: &mut SqliteConnection,
+    rom_id: i32,
+) -> Result<Vec<PatchWithPatchedRoms>, Error> {
+    let patches = patches_table::table
+        .filter(patches_table::rom_id.eq(rom_id))
+        .order(patches_table::path)
+        .select(Patch::as_select())
+        .load(conn)?;
+
+    let patched_roms = PatchedRom::belonging_to(&patches)
+        .select(PatchedRom::as_select())
+        .order(patched_roms_table::path)
+        .load(conn)?;
+
+    Ok(patched_roms
+        .grouped_by(&patches)
+        .into_iter()
+        .zip(patches)
+        .map(|(patched_roms, patch)| PatchWithPatchedRoms {
+            patch,
+            patched_roms,
+        })
+        .collect())
+}
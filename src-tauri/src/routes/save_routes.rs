@@ -0,0 +1,84 @@
+use diesel::{insert_into, prelude::*, result::Error};
+
+use crate::{
+    models::{Game, NewSave, NewSaveBackup, Rom, Save, SaveBackup, SaveWithBackups},
+    schemas::{games_table, roms_table, save_backups_table, saves_table},
+};
+
+/// Returns all roms which aren't removed together with the console id of their game, used to
+/// match save files
+pub fn get_roms_with_console_id(conn: &mut SqliteConnection) -> Result<Vec<(Rom, i32)>, Error> {
+    roms_table::table
+        .inner_join(games_table::table)
+        .filter(roms_table::removed.eq(false))
+        .select((Rom::as_select(), games_table::console_id))
+        .load(conn)
+}
+
+pub fn upsert_save(conn: &mut SqliteConnection, new_save: &NewSave) -> Result<Save, Error> {
+    insert_into(saves_table::table)
+        .values(new_save)
+        .on_conflict(saves_table::path)
+        .do_update()
+        .set(new_save)
+        .get_result(conn)
+}
+
+/// Inserts the backup, returns `None` if this version of the save is already backed up
+pub fn insert_backup(
+    conn: &mut SqliteConnection,
+    backup: &NewSaveBackup,
+) -> Result<Option<SaveBackup>, Error> {
+    insert_into(save_backups_table::table)
+        .values(backup)
+        .on_conflict_do_nothing()
+        .get_result(conn)
+        .optional()
+}
+
+pub fn has_backup(conn: &mut SqliteConnection, save_id: i32, md5: &str) -> Result<bool, Error> {
+    diesel::select(diesel::dsl::exists(
+        save_backups_table::table
+            .filter(save_backups_table::save_id.eq(save_id))
+            .filter(save_backups_table::md5.eq(md5)),
+    ))
+    .get_result(conn)
+}
+
+pub fn get_backup(
+    conn: &mut SqliteConnection,
+    backup_id: i32,
+) -> Result<(SaveBackup, Save), Error> {
+    save_backups_table::table
+        .find(backup_id)
+        .inner_join(saves_table::table)
+        .select((SaveBackup::as_select(), Save::as_select()))
+        .first(conn)
+}
+
+pub fn get_saves_for_game(
+    conn: &mut SqliteConnection,
+    game_id: i32,
+) -> Result<Vec<SaveWithBackups>, Error> {
+    let game = games_table::table
+        .find(game_id)
+        .select(Game::as_select())
+        .first(conn)?;
+
+    let saves = Save::belonging_to(&game)
+        .select(Save::as_select())
+        .order(saves_table::path)
+        .load(conn)?;
+
+    let backups = SaveBackup::belonging_to(&saves)
+        .select(SaveBackup::as_select())
+        .order(save_backups_table::created_at.desc())
+        .load(conn)?;
+
+    Ok(backups
+        .grouped_by(&saves)
+        .into_iter()
+        .zip(saves)
+        .map(|(backups, save)| SaveWithBackups { save, backups })
+        .collect())
+}
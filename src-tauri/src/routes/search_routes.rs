@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use diesel::{
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Double, Integer, Text},
+};
+use serde::Serialize;
+
+use crate::{
+    models::{Console, Game, GameWithRoms, Rom},
+    routes::metadata_routes,
+    schemas::{consoles_table, games_table, roms_table},
+};
+
+/// Articles No-Intro moves to the end of titles, e.g. `Legend of Zelda, The`
+const ARTICLES: [&str; 3] = ["the", "a", "an"];
+
+const ROMAN_NUMERALS: [&str; 20] = [
+    "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix", "x", "xi", "xii", "xiii", "xiv", "xv",
+    "xvi", "xvii", "xviii", "xix", "xx",
+];
+
+/// A game whose title or rom titles match the search
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub console: Console,
+    /// the matching roms, all roms of the game if only its title matched
+    #[serde(flatten)]
+    pub game: GameWithRoms,
+    /// bm25 score of the best match, lower is better
+    pub rank: f64,
+}
+
+#[derive(QueryableByName)]
+struct TitleMatch {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    #[diesel(sql_type = Double)]
+    rank: f64,
+}
+
+/// Turns the words of a search into an FTS5 query. Articles are left out, numerals match
+/// both their roman and arabic form and the other words match as prefixes. Diacritics are
+/// removed by the tokenizer. `None` if nothing is left to search for.
+pub(crate) fn match_expression(search: &str) -> Option<String> {
+    let words: Vec<String> = search
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    // a search for just an article still looks for it
+    let keep_articles = words.iter().all(|word| ARTICLES.contains(&word.as_str()));
+
+    let terms: Vec<String> = words
+        .iter()
+        .filter(|word| keep_articles || !ARTICLES.contains(&word.as_str()))
+        .map(|word| {
+            let roman = ROMAN_NUMERALS.iter().position(|numeral| numeral == word);
+            let arabic = word
+                .parse::<usize>()
+                .ok()
+                .filter(|number| (1..=ROMAN_NUMERALS.len()).contains(number));
+
+            match (roman, arabic) {
+                (Some(index), _) => format!("({} OR {})", word, index + 1),
+                (_, Some(number)) => format!("({} OR {})", word, ROMAN_NUMERALS[number - 1]),
+                _ => format!("\"{}\"*", word),
+            }
+        })
+        .collect();
+
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn title_matches(
+    conn: &mut SqliteConnection,
+    table: &str,
+    expression: &str,
+    limit: i64,
+) -> Result<Vec<TitleMatch>, Error> {
+    diesel::sql_query(format!(
+        "SELECT rowid AS id, bm25({table}) AS rank FROM {table} WHERE {table} MATCH ? \
+         ORDER BY rank LIMIT ?"
+    ))
+    .bind::<Text, _>(expression)
+    .bind::<BigInt, _>(limit)
+    .load(conn)
+}
+
+/// Searches the titles of the games and roms of all consoles, best matches first
+pub fn search_titles(
+    conn: &mut SqliteConnection,
+    search: &str,
+    limit: i64,
+) -> Result<Vec<SearchHit>, Error> {
+    let Some(expression) = match_expression(search) else {
+        return Ok(Vec::new());
+    };
+    let game_matches = title_matches(conn, "games_search", &expression, limit)?;
+    let rom_matches = title_matches(conn, "roms_search", &expression, limit)?;
+
+    let matched_roms: Vec<Rom> = roms_table::table
+        .filter(roms_table::id.eq_any(rom_matches.iter().map(|rom| rom.id)))
+        .filter(roms_table::removed.eq(false))
+        .select(Rom::as_select())
+        .load(conn)?;
+
+    // best rank of each game, by its title or one of its roms
+    let mut ranks: HashMap<i32, f64> = HashMap::new();
+    for game in &game_matches {
+        ranks.insert(game.id, game.rank);
+    }
+    for rom in &rom_matches {
+        if let Some(game_id) = matched_roms
+            .iter()
+            .find(|matched| matched.id == rom.id)
+            .map(|matched| matched.game_id)
+        {
+            let rank = ranks.entry(game_id).or_insert(rom.rank);
+            *rank = rank.min(rom.rank);
+        }
+    }
+
+    let mut ranked: Vec<(i32, f64)> = ranks.into_iter().collect();
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    ranked.truncate(limit.max(0) as usize);
+
+    let games: Vec<(Game, Console)> = games_table::table
+        .inner_join(consoles_table::table)
+        .filter(games_table::id.eq_any(ranked.iter().map(|(id, _)| *id)))
+        .filter(games_table::removed.eq(false))
+        .select((Game::as_select(), Console::as_select()))
+        .load(conn)?;
+
+    let title_only: Vec<i32> = games
+        .iter()
+        .map(|(game, _)| game.id)
+        .filter(|id| !matched_roms.iter().any(|rom| rom.game_id == *id))
+        .collect();
+    let all_roms: Vec<Rom> = roms_table::table
+        .filter(roms_table::game_id.eq_any(&title_only))
+        .filter(roms_table::removed.eq(false))
+        .select(Rom::as_select())
+        .load(conn)?;
+
+    let ids: Vec<i32> = games.iter().map(|(game, _)| game.id).collect();
+    let mut metadata = metadata_routes::get_metadata(conn, &ids)?;
+    let mut games: HashMap<i32, (Game, Console)> = games
+        .into_iter()
+        .map(|(game, console)| (game.id, (game, console)))
+        .collect();
+
+    Ok(ranked
+        .into_iter()
+        .filter_map(|(id, rank)| {
+            let (game, console) = games.remove(&id)?;
+            let roms = matched_roms
+                .iter()
+                .chain(&all_roms)
+                .filter(|rom| rom.game_id == id)
+                .cloned()
+                .collect();
+
+            Some(SearchHit {
+                console,
+                game: GameWithRoms {
+                    game,
+                    roms,
+                    metadata: metadata.remove(&id).unwrap_or_default(),
+                },
+                rank,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use crate::db::test_connection;
+
+    use super::*;
+
+    #[test]
+    fn test_match_expression() {
+        assert_eq!(
+            Some("\"zelda\"* \"link\"* \"past\"*".to_string()),
+            match_expression("The Zelda: a link past")
+        );
+        assert_eq!(
+            Some("\"final\"* \"fantasy\"* (7 OR vii)".to_string()),
+            match_expression("Final Fantasy 7")
+        );
+        assert_eq!(
+            Some("\"mega\"* \"man\"* (x OR 10)".to_string()),
+            match_expression("MEGA MAN X")
+        );
+        assert_eq!(Some("\"the\"*".to_string()), match_expression("the"));
+        assert_eq!(None, match_expression(" - ! "));
+    }
+
+    #[test]
+    fn test_search_titles() {
+        let conn = &mut test_connection();
+        conn.batch_execute(
+            "INSERT INTO games (id, title, console_id)
+                 SELECT 1, 'Legend of Zelda, The - A Link to the Past', id FROM consoles
+                 WHERE abbreviation = 'snes';
+             INSERT INTO games (id, title, console_id)
+                 SELECT 2, 'Legend of Zelda, The - Link''s Awakening', id FROM consoles
+                 WHERE abbreviation = 'gb';
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES
+                 (1, 'Legend of Zelda, The - A Link to the Past (USA).sfc', '', 0, 1),
+                 (2, 'Legend of Zelda, The - Link''s Awakening (USA, Europe).gb', '', 0, 2);",
+        )
+        .unwrap();
+
+        let hits = search_titles(conn, "zelda link past", 10).unwrap();
+        assert_eq!(1, hits.len());
+        let hit = hits
+            .iter()
+            .find(|hit| hit.game.game.title == "Legend of Zelda, The - A Link to the Past")
+            .expect("no hit for A Link to the Past");
+        assert_eq!("Super Nintendo Entertainment System", hit.console.name);
+        assert!(!hit.game.roms.is_empty());
+    }
+}
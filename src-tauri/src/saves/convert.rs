@@ -0,0 +1,153 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+const DSV_FOOTER_TEXT: &[u8] =
+    b"|<--Snip above here to create a raw sav by excluding this DeSmuME savedata footer:";
+const DSV_COOKIE: &[u8] = b"|-DESMUME SAVE-|";
+/// footer text, six little endian u32 fields and the cookie
+const DSV_FOOTER_SIZE: usize = DSV_FOOTER_TEXT.len() + 6 * 4 + DSV_COOKIE.len();
+
+/// Save formats which can be converted into each other without touching the save data
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveFormat {
+    /// RetroArch battery save, the raw save memory
+    Srm,
+    /// raw save memory as most standalone emulators and flashcarts write it
+    Sav,
+    /// DeSmuME save, raw save memory followed by a footer
+    Dsv,
+}
+
+impl SaveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SaveFormat::Srm => "srm",
+            SaveFormat::Sav => "sav",
+            SaveFormat::Dsv => "dsv",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "srm" => Some(SaveFormat::Srm),
+            "sav" => Some(SaveFormat::Sav),
+            "dsv" => Some(SaveFormat::Dsv),
+            _ => None,
+        }
+    }
+}
+
+/// Strips the DeSmuME footer, returns `None` if the data is not a DeSmuME save
+pub fn dsv_to_raw(data: &[u8]) -> Option<&[u8]> {
+    if data.len() < DSV_FOOTER_SIZE || !data.ends_with(DSV_COOKIE) {
+        return None;
+    }
+
+    let footer_start = data.len() - DSV_FOOTER_SIZE;
+    data[footer_start..]
+        .starts_with(DSV_FOOTER_TEXT)
+        .then(|| &data[..footer_start])
+}
+
+/// Appends a DeSmuME footer to raw save memory. The save type is left at 0,
+/// so DeSmuME detects it on its own.
+pub fn raw_to_dsv(data: &[u8]) -> Vec<u8> {
+    let size = data.len() as u32;
+    let address_size: u32 = match size {
+        0..=512 => 1,
+        513..=65536 => 2,
+        _ => 3,
+    };
+
+    let mut dsv = Vec::with_capacity(data.len() + DSV_FOOTER_SIZE);
+    dsv.extend_from_slice(data);
+    dsv.extend_from_slice(DSV_FOOTER_TEXT);
+    // actual size, padded size, type, address size, memory size, version
+    for field in [size, size, 0, address_size, size, 0] {
+        dsv.extend_from_slice(&field.to_le_bytes());
+    }
+    dsv.extend_from_slice(DSV_COOKIE);
+
+    dsv
+}
+
+pub fn convert(data: &[u8], from: SaveFormat, to: SaveFormat) -> io::Result<Vec<u8>> {
+    let raw = match from {
+        SaveFormat::Dsv => dsv_to_raw(data)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a DeSmuME save file"))?,
+        SaveFormat::Srm | SaveFormat::Sav => data,
+    };
+
+    Ok(match to {
+        SaveFormat::Dsv => raw_to_dsv(raw),
+        SaveFormat::Srm | SaveFormat::Sav => raw.to_vec(),
+    })
+}
+
+/// Writes the converted save next to the original, never overwriting an existing file
+pub fn convert_file(path: &Path, to: SaveFormat) -> io::Result<PathBuf> {
+    let from = SaveFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported save format: {}", path.display()),
+        )
+    })?;
+
+    let output = path.with_extension(to.extension());
+    if output.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", output.display()),
+        ));
+    }
+
+    fs::write(&output, convert(&fs::read(path)?, from, to)?)?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dsv_round_trip() {
+        let raw: Vec<u8> = (0..=255).cycle().take(8192).collect();
+
+        let dsv = raw_to_dsv(&raw);
+
+        assert_eq!(raw.len() + 122, dsv.len());
+        assert!(dsv.ends_with(b"|-DESMUME SAVE-|"));
+        assert_eq!(Some(raw.as_slice()), dsv_to_raw(&dsv));
+    }
+
+    #[test]
+    fn test_dsv_to_raw_rejects_raw_data() {
+        assert_eq!(None, dsv_to_raw(&[0; 512]));
+    }
+
+    #[test]
+    fn test_convert() {
+        let raw = vec![1, 2, 3, 4];
+
+        assert_eq!(
+            raw,
+            convert(&raw, SaveFormat::Srm, SaveFormat::Sav).unwrap()
+        );
+        assert_eq!(
+            raw,
+            convert(
+                &convert(&raw, SaveFormat::Sav, SaveFormat::Dsv).unwrap(),
+                SaveFormat::Dsv,
+                SaveFormat::Srm
+            )
+            .unwrap()
+        );
+        assert!(convert(&raw, SaveFormat::Dsv, SaveFormat::Sav).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SavesConfig,
+    file_utils::{unix_timestamp, walk_files},
+    hashing::md5_file,
+    models::{Console, NewSave, NewSaveBackup, Rom, Save, SaveBackup},
+    routes::save_routes,
+};
+
+pub mod convert;
+
+/// Files RetroArch and frontends put next to saves which are not save data
+const IGNORED_EXTENSIONS: [&str; 4] = ["png", "jpg", "txt", "xml"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SaveFolder {
+    pub path: String,
+    pub layout: SaveFolderLayout,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveFolderLayout {
+    /// RetroArch `saves/`, battery saves directly or in per core folders
+    RetroArchSaves,
+    /// RetroArch `states/`, every file is a save state
+    RetroArchStates,
+    /// EmulationStation style `<folder>/<console abbreviation>/<save>`
+    EmulationStation,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveKind {
+    Save,
+    State,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SaveScanSummary {
+    pub scanned: usize,
+    pub matched: usize,
+    pub backed_up: usize,
+    pub unmatched: Vec<PathBuf>,
+}
+
+impl SaveKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SaveKind::Save => "save",
+            SaveKind::State => "state",
+        }
+    }
+
+    fn detect(file_name: &str, layout: SaveFolderLayout) -> Self {
+        let extension = Path::new(file_name.strip_suffix(".auto").unwrap_or(file_name))
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if layout == SaveFolderLayout::RetroArchStates || extension.starts_with("state") {
+            SaveKind::State
+        } else {
+            SaveKind::Save
+        }
+    }
+}
+
+/// Returns the name a save file shares with its rom, e.g. `Game (USA)` for
+/// `Game (USA).srm`, `Game (USA).state3` and `Game (USA).state.auto`
+pub fn save_stem(file_name: &str) -> &str {
+    let name = file_name.strip_suffix(".auto").unwrap_or(file_name);
+
+    name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name)
+}
+
+/// Roms indexed by file stem and md5 to find the rom a save belongs to
+struct RomIndex {
+    by_stem: HashMap<String, Vec<(Rom, i32)>>,
+    by_md5: HashMap<String, Rom>,
+}
+
+impl RomIndex {
+    fn new(roms: Vec<(Rom, i32)>) -> Self {
+        let mut by_stem: HashMap<String, Vec<(Rom, i32)>> = HashMap::new();
+        let mut by_md5 = HashMap::new();
+
+        for (rom, console_id) in roms {
+            if !rom.md5.is_empty() {
+                by_md5.insert(rom.md5.to_lowercase(), rom.clone());
+            }
+
+            let stem = Path::new(&rom.title)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            by_stem.entry(stem).or_default().push((rom, console_id));
+        }
+
+        RomIndex { by_stem, by_md5 }
+    }
+
+    /// Matches by file stem, preferring roms of the given console, and falls back to saves
+    /// named after the rom's md5
+    fn find(&self, stem: &str, console_id: Option<i32>) -> Option<&Rom> {
+        let stem = stem.to_lowercase();
+
+        if let Some(candidates) = self.by_stem.get(&stem) {
+            let rom = candidates
+                .iter()
+                .find(|(_, rom_console_id)| Some(*rom_console_id) == console_id)
+                .or(candidates.first())
+                .map(|(rom, _)| rom);
+
+            if rom.is_some() {
+                return rom;
+            }
+        }
+
+        self.by_md5.get(&stem)
+    }
+}
+
+/// Scans all configured save folders, links the saves to their roms and backs up
+/// every save version which isn't in the backup store yet
+pub fn scan(
+    conn: &mut SqliteConnection,
+    config: &SavesConfig,
+    consoles: &[Console],
+) -> io::Result<SaveScanSummary> {
+    let backup_dir = config.backup_dir()?;
+    let roms = save_routes::get_roms_with_console_id(conn).map_err(io::Error::other)?;
+    let index = RomIndex::new(roms);
+    let mut summary = SaveScanSummary::default();
+
+    for folder in &config.folders {
+        let folder_path = Path::new(&folder.path);
+        if !folder_path.is_dir() {
+            continue;
+        }
+
+        for path in walk_files(folder_path)? {
+            let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let is_ignored = Path::new(file_name)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    IGNORED_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+                });
+            if is_ignored {
+                continue;
+            }
+
+            summary.scanned += 1;
+
+            let console_id = match folder.layout {
+                SaveFolderLayout::EmulationStation => console_folder(folder_path, &path)
+                    .and_then(|abbreviation| {
+                        consoles
+                            .iter()
+                            .find(|console| console.abbreviation == abbreviation)
+                    })
+                    .map(|console| console.id),
+                _ => None,
+            };
+
+            let rom = index.find(save_stem(file_name), console_id);
+            match rom {
+                Some(_) => summary.matched += 1,
+                None => summary.unmatched.push(path.clone()),
+            }
+
+            let metadata = fs::metadata(&path)?;
+            let md5 = md5_file(&path)?;
+            let path_string = path.to_string_lossy();
+
+            let save = save_routes::upsert_save(
+                conn,
+                &NewSave {
+                    path: &path_string,
+                    kind: SaveKind::detect(file_name, folder.layout).as_str(),
+                    md5: &md5,
+                    size: metadata.len() as i64,
+                    modified_at: unix_timestamp(metadata.modified()?),
+                    rom_id: rom.map(|rom| rom.id),
+                    game_id: rom.map(|rom| rom.game_id),
+                },
+            )
+            .map_err(io::Error::other)?;
+
+            if backup(conn, &save, &backup_dir)?.is_some() {
+                summary.backed_up += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Name of the first folder below the save folder, the console in EmulationStation layouts
+fn console_folder<'a>(folder: &Path, path: &'a Path) -> Option<&'a str> {
+    path.strip_prefix(folder)
+        .ok()?
+        .components()
+        .next()
+        .filter(|_| path.parent() != Some(folder))?
+        .as_os_str()
+        .to_str()
+}
+
+/// Copies the save's current version into the backup store, unless it's already backed up
+pub fn backup(
+    conn: &mut SqliteConnection,
+    save: &Save,
+    backup_dir: &Path,
+) -> io::Result<Option<SaveBackup>> {
+    if save_routes::has_backup(conn, save.id, &save.md5).map_err(io::Error::other)? {
+        return Ok(None);
+    }
+
+    let source = Path::new(&save.path);
+    let file_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let created_at = unix_timestamp(SystemTime::now());
+
+    let directory = backup_dir.join(save_stem(&file_name));
+    fs::create_dir_all(&directory)?;
+    let backup_path = directory.join(format!(
+        "{}-{}-{}",
+        created_at,
+        &save.md5[..8.min(save.md5.len())],
+        file_name
+    ));
+    fs::copy(source, &backup_path)?;
+
+    save_routes::insert_backup(
+        conn,
+        &NewSaveBackup {
+            save_id: save.id,
+            path: &backup_path.to_string_lossy(),
+            md5: &save.md5,
+            created_at,
+        },
+    )
+    .map_err(io::Error::other)
+}
+
+/// Puts a backed up version back in place of the save, after backing up the current file
+pub fn restore(
+    conn: &mut SqliteConnection,
+    backup_id: i32,
+    config: &SavesConfig,
+) -> io::Result<PathBuf> {
+    let (save_backup, save) = save_routes::get_backup(conn, backup_id).map_err(io::Error::other)?;
+    let save_path = PathBuf::from(&save.path);
+
+    if save_path.is_file() {
+        let current = Save {
+            md5: md5_file(&save_path)?,
+            ..save
+        };
+        backup(conn, &current, &config.backup_dir()?)?;
+    }
+
+    if let Some(parent) = save_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(&save_backup.path, &save_path)?;
+
+    Ok(save_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(id: i32, title: &str, md5: &str) -> Rom {
+        Rom {
+            id,
+            title: title.to_string(),
+            md5: md5.to_string(),
+            game_id: id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_stem() {
+        assert_eq!(
+            "Secret of Mana (USA)",
+            save_stem("Secret of Mana (USA).srm")
+        );
+        assert_eq!(
+            "Secret of Mana (USA)",
+            save_stem("Secret of Mana (USA).state3")
+        );
+        assert_eq!(
+            "Secret of Mana (USA)",
+            save_stem("Secret of Mana (USA).state.auto")
+        );
+    }
+
+    #[test]
+    fn test_save_kind() {
+        let layout = SaveFolderLayout::RetroArchSaves;
+
+        assert_eq!(SaveKind::Save, SaveKind::detect("Game.srm", layout));
+        assert_eq!(SaveKind::State, SaveKind::detect("Game.state2", layout));
+        assert_eq!(SaveKind::State, SaveKind::detect("Game.state.auto", layout));
+        assert_eq!(
+            SaveKind::State,
+            SaveKind::detect("Game.ss0", SaveFolderLayout::RetroArchStates)
+        );
+    }
+
+    #[test]
+    fn test_rom_index() {
+        let index = RomIndex::new(vec![
+            (
+                rom(1, "Tetris (World).gb", "982ed5d2b12a0377eb14bcdc4123744e"),
+                1,
+            ),
+            (rom(2, "Tetris (World).gb", "0000"), 2),
+        ]);
+
+        assert_eq!(
+            Some(1),
+            index.find("tetris (world)", None).map(|rom| rom.id)
+        );
+        assert_eq!(
+            Some(2),
+            index.find("Tetris (World)", Some(2)).map(|rom| rom.id)
+        );
+        assert_eq!(
+            Some(1),
+            index
+                .find("982ED5D2B12A0377EB14BCDC4123744E", None)
+                .map(|rom| rom.id)
+        );
+        assert_eq!(None, index.find("Unknown", None));
+    }
+
+    #[test]
+    fn test_console_folder() {
+        let folder = Path::new("/saves");
+
+        assert_eq!(
+            Some("snes"),
+            console_folder(folder, Path::new("/saves/snes/Game.srm"))
+        );
+        assert_eq!(None, console_folder(folder, Path::new("/saves/Game.srm")));
+    }
+}
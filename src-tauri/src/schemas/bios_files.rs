@@ -0,0 +1,14 @@
+diesel::table! {
+    bios_files (id) {
+        id -> Integer,
+        console_id -> Integer,
+        name -> Text,
+        size -> BigInt,
+        crc -> Text,
+        md5 -> Text,
+        sha1 -> Text,
+        required -> Bool,
+    }
+}
+
+pub use self::bios_files::dsl::*;
@@ -0,0 +1,16 @@
+diesel::table! {
+    compressed_roms (id) {
+        id -> Integer,
+        rom_id -> Integer,
+        path -> Text,
+        format -> Text,
+        block_size -> Integer,
+        compressed_size -> BigInt,
+        size -> BigInt,
+        crc -> Text,
+        md5 -> Text,
+        sha1 -> Text,
+    }
+}
+
+pub use self::compressed_roms::dsl::*;
@@ -0,0 +1,9 @@
+diesel::table! {
+    game_details (game_id) {
+        game_id -> Integer,
+        description -> Nullable<Text>,
+        players -> Nullable<Integer>,
+    }
+}
+
+pub use self::game_details::dsl::*;
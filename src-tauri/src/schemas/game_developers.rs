@@ -0,0 +1,8 @@
+diesel::table! {
+    game_developers (game_id, developer_id) {
+        game_id -> Integer,
+        developer_id -> Integer,
+    }
+}
+
+pub use self::game_developers::dsl::*;
@@ -0,0 +1,8 @@
+diesel::table! {
+    game_genres (game_id, genre_id) {
+        game_id -> Integer,
+        genre_id -> Integer,
+    }
+}
+
+pub use self::game_genres::dsl::*;
@@ -0,0 +1,8 @@
+diesel::table! {
+    game_publishers (game_id, publisher_id) {
+        game_id -> Integer,
+        publisher_id -> Integer,
+    }
+}
+
+pub use self::game_publishers::dsl::*;
@@ -0,0 +1,9 @@
+diesel::table! {
+    game_releases (game_id, region) {
+        game_id -> Integer,
+        region -> Text,
+        date -> Text,
+    }
+}
+
+pub use self::game_releases::dsl::*;
@@ -5,6 +5,9 @@ diesel::table! {
         id -> Integer,
         title -> Text,
         console_id -> Integer,
+        serial -> Text,
+        clone_of -> Nullable<Text>,
+        removed -> Bool,
     }
 }
 
@@ -0,0 +1,8 @@
+diesel::table! {
+    genres (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+pub use self::genres::dsl::*;
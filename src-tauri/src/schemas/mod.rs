@@ -1,16 +1,44 @@
+pub mod bios_files;
+pub mod compressed_roms;
 pub mod consoles;
 pub mod developers;
+pub mod game_details;
+pub mod game_developers;
+pub mod game_genres;
+pub mod game_publishers;
+pub mod game_releases;
 pub mod games;
+pub mod genres;
+pub mod patched_roms;
+pub mod patches;
+pub mod publishers;
 pub mod regions;
 pub mod rom_regions;
+pub mod rom_tracks;
 pub mod roms;
+pub mod save_backups;
+pub mod saves;
 
+pub use bios_files::bios_files as bios_files_table;
+pub use compressed_roms::compressed_roms as compressed_roms_table;
 pub use consoles::consoles as consoles_table;
 pub use developers::developers as developers_table;
+pub use game_details::game_details as game_details_table;
+pub use game_developers::game_developers as game_developers_table;
+pub use game_genres::game_genres as game_genres_table;
+pub use game_publishers::game_publishers as game_publishers_table;
+pub use game_releases::game_releases as game_releases_table;
 pub use games::games as games_table;
+pub use genres::genres as genres_table;
+pub use patched_roms::patched_roms as patched_roms_table;
+pub use patches::patches as patches_table;
+pub use publishers::publishers as publishers_table;
 pub use regions::regions as regions_table;
 pub use rom_regions::rom_regions as rom_regions_table;
+pub use rom_tracks::rom_tracks as rom_tracks_table;
 pub use roms::roms as roms_table;
+pub use save_backups::save_backups as save_backups_table;
+pub use saves::saves as saves_table;
 
 diesel::allow_tables_to_appear_in_same_query!(
     consoles_table,
@@ -18,10 +46,41 @@ diesel::allow_tables_to_appear_in_same_query!(
     developers_table,
     regions_table,
     roms_table,
-    rom_regions_table
+    rom_regions_table,
+    saves_table,
+    save_backups_table,
+    bios_files_table,
+    patches_table,
+    patched_roms_table,
+    rom_tracks_table,
+    compressed_roms_table,
+    publishers_table,
+    genres_table,
+    game_developers_table,
+    game_publishers_table,
+    game_genres_table,
+    game_releases_table,
+    game_details_table
 );
 
 diesel::joinable!(rom_regions_table -> regions_table (region_id));
 diesel::joinable!(rom_regions_table -> roms_table (rom_id));
 diesel::joinable!(roms_table -> games_table (game_id));
 diesel::joinable!(games_table -> consoles_table (console_id));
+diesel::joinable!(saves_table -> roms_table (rom_id));
+diesel::joinable!(saves_table -> games_table (game_id));
+diesel::joinable!(save_backups_table -> saves_table (save_id));
+diesel::joinable!(bios_files_table -> consoles_table (console_id));
+diesel::joinable!(patches_table -> roms_table (rom_id));
+diesel::joinable!(patched_roms_table -> roms_table (rom_id));
+diesel::joinable!(patched_roms_table -> patches_table (patch_id));
+diesel::joinable!(rom_tracks_table -> roms_table (rom_id));
+diesel::joinable!(compressed_roms_table -> roms_table (rom_id));
+diesel::joinable!(game_developers_table -> games_table (game_id));
+diesel::joinable!(game_developers_table -> developers_table (developer_id));
+diesel::joinable!(game_publishers_table -> games_table (game_id));
+diesel::joinable!(game_publishers_table -> publishers_table (publisher_id));
+diesel::joinable!(game_genres_table -> games_table (game_id));
+diesel::joinable!(game_genres_table -> genres_table (genre_id));
+diesel::joinable!(game_releases_table -> games_table (game_id));
+diesel::joinable!(game_details_table -> games_table (game_id));
@@ -0,0 +1,14 @@
+diesel::table! {
+    patched_roms (id) {
+        id -> Integer,
+        rom_id -> Integer,
+        patch_id -> Integer,
+        path -> Text,
+        size -> BigInt,
+        crc -> Text,
+        md5 -> Text,
+        sha1 -> Text,
+    }
+}
+
+pub use self::patched_roms::dsl::*;
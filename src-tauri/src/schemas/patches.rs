@@ -0,0 +1,11 @@
+diesel::table! {
+    patches (id) {
+        id -> Integer,
+        rom_id -> Integer,
+        path -> Text,
+        format -> Text,
+        md5 -> Text,
+    }
+}
+
+pub use self::patches::dsl::*;
@@ -0,0 +1,8 @@
+diesel::table! {
+    publishers (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+pub use self::publishers::dsl::*;
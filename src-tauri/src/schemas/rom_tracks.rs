@@ -0,0 +1,13 @@
+diesel::table! {
+    rom_tracks (id) {
+        id -> Integer,
+        rom_id -> Integer,
+        name -> Text,
+        size -> BigInt,
+        crc -> Text,
+        md5 -> Text,
+        sha1 -> Text,
+    }
+}
+
+pub use self::rom_tracks::dsl::*;
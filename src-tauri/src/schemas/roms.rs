@@ -3,8 +3,13 @@ diesel::table! {
         id -> Integer,
         title -> Text,
         md5 -> Text,
-        size -> Integer,
+        size -> BigInt,
         game_id -> Integer,
+        disc -> Nullable<Integer>,
+        crc -> Text,
+        sha1 -> Text,
+        category -> Text,
+        removed -> Bool,
     }
 }
 
@@ -0,0 +1,11 @@
+diesel::table! {
+    save_backups (id) {
+        id -> Integer,
+        save_id -> Integer,
+        path -> Text,
+        md5 -> Text,
+        created_at -> BigInt,
+    }
+}
+
+pub use self::save_backups::dsl::*;
@@ -0,0 +1,14 @@
+diesel::table! {
+    saves (id) {
+        id -> Integer,
+        path -> Text,
+        kind -> Text,
+        md5 -> Text,
+        size -> BigInt,
+        modified_at -> BigInt,
+        rom_id -> Nullable<Integer>,
+        game_id -> Nullable<Integer>,
+    }
+}
+
+pub use self::saves::dsl::*;
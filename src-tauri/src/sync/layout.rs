@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Console;
+
+/// OPL expects images up to the size of a CD in the `CD` folder, everything else in `DVD`
+const OPL_CD_MAX_SIZE: u64 = 700 * 1024 * 1024;
+
+/// Folder structure the synced files are written in on the target
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncLayout {
+    /// EmulationStation/Batocera style `<root>/<console abbreviation>/<file>`
+    Es,
+    /// Open PS2 Loader `<root>/CD/<file>` and `<root>/DVD/<file>`, only for PlayStation 2 games
+    Opl,
+    /// every file directly inside the root
+    Flat,
+}
+
+impl SyncLayout {
+    /// Returns the directory a file of the console is placed in, `None` if the layout
+    /// doesn't support the console
+    pub fn directory(&self, root: &Path, console: &Console, file_size: u64) -> Option<PathBuf> {
+        match self {
+            SyncLayout::Es => Some(root.join(&console.abbreviation)),
+            SyncLayout::Opl if console.abbreviation == "ps2" => {
+                if file_size <= OPL_CD_MAX_SIZE {
+                    Some(root.join("CD"))
+                } else {
+                    Some(root.join("DVD"))
+                }
+            }
+            SyncLayout::Opl => None,
+            SyncLayout::Flat => Some(root.to_path_buf()),
+        }
+    }
+
+    /// All directories the layout writes files of the console to, used for cleaning up
+    pub fn managed_directories(&self, root: &Path, console: &Console) -> Vec<PathBuf> {
+        match self {
+            SyncLayout::Es => vec![root.join(&console.abbreviation)],
+            SyncLayout::Opl if console.abbreviation == "ps2" => {
+                vec![root.join("CD"), root.join("DVD")]
+            }
+            SyncLayout::Opl => Vec::new(),
+            SyncLayout::Flat => vec![root.to_path_buf()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console(abbreviation: &str) -> Console {
+        Console {
+            id: 1,
+            name: abbreviation.to_string(),
+            abbreviation: abbreviation.to_string(),
+            manufacturer: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_directories() {
+        let root = Path::new("/media/sd");
+
+        assert_eq!(
+            Some(PathBuf::from("/media/sd/snes")),
+            SyncLayout::Es.directory(root, &console("snes"), 1024)
+        );
+        assert_eq!(
+            Some(PathBuf::from("/media/sd/CD")),
+            SyncLayout::Opl.directory(root, &console("ps2"), 600 * 1024 * 1024)
+        );
+        assert_eq!(
+            Some(PathBuf::from("/media/sd/DVD")),
+            SyncLayout::Opl.directory(root, &console("ps2"), 4 * 1024 * 1024 * 1024)
+        );
+        assert_eq!(
+            None,
+            SyncLayout::Opl.directory(root, &console("snes"), 1024)
+        );
+        assert_eq!(
+            Some(PathBuf::from("/media/sd")),
+            SyncLayout::Flat.directory(root, &console("snes"), 1024)
+        );
+    }
+}
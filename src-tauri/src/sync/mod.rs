@@ -0,0 +1,819 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cartridge::n64::{self, ByteOrder},
+    config::AppConfig,
+    exporters::{
+        m3u::{self, DiscSet, PlayableEntry},
+        target::{ExportTarget, PathIssue},
+    },
+    filters::Filter,
+    hashing::{hash_reader, md5_file},
+    models::{Console, GameWithRoms, RomTrack},
+    routes::{console_routes, disc_routes, games_routes},
+};
+
+pub mod layout;
+
+pub use layout::SyncLayout;
+
+/// Event name the sync progress is emitted under to the frontend
+pub const PROGRESS_EVENT: &str = "sync-progress";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SyncSelection {
+    pub console_ids: Vec<i32>,
+    /// restricts the sync to these games, all games of the consoles if empty
+    #[serde(default)]
+    pub game_ids: Vec<i32>,
+    /// case insensitive part of the game title
+    pub title_filter: Option<String>,
+    /// name of a saved filter ruleset the roms have to pass
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncRequest {
+    pub selection: SyncSelection,
+    pub root: PathBuf,
+    pub layout: SyncLayout,
+    /// removes library files from the target which are not part of the selection anymore
+    #[serde(default)]
+    pub delete_unselected: bool,
+    /// name of the export target in the config, used for file name and path limits
+    pub target: Option<String>,
+    /// converts N64 roms to this byte order on the way, renaming them to its extension,
+    /// for emulators and flashcarts which only read one order
+    pub n64_byte_order: Option<ByteOrder>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncActionKind {
+    Copy,
+    Update,
+    Unchanged,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SyncAction {
+    pub kind: SyncActionKind,
+    pub source: Option<PathBuf>,
+    pub target: PathBuf,
+    pub size: u64,
+    /// the N64 rom is copied converted to this byte order
+    pub byte_order: Option<ByteOrder>,
+}
+
+/// The difference between the selection and the target, returned as is for a dry run
+#[derive(Debug, Default, Serialize)]
+pub struct SyncPlan {
+    pub actions: Vec<SyncAction>,
+    /// selected entries which can't be synced, e.g. because the layout doesn't support the console
+    pub skipped: Vec<String>,
+    pub path_issues: Vec<PathIssue>,
+    /// m3u playlists for multi-disc games, written after all files are copied
+    pub playlists: Vec<SyncPlaylist>,
+}
+
+/// Playlist of the ES-DE/Batocera directory-as-game layout, `<set>.m3u/<set>.m3u`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SyncPlaylist {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgress {
+    pub current: usize,
+    pub total: usize,
+    pub kind: SyncActionKind,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub copied: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+    pub playlists: usize,
+    pub failed: Vec<String>,
+}
+
+impl SyncSelection {
+    pub fn matches(&self, game_roms: &GameWithRoms) -> bool {
+        let game = &game_roms.game;
+
+        (self.game_ids.is_empty() || self.game_ids.contains(&game.id))
+            && self
+                .title_filter
+                .as_ref()
+                .is_none_or(|filter| game.title.to_lowercase().contains(&filter.to_lowercase()))
+    }
+}
+
+/// Compares the selection with the target root and returns the needed actions
+pub fn plan(
+    conn: &mut SqliteConnection,
+    request: &SyncRequest,
+    config: &AppConfig,
+    target: Option<&ExportTarget>,
+) -> io::Result<SyncPlan> {
+    let mut plan = SyncPlan::default();
+    let mut selected_targets = HashSet::new();
+    // library file names per managed directory. Deletes are planned once all consoles are,
+    // as consoles share the root in the flat layout.
+    let mut managed_directories: BTreeMap<PathBuf, HashSet<String>> = BTreeMap::new();
+    let ruleset = config
+        .filter_ruleset(request.selection.filter.as_deref())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let consoles: Vec<Console> = console_routes::get_consoles(conn)
+        .into_iter()
+        .filter(|console| request.selection.console_ids.contains(&console.id))
+        .collect();
+
+    for console in consoles {
+        let rom_dir = match config.rom_paths.get(&console.abbreviation) {
+            Some(rom_path) if !rom_path.is_empty() => PathBuf::from(rom_path),
+            _ => {
+                plan.skipped
+                    .push(format!("No rom path configured for {}", console.name));
+                continue;
+            }
+        };
+
+        let games = games_routes::get_games_for_console(conn, &console.id);
+        // track files of the cue and gdi sheets, copied along with their sheet
+        let disc_tracks: HashMap<i32, Vec<RomTrack>> =
+            disc_routes::get_disc_roms_for_console(conn, console.id)
+                .map_err(io::Error::other)?
+                .into_iter()
+                .map(|disc| (disc.rom.id, disc.tracks))
+                .collect();
+        let filter = ruleset
+            .map(|ruleset| Filter::new(ruleset, Some(rom_dir.clone())))
+            .transpose()?;
+        let mut library_names = HashSet::new();
+        // with 1G1R only the picked releases count as selected, picked among the filtered roms
+        let picked_roms: Option<HashSet<i32>> = target
+            .and_then(|target| target.one_game_one_rom.as_ref())
+            .map(|rules| {
+                let candidates = match &filter {
+                    Some(filter) => filter.apply(games.clone()),
+                    None => games.clone(),
+                };
+                rules
+                    .apply(&candidates)
+                    .iter()
+                    .flat_map(|game_roms| &game_roms.roms)
+                    .map(|rom| rom.id)
+                    .collect()
+            });
+
+        for game_roms in &games {
+            let selected = request.selection.matches(game_roms);
+            let disc_sets: Vec<_> = match request.layout {
+                SyncLayout::Es => m3u::playable_entries(&game_roms.roms)
+                    .into_iter()
+                    .filter_map(|entry| match entry {
+                        PlayableEntry::DiscSet(set) => Some(set),
+                        PlayableEntry::Single(_) => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let playlist_name = |set: &DiscSet| match target {
+                Some(target) => target.sanitize_file_name(&set.playlist_file_name()),
+                None => set.playlist_file_name(),
+            };
+            for set in &disc_sets {
+                library_names.insert(playlist_name(set));
+            }
+
+            for rom in &game_roms.roms {
+                let title = target_title(&rom.title, request.n64_byte_order);
+                let file_name = match target {
+                    Some(target) => target.sanitize_file_name(&title),
+                    None => title,
+                };
+                library_names.insert(file_name.clone());
+                // track names are kept as they are, the sheet's FILE lines reference them
+                let tracks = disc_tracks
+                    .get(&rom.id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                library_names.extend(tracks.iter().map(|track| track.name.clone()));
+
+                let source = rom_dir.join(&rom.title);
+                let picked = picked_roms
+                    .as_ref()
+                    .is_none_or(|picked_roms| picked_roms.contains(&rom.id));
+                let passes = filter.as_ref().is_none_or(|filter| filter.matches(rom));
+                if !selected || !picked || !passes || !source.is_file() {
+                    continue;
+                }
+
+                let size = fs::metadata(&source)?.len();
+                let track_sources: Vec<(&RomTrack, PathBuf)> = tracks
+                    .iter()
+                    .map(|track| (track, rom_dir.join(&track.name)))
+                    .collect();
+                if let Some((track, _)) = track_sources.iter().find(|(_, path)| !path.is_file()) {
+                    plan.skipped
+                        .push(format!("{} is missing its track {}", rom.title, track.name));
+                    continue;
+                }
+                let disc_size = track_sources
+                    .iter()
+                    .map(|(_, path)| fs::metadata(path).map(|metadata| metadata.len()))
+                    .sum::<io::Result<u64>>()?
+                    + size;
+                let Some(directory) = request.layout.directory(&request.root, &console, disc_size)
+                else {
+                    plan.skipped.push(format!(
+                        "{} is not supported by the {:?} layout",
+                        rom.title, request.layout
+                    ));
+                    continue;
+                };
+
+                let disc_set = disc_sets
+                    .iter()
+                    .find(|set| set.discs.iter().any(|disc| disc.id == rom.id));
+                // the ES layout's `<set>.m3u/` directory, holding the playlist of that name
+                let directory = match disc_set {
+                    Some(set) => directory.join(playlist_name(set)),
+                    None => directory,
+                };
+
+                if let Some(set) = disc_set {
+                    let playlist = directory.join(playlist_name(set));
+                    if !plan.playlists.iter().any(|p| p.path == playlist) {
+                        let discs: Vec<String> = match target {
+                            Some(target) => set
+                                .disc_files()
+                                .iter()
+                                .map(|disc| target.sanitize_file_name(disc))
+                                .collect(),
+                            None => set.disc_files(),
+                        };
+                        plan.playlists.push(SyncPlaylist {
+                            path: playlist,
+                            contents: m3u::render(&discs),
+                        });
+                    }
+                }
+
+                let target_path = directory.join(&file_name);
+                if let Some(target) = target {
+                    plan.path_issues
+                        .extend(target.check_path(&target_path.to_string_lossy()));
+                }
+
+                let byte_order = match request.n64_byte_order {
+                    Some(order) if is_n64_rom(&rom.title) => n64::detect_file(&source)?
+                        .filter(|stored| *stored != order)
+                        .map(|_| order),
+                    _ => None,
+                };
+
+                selected_targets.insert(target_path.clone());
+                plan.actions
+                    .push(copy_action(source, target_path, size, byte_order)?);
+
+                for (track, track_source) in track_sources {
+                    let track_path = directory.join(&track.name);
+                    if let Some(target) = target {
+                        plan.path_issues
+                            .extend(target.check_path(&track_path.to_string_lossy()));
+                    }
+
+                    let track_size = fs::metadata(&track_source)?.len();
+                    selected_targets.insert(track_path.clone());
+                    plan.actions
+                        .push(copy_action(track_source, track_path, track_size, None)?);
+                }
+            }
+        }
+
+        for directory in request.layout.managed_directories(&request.root, &console) {
+            managed_directories
+                .entry(directory)
+                .or_default()
+                .extend(library_names.iter().cloned());
+        }
+    }
+
+    if request.delete_unselected {
+        let playlists: HashSet<PathBuf> = plan
+            .playlists
+            .iter()
+            .map(|playlist| playlist.path.clone())
+            .collect();
+
+        for (directory, library_names) in &managed_directories {
+            plan.actions.extend(unselected_files(
+                directory,
+                library_names,
+                &selected_targets,
+                &playlists,
+            )?);
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Copies the source to the target, updates it if it differs or leaves it if it's the same
+fn copy_action(
+    source: PathBuf,
+    target: PathBuf,
+    size: u64,
+    byte_order: Option<ByteOrder>,
+) -> io::Result<SyncAction> {
+    let kind = if !target.exists() {
+        SyncActionKind::Copy
+    } else if is_same_file(&source, &target, byte_order)? {
+        SyncActionKind::Unchanged
+    } else {
+        SyncActionKind::Update
+    };
+
+    Ok(SyncAction {
+        kind,
+        source: Some(source),
+        target,
+        size,
+        byte_order,
+    })
+}
+
+fn delete_action(target: PathBuf, size: u64) -> SyncAction {
+    SyncAction {
+        kind: SyncActionKind::Delete,
+        source: None,
+        target,
+        size,
+        byte_order: None,
+    }
+}
+
+/// Files inside the directory which belong to the library but are not selected anymore,
+/// including those of the `<set>.m3u/` directories of disc sets.
+/// Anything romana doesn't know about, like gamelists or media, is left alone.
+fn unselected_files(
+    directory: &Path,
+    library_names: &HashSet<String>,
+    selected_targets: &HashSet<PathBuf>,
+    playlists: &HashSet<PathBuf>,
+) -> io::Result<Vec<SyncAction>> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut actions = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_library_file = library_names.contains(&name);
+        let file_type = entry.file_type()?;
+
+        if file_type.is_file() && is_library_file && !selected_targets.contains(&path) {
+            actions.push(delete_action(path, entry.metadata()?.len()));
+        } else if file_type.is_dir() && is_library_file && name.ends_with(".m3u") {
+            actions.extend(unselected_set_files(
+                &path,
+                &name,
+                library_names,
+                selected_targets,
+                playlists,
+            )?);
+        }
+    }
+
+    Ok(actions)
+}
+
+/// The unselected discs of a disc set directory, its playlist if none is written for it
+/// anymore, and the directory itself if nothing else is left in it
+fn unselected_set_files(
+    directory: &Path,
+    playlist_name: &str,
+    library_names: &HashSet<String>,
+    selected_targets: &HashSet<PathBuf>,
+    playlists: &HashSet<PathBuf>,
+) -> io::Result<Vec<SyncAction>> {
+    let mut actions = Vec::new();
+    let mut kept = 0;
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        let unselected = if name == playlist_name {
+            !playlists.contains(&path)
+        } else {
+            entry.file_type()?.is_file()
+                && library_names.contains(&name)
+                && !selected_targets.contains(&path)
+        };
+
+        if unselected {
+            actions.push(delete_action(path, entry.metadata()?.len()));
+        } else {
+            kept += 1;
+        }
+    }
+
+    if kept == 0 {
+        actions.push(delete_action(directory.to_path_buf(), 0));
+    }
+
+    Ok(actions)
+}
+
+fn is_n64_rom(title: &str) -> bool {
+    Path::new(title)
+        .extension()
+        .and_then(|extension| ByteOrder::from_extension(&extension.to_string_lossy()))
+        .is_some()
+}
+
+/// The rom's file name on the target, N64 roms take the extension of the requested byte order
+fn target_title(title: &str, n64_byte_order: Option<ByteOrder>) -> String {
+    match n64_byte_order {
+        Some(order) if is_n64_rom(title) => Path::new(title)
+            .with_extension(order.extension())
+            .to_string_lossy()
+            .to_string(),
+        _ => title.to_string(),
+    }
+}
+
+/// The md5 of the source as it ends up on the target
+fn source_md5(source: &Path, byte_order: Option<ByteOrder>) -> io::Result<String> {
+    match byte_order {
+        Some(order) => Ok(hash_reader(&mut n64::open_as(source, order)?)?.md5),
+        None => md5_file(source),
+    }
+}
+
+fn is_same_file(source: &Path, target: &Path, byte_order: Option<ByteOrder>) -> io::Result<bool> {
+    if fs::metadata(source)?.len() != fs::metadata(target)?.len() {
+        return Ok(false);
+    }
+
+    Ok(source_md5(source, byte_order)? == md5_file(target)?)
+}
+
+/// Copies the file to a temporary name next to the target, verifies it and moves it in place
+fn copy_verified(source: &Path, target: &Path, byte_order: Option<ByteOrder>) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    match byte_order {
+        Some(order) => {
+            let mut writer = BufWriter::new(File::create(&partial)?);
+            io::copy(&mut n64::open_as(source, order)?, &mut writer)?;
+            writer.flush()?;
+        }
+        None => {
+            fs::copy(source, &partial)?;
+        }
+    }
+
+    if source_md5(source, byte_order)? != md5_file(&partial)? {
+        fs::remove_file(&partial)?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("hash mismatch after copying {}", source.display()),
+        ));
+    }
+
+    fs::rename(&partial, target)
+}
+
+/// Applies the plan, reporting each processed action to `on_progress`
+pub fn execute(plan: &SyncPlan, mut on_progress: impl FnMut(SyncProgress)) -> SyncReport {
+    let mut report = SyncReport::default();
+    let total = plan.actions.len();
+
+    for (index, action) in plan.actions.iter().enumerate() {
+        let result = match (action.kind, &action.source) {
+            (SyncActionKind::Copy | SyncActionKind::Update, Some(source)) => {
+                copy_verified(source, &action.target, action.byte_order)
+            }
+            // disc set directories are planned after their files
+            (SyncActionKind::Delete, _) if action.target.is_dir() => fs::remove_dir(&action.target),
+            (SyncActionKind::Delete, _) => fs::remove_file(&action.target),
+            _ => Ok(()),
+        };
+
+        match result {
+            Ok(()) => match action.kind {
+                SyncActionKind::Copy => report.copied += 1,
+                SyncActionKind::Update => report.updated += 1,
+                SyncActionKind::Unchanged => report.unchanged += 1,
+                SyncActionKind::Delete => report.deleted += 1,
+            },
+            Err(e) => report
+                .failed
+                .push(format!("{}: {}", action.target.display(), e)),
+        }
+
+        on_progress(SyncProgress {
+            current: index + 1,
+            total,
+            kind: action.kind,
+            path: action.target.clone(),
+        });
+    }
+
+    for playlist in &plan.playlists {
+        match fs::write(&playlist.path, &playlist.contents) {
+            Ok(()) => report.playlists += 1,
+            Err(e) => report
+                .failed
+                .push(format!("{}: {}", playlist.path.display(), e)),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("romana_sync_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_execute_copies_and_deletes() {
+        let dir = temp_dir("execute");
+        let source = dir.join("source.sfc");
+        let stale = dir.join("target/stale.sfc");
+        fs::write(&source, b"rom data").unwrap();
+        fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        fs::write(&stale, b"old").unwrap();
+
+        let plan = SyncPlan {
+            actions: vec![
+                SyncAction {
+                    kind: SyncActionKind::Copy,
+                    source: Some(source.clone()),
+                    target: dir.join("target/snes/source.sfc"),
+                    size: 8,
+                    byte_order: None,
+                },
+                SyncAction {
+                    kind: SyncActionKind::Delete,
+                    source: None,
+                    target: stale.clone(),
+                    size: 3,
+                    byte_order: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut progress = Vec::new();
+        let report = execute(&plan, |p| progress.push(p.current));
+
+        assert_eq!((1, 1), (report.copied, report.deleted));
+        assert!(report.failed.is_empty());
+        assert_eq!(vec![1, 2], progress);
+        assert_eq!(
+            b"rom data".to_vec(),
+            fs::read(dir.join("target/snes/source.sfc")).unwrap()
+        );
+        assert!(!stale.exists());
+        assert!(is_same_file(&source, &dir.join("target/snes/source.sfc"), None).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unselected_files() {
+        let dir = temp_dir("unselected");
+        fs::write(dir.join("kept.sfc"), b"").unwrap();
+        fs::write(dir.join("removed.sfc"), b"").unwrap();
+        fs::write(dir.join("gamelist.xml"), b"").unwrap();
+
+        let library_names = HashSet::from(["kept.sfc".to_string(), "removed.sfc".to_string()]);
+        let selected_targets = HashSet::from([dir.join("kept.sfc")]);
+
+        let actions =
+            unselected_files(&dir, &library_names, &selected_targets, &HashSet::new()).unwrap();
+
+        assert_eq!(1, actions.len());
+        assert_eq!(dir.join("removed.sfc"), actions[0].target);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unselected_disc_sets() {
+        let dir = temp_dir("unselected_sets");
+        let removed = dir.join("Game (USA).m3u");
+        let kept = dir.join("Other (USA).m3u");
+        for set in [&removed, &kept] {
+            fs::create_dir_all(set).unwrap();
+        }
+        fs::write(removed.join("Game (USA).m3u"), b"").unwrap();
+        fs::write(removed.join("Game (USA) (Disc 1).chd"), b"").unwrap();
+        fs::write(kept.join("Other (USA).m3u"), b"").unwrap();
+        fs::write(kept.join("Other (USA) (Disc 1).chd"), b"").unwrap();
+        fs::write(kept.join("Other (USA) (Disc 2).chd"), b"").unwrap();
+
+        let library_names = HashSet::from([
+            "Game (USA).m3u".to_string(),
+            "Game (USA) (Disc 1).chd".to_string(),
+            "Other (USA).m3u".to_string(),
+            "Other (USA) (Disc 1).chd".to_string(),
+            "Other (USA) (Disc 2).chd".to_string(),
+        ]);
+        let selected_targets = HashSet::from([kept.join("Other (USA) (Disc 1).chd")]);
+        let playlists = HashSet::from([kept.join("Other (USA).m3u")]);
+
+        let mut actions =
+            unselected_files(&dir, &library_names, &selected_targets, &playlists).unwrap();
+        // the directory of a set is deleted after its files
+        assert_eq!(
+            removed,
+            actions
+                .iter()
+                .rev()
+                .find(|a| a.target.starts_with(&removed))
+                .unwrap()
+                .target
+        );
+        actions.sort_by(|a, b| a.target.cmp(&b.target));
+
+        assert_eq!(
+            vec![
+                removed.clone(),
+                removed.join("Game (USA) (Disc 1).chd"),
+                removed.join("Game (USA).m3u"),
+                kept.join("Other (USA) (Disc 2).chd"),
+            ],
+            actions.into_iter().map(|a| a.target).collect::<Vec<_>>()
+        );
+
+        let report = execute(
+            &SyncPlan {
+                actions: unselected_files(&dir, &library_names, &selected_targets, &playlists)
+                    .unwrap(),
+                ..Default::default()
+            },
+            |_| (),
+        );
+        assert!(report.failed.is_empty());
+        assert!(!removed.exists());
+        assert!(kept.join("Other (USA).m3u").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_plan_copies_disc_tracks() {
+        let dir = temp_dir("disc_tracks");
+        let rom_dir = dir.join("roms");
+        let root = dir.join("target");
+        fs::create_dir_all(&rom_dir).unwrap();
+        fs::create_dir_all(root.join("psx")).unwrap();
+        fs::write(
+            rom_dir.join("Game (USA).cue"),
+            b"FILE \"Game (USA) (Track 1).bin\" BINARY",
+        )
+        .unwrap();
+        fs::write(rom_dir.join("Game (USA) (Track 1).bin"), b"data").unwrap();
+        fs::write(rom_dir.join("Game (USA) (Track 2).bin"), b"audio").unwrap();
+        // a track of the synced disc, already on the target, and one of an unselected disc
+        fs::write(root.join("psx/Game (USA) (Track 2).bin"), b"audio").unwrap();
+        fs::write(root.join("psx/Other (USA) (Track 1).bin"), b"old").unwrap();
+
+        let conn = &mut crate::db::test_connection();
+        conn.batch_execute(
+            "INSERT INTO games (id, title, console_id)
+                 SELECT 1, 'Game (USA)', id FROM consoles WHERE abbreviation = 'psx';
+             INSERT INTO games (id, title, console_id)
+                 SELECT 2, 'Other (USA)', id FROM consoles WHERE abbreviation = 'psx';
+             INSERT INTO roms (id, title, md5, size, game_id) VALUES
+                 (1, 'Game (USA).cue', '', 0, 1),
+                 (2, 'Other (USA).cue', '', 0, 2);
+             INSERT INTO rom_tracks (rom_id, name, size, crc, md5, sha1) VALUES
+                 (1, 'Game (USA) (Track 1).bin', 4, '', '', ''),
+                 (1, 'Game (USA) (Track 2).bin', 5, '', '', ''),
+                 (2, 'Other (USA) (Track 1).bin', 3, '', '', '');",
+        )
+        .unwrap();
+        let console = console_routes::get_consoles(conn)
+            .into_iter()
+            .find(|console| console.abbreviation == "psx")
+            .unwrap();
+        let config = AppConfig {
+            rom_paths: [("psx".to_string(), rom_dir.to_string_lossy().to_string())].into(),
+            ..Default::default()
+        };
+        let request = SyncRequest {
+            selection: SyncSelection {
+                console_ids: vec![console.id],
+                ..Default::default()
+            },
+            root: root.clone(),
+            layout: SyncLayout::Es,
+            delete_unselected: true,
+            target: None,
+            n64_byte_order: None,
+        };
+
+        let sync_plan = plan(conn, &request, &config, None).unwrap();
+        let actions: Vec<(SyncActionKind, PathBuf)> = sync_plan
+            .actions
+            .iter()
+            .map(|action| (action.kind, action.target.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (SyncActionKind::Copy, root.join("psx/Game (USA).cue")),
+                (
+                    SyncActionKind::Copy,
+                    root.join("psx/Game (USA) (Track 1).bin")
+                ),
+                (
+                    SyncActionKind::Unchanged,
+                    root.join("psx/Game (USA) (Track 2).bin")
+                ),
+                (
+                    SyncActionKind::Delete,
+                    root.join("psx/Other (USA) (Track 1).bin")
+                ),
+            ],
+            actions
+        );
+
+        let report = execute(&sync_plan, |_| ());
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            b"data".to_vec(),
+            fs::read(root.join("psx/Game (USA) (Track 1).bin")).unwrap()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_converts_n64_roms() {
+        let dir = temp_dir("n64");
+        let source = dir.join("Game (USA).z64");
+        let mut data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+        data[..4].copy_from_slice(&[0x80, 0x37, 0x12, 0x40]);
+        fs::write(&source, &data).unwrap();
+
+        assert_eq!(
+            "Game (USA).v64",
+            target_title("Game (USA).z64", Some(ByteOrder::ByteSwapped))
+        );
+        assert_eq!(
+            "Game (USA).sfc",
+            target_title("Game (USA).sfc", Some(ByteOrder::ByteSwapped))
+        );
+        assert_eq!("Game (USA).z64", target_title("Game (USA).z64", None));
+
+        let target = dir.join("target/Game (USA).v64");
+        copy_verified(&source, &target, Some(ByteOrder::ByteSwapped)).unwrap();
+
+        ByteOrder::ByteSwapped.swap(&mut data);
+        assert_eq!(data, fs::read(&target).unwrap());
+        assert!(is_same_file(&source, &target, Some(ByteOrder::ByteSwapped)).unwrap());
+        assert!(!is_same_file(&source, &target, None).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}